@@ -0,0 +1,81 @@
+use std::{io::Write, path::PathBuf};
+
+use crate::TestcaseProperty;
+
+/// Artifacts are the request and response bodies of failed testcases. They are written to their
+/// own files so the report only needs to reference them, no matter how large the bodies are.
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    pub directory: PathBuf,
+}
+
+impl Artifacts {
+    /// Writes the bodies to files named after the correlation ID and returns a property with the
+    /// path of each file. A number is added to the name when an earlier testcase with the same
+    /// correlation ID already has artifacts, so they are never overwritten.
+    pub fn write(
+        &self,
+        correlation_id: &str,
+        request_body: &[u8],
+        response_body: Option<&[u8]>,
+    ) -> Vec<TestcaseProperty> {
+        let mut properties = vec![];
+        let stem = file_stem(correlation_id);
+        // Creating the request file reserves the name, even when requests with the same
+        // correlation ID fail at the same time
+        let (request_path, mut request_file) = match (1..)
+            .map(|attempt| match attempt {
+                1 => self.directory.join(format!("{}.request", stem)),
+                _ => self.directory.join(format!("{}-{}.request", stem, attempt)),
+            })
+            .map(|path| {
+                let file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path);
+                (path, file)
+            })
+            .find(|(_, file)| {
+                !matches!(file, Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists)
+            }) {
+            Some((path, Ok(file))) => (path, file),
+            Some((path, Err(err))) => {
+                tracing::error!(?path, %err, "Failed to write request artifact");
+                return properties;
+            }
+            None => return properties,
+        };
+        if let Err(err) = request_file.write_all(request_body) {
+            tracing::error!(path = ?request_path, %err, "Failed to write request artifact");
+            return properties;
+        }
+        properties.push(TestcaseProperty {
+            name: "requestBodyArtifact".to_string(),
+            value: request_path.display().to_string(),
+        });
+        if let Some(response_body) = response_body {
+            let response_path = request_path.with_extension("response");
+            match std::fs::write(&response_path, response_body) {
+                Ok(()) => properties.push(TestcaseProperty {
+                    name: "responseBodyArtifact".to_string(),
+                    value: response_path.display().to_string(),
+                }),
+                Err(err) => {
+                    tracing::error!(path = ?response_path, %err, "Failed to write response artifact")
+                }
+            }
+        }
+        properties
+    }
+}
+
+/// Replaces the characters of a correlation ID that aren't safe in a file name.
+fn file_stem(correlation_id: &str) -> String {
+    correlation_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
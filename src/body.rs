@@ -0,0 +1,97 @@
+use axum::body::{Body, Bytes};
+use futures_util::StreamExt;
+use http_body_util::BodyExt;
+use tokio::sync::oneshot::{Receiver, Sender};
+
+/// How many bytes of request and response bodies are buffered so they can be validated and
+/// recorded. Bodies that are larger are streamed through the proxy instead, so they don't have to
+/// fit in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub request: usize,
+    pub response: usize,
+}
+
+/// A body that was read into the validation buffer.
+pub enum Buffered {
+    /// The whole body.
+    Complete(Bytes),
+    /// A body that didn't fit in the buffer. It replays the chunks that were already read and then
+    /// streams the rest of the original body.
+    Streaming(Body),
+}
+
+/// Reads a body until it ends or more than `limit` bytes have been read. Trailers are dropped.
+pub async fn buffer(mut body: Body, limit: usize) -> Result<Buffered, axum::Error> {
+    let mut chunks = vec![];
+    let mut length = 0;
+    while let Some(frame) = body.frame().await {
+        let data = match frame?.into_data() {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        length += data.len();
+        chunks.push(data);
+        if length > limit {
+            let read = futures_util::stream::iter(chunks.into_iter().map(Ok));
+            return Ok(Buffered::Streaming(Body::from_stream(
+                read.chain(body.into_data_stream()),
+            )));
+        }
+    }
+    Ok(Buffered::Complete(chunks.concat().into()))
+}
+
+/// Forwards a body as it arrives while a copy of it is buffered for validation. The copy is sent
+/// to `buffered` once the body ends, or None if it was larger than `limit`. `buffered` is dropped
+/// without a copy if reading the body fails.
+///
+/// The end of the forwarded body is held back until `validated` is sent or dropped, so the
+/// exchange has been recorded by the time the client has read the whole body.
+pub fn tee(body: Body, limit: usize) -> (Body, Receiver<Option<Bytes>>, Sender<()>) {
+    let (buffered_sender, buffered) = tokio::sync::oneshot::channel();
+    let (validated, validated_receiver) = tokio::sync::oneshot::channel();
+    let state = Tee {
+        body: body.into_data_stream(),
+        chunks: vec![],
+        length: 0,
+        buffered: Some(buffered_sender),
+        validated: Some(validated_receiver),
+    };
+    let stream = futures_util::stream::unfold(state, move |mut state| async move {
+        match state.body.next().await {
+            Some(Ok(chunk)) => {
+                state.length += chunk.len();
+                if state.length > limit {
+                    state.chunks.clear();
+                } else {
+                    state.chunks.push(chunk.clone());
+                }
+                Some((Ok(chunk), state))
+            }
+            Some(Err(err)) => {
+                state.buffered.take();
+                Some((Err(err), state))
+            }
+            None => {
+                let copy = (state.length <= limit).then(|| state.chunks.concat().into());
+                if let Some(buffered) = state.buffered.take() {
+                    let _ = buffered.send(copy);
+                }
+                if let Some(validated) = state.validated.take() {
+                    let _ = validated.await;
+                }
+                None
+            }
+        }
+    });
+    (Body::from_stream(stream), buffered, validated)
+}
+
+struct Tee {
+    body: axum::body::BodyDataStream,
+    chunks: Vec<Bytes>,
+    length: usize,
+    buffered: Option<Sender<Option<Bytes>>>,
+    validated: Option<Receiver<()>>,
+}
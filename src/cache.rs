@@ -0,0 +1,107 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+};
+
+use crate::TestcaseFailure;
+
+/// Identifies a body that was validated against a part of the spec. Only a hash of the body is
+/// kept so that large payloads aren't held in memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    scope: String,
+    content_type: String,
+    body_hash: u64,
+    body_len: usize,
+}
+
+impl CacheKey {
+    /// `scope` identifies what the body was validated against, like the operation and status code
+    /// of a response. Bodies are only the same if they were validated against the same content.
+    pub fn new(scope: String, content_type: &str, body: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        CacheKey {
+            scope,
+            content_type: content_type.to_string(),
+            body_hash: hasher.finish(),
+            body_len: body.len(),
+        }
+    }
+}
+
+/// Remembers the failures found in bodies that were validated before. Polling and retrying clients
+/// send the same payloads over and over, and validating a large body against its schema is the
+/// most expensive part of handling a request.
+#[derive(Debug)]
+pub struct ValidationCache {
+    capacity: usize,
+    inner: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    failures: HashMap<CacheKey, Vec<TestcaseFailure>>,
+    /// Keys in the order they were inserted. The oldest one is evicted first.
+    order: VecDeque<CacheKey>,
+    hits: u64,
+    misses: u64,
+}
+
+impl ValidationCache {
+    /// A capacity of 0 disables the cache.
+    pub fn new(capacity: usize) -> Self {
+        ValidationCache {
+            capacity,
+            inner: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Returns the failures found in the body the last time it was validated, or validates it with
+    /// `validate` if it hasn't been seen yet.
+    pub fn get_or_validate(
+        &self,
+        key: CacheKey,
+        validate: impl FnOnce() -> Vec<TestcaseFailure>,
+    ) -> Vec<TestcaseFailure> {
+        if self.capacity == 0 {
+            return validate();
+        }
+        let mut entries = self.inner.lock().unwrap();
+        if let Some(failures) = entries.failures.get(&key) {
+            let failures = failures.clone();
+            entries.hits += 1;
+            return failures;
+        }
+        // Other requests can keep using the cache while this body is validated
+        drop(entries);
+        let failures = validate();
+        let mut entries = self.inner.lock().unwrap();
+        entries.misses += 1;
+        if !entries.failures.contains_key(&key) {
+            entries.failures.insert(key.clone(), failures.clone());
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.failures.remove(&oldest);
+            }
+        }
+        failures
+    }
+
+    /// Forgets every validated body, for when the spec they were validated against changes.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.failures.clear();
+        inner.order.clear();
+    }
+
+    /// The number of bodies whose failures were found in the cache, and the number that had to be
+    /// validated.
+    pub fn stats(&self) -> (u64, u64) {
+        let entries = self.inner.lock().unwrap();
+        (entries.hits, entries.misses)
+    }
+}
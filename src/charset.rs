@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+/// Returns the media type of a Content-Type header without its parameters, like
+/// `application/json` for `application/json; charset=utf-8`.
+pub fn essence(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or_default().trim()
+}
+
+/// Returns the name and value of every parameter of a Content-Type header, with the quotes
+/// around quoted values removed.
+pub fn parameters(content_type: &str) -> impl Iterator<Item = (&str, &str)> {
+    content_type.split(';').skip(1).filter_map(|parameter| {
+        let (name, value) = parameter.split_once('=')?;
+        Some((name.trim(), value.trim().trim_matches('"')))
+    })
+}
+
+/// Returns the charset parameter of a Content-Type header, if it has one.
+pub fn charset(content_type: &str) -> Option<&str> {
+    parameters(content_type)
+        .find(|(name, _)| name.eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value)
+}
+
+/// Returns true if the Content-Type header is for a JSON body. Media types are case-insensitive,
+/// so `Application/JSON; charset=UTF-8` is JSON too.
+pub fn is_json(content_type: &str) -> bool {
+    essence(content_type).eq_ignore_ascii_case("application/json")
+}
+
+/// Returns true if the Content-Type header is for a newline delimited JSON body, where every line
+/// is a JSON document.
+pub fn is_ndjson(content_type: &str) -> bool {
+    ["application/x-ndjson", "application/jsonl"]
+        .iter()
+        .any(|media_type| essence(content_type).eq_ignore_ascii_case(media_type))
+}
+
+/// Transcodes a body to UTF-8 using the charset from its Content-Type header. Bodies without a
+/// charset are returned unchanged. Returns an error describing the problem if the charset is not
+/// supported or the body is not valid in that charset.
+pub fn decode<'a>(body: &'a [u8], content_type: &str) -> Result<Cow<'a, [u8]>, String> {
+    let label = match charset(content_type) {
+        Some(label) => label,
+        None => return Ok(Cow::Borrowed(body)),
+    };
+    let encoding = encoding_rs::Encoding::for_label(label.as_bytes())
+        .ok_or_else(|| format!("Unsupported charset {}", label))?;
+    if encoding == encoding_rs::UTF_8 {
+        return Ok(Cow::Borrowed(body));
+    }
+    match encoding.decode_without_bom_handling_and_without_replacement(body) {
+        Some(decoded) => Ok(Cow::Owned(decoded.into_owned().into_bytes())),
+        None => Err(format!("Body is not valid {}", encoding.name())),
+    }
+}
@@ -0,0 +1,468 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use openapiv3::{ParameterSchemaOrContent, ReferenceOr};
+
+use crate::{resolve_parameter, JsonChecks, TestcaseFailureType, ValidationPerspective};
+
+/// The kinds of problems in a spec that would surprise the proxy once traffic flows through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintType {
+    /// A variable in the path template has no matching `in: path` parameter.
+    MissingPathParameter,
+    /// An `in: path` parameter does not appear in the path template.
+    UnusedPathParameter,
+    /// The path template can't be added to the router, so requests will never match it.
+    UnroutablePath,
+    /// The path template only differs from another one by the names of its variables, so every
+    /// request that matches one of them is routed to the same operation.
+    AmbiguousPath,
+    /// An example in the spec does not match the schema it is an example of.
+    InvalidExample,
+}
+
+impl std::fmt::Display for LintType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LintType::MissingPathParameter => write!(f, "MissingPathParameter"),
+            LintType::UnusedPathParameter => write!(f, "UnusedPathParameter"),
+            LintType::UnroutablePath => write!(f, "UnroutablePath"),
+            LintType::AmbiguousPath => write!(f, "AmbiguousPath"),
+            LintType::InvalidExample => write!(f, "InvalidExample"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub text: String,
+    pub r#type: LintType,
+}
+
+impl std::fmt::Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.r#type, self.text)
+    }
+}
+
+/// Checks that the path templates in the spec can be routed and agree with the path parameters
+/// of their operations, and that the examples in the spec match their schemas.
+pub fn check_spec(spec: &openapiv3::OpenAPI) -> Vec<Lint> {
+    let mut lints = vec![];
+    let mut wayfinder = wayfind::Router::new();
+    let mut routes: BTreeMap<String, &str> = BTreeMap::new();
+    for (path_template, path_item) in spec.paths.paths.iter() {
+        lints.extend(route_lints(path_template, &mut wayfinder, &mut routes));
+        let template_variables = template_variables(path_template);
+        let path_item = match path_item.as_item() {
+            Some(path_item) => path_item,
+            None => continue,
+        };
+        for (method, operation) in path_item.iter() {
+            let operation_name = format!("{} {}", method.to_uppercase(), path_template);
+            // Parameters on the operation are added to the ones shared by the whole path
+            let path_parameters = path_item
+                .parameters
+                .iter()
+                .chain(operation.parameters.iter())
+                .filter_map(|parameter| resolve_parameter(parameter, spec))
+                .filter_map(|parameter| match parameter {
+                    openapiv3::Parameter::Path { parameter_data, .. } => {
+                        Some(parameter_data.name.as_str())
+                    }
+                    _ => None,
+                })
+                .collect::<BTreeSet<&str>>();
+            for variable in template_variables.iter() {
+                if !path_parameters.contains(variable) {
+                    lints.push(Lint {
+                        text: format!(
+                            "{} has no path parameter for {{{}}}",
+                            operation_name, variable
+                        ),
+                        r#type: LintType::MissingPathParameter,
+                    });
+                }
+            }
+            for parameter in path_parameters.iter() {
+                if !template_variables.contains(parameter) {
+                    lints.push(Lint {
+                        text: format!(
+                            "{} declares path parameter {} that is not in the path",
+                            operation_name, parameter
+                        ),
+                        r#type: LintType::UnusedPathParameter,
+                    });
+                }
+            }
+        }
+    }
+    lints.extend(check_examples(spec, &JsonChecks::default()));
+    lints
+}
+
+/// Checks that every path template in the spec can be routed and matches different requests than
+/// the others. Each lint is returned with the path template it is about.
+pub fn check_path_templates(spec: &openapiv3::OpenAPI) -> Vec<(&str, Lint)> {
+    let mut wayfinder = wayfind::Router::new();
+    let mut routes: BTreeMap<String, &str> = BTreeMap::new();
+    spec.paths
+        .paths
+        .keys()
+        .flat_map(|path_template| {
+            route_lints(path_template, &mut wayfinder, &mut routes)
+                .into_iter()
+                .map(move |lint| (path_template.as_str(), lint))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Adds the path template to the router and reports it when it can't be routed or when a
+/// template that was added before it matches the same requests.
+fn route_lints<'a>(
+    path_template: &'a str,
+    wayfinder: &mut wayfind::Router<()>,
+    routes: &mut BTreeMap<String, &'a str>,
+) -> Vec<Lint> {
+    let mut lints = vec![];
+    if let Err(err) = wayfinder.insert(path_template, ()) {
+        // wayfind's errors span several lines, the first one describes the problem
+        let err = err.to_string();
+        lints.push(Lint {
+            text: format!(
+                "{} can't be routed: {}",
+                path_template,
+                err.lines().next().unwrap_or_default()
+            ),
+            r#type: LintType::UnroutablePath,
+        });
+    }
+    let route = template_variables(path_template)
+        .iter()
+        .fold(path_template.to_string(), |route, variable| {
+            route.replace(&format!("{{{}}}", variable), "{}")
+        });
+    match routes.get(&route) {
+        Some(other_template) => lints.push(Lint {
+            text: format!(
+                "{} matches the same requests as {}",
+                path_template, other_template
+            ),
+            r#type: LintType::AmbiguousPath,
+        }),
+        None => {
+            routes.insert(route, path_template);
+        }
+    }
+    lints
+}
+
+/// An example from the spec and the schema it is an example of.
+struct Example<'a> {
+    /// Where the example is in the spec, like `GET /pets response 200 application/json example`.
+    location: String,
+    value: &'a serde_json::Value,
+    schema: &'a openapiv3::Schema,
+    /// Whether the example is sent in requests or responses. Examples of component schemas are
+    /// neither, so they may contain both readOnly and writeOnly properties.
+    perspective: Option<ValidationPerspective>,
+}
+
+/// Validates every example in the spec against its schema, the same way bodies and parameters are
+/// validated when traffic flows through the proxy. Examples of request bodies, responses, response
+/// headers, parameters, and component schemas are checked. Examples that only have an
+/// externalValue are skipped.
+pub fn check_examples(spec: &openapiv3::OpenAPI, json_checks: &JsonChecks) -> Vec<Lint> {
+    let mut examples = vec![];
+    for (path_template, path_item) in spec.paths.paths.iter() {
+        let path_item = match path_item.as_item() {
+            Some(path_item) => path_item,
+            None => continue,
+        };
+        parameter_examples(path_template, &path_item.parameters, spec, &mut examples);
+        for (method, operation) in path_item.iter() {
+            let operation_name = format!("{} {}", method.to_uppercase(), path_template);
+            parameter_examples(&operation_name, &operation.parameters, spec, &mut examples);
+            if let Some(request_body) = operation
+                .request_body
+                .as_ref()
+                .and_then(|request_body| crate::resolve_request_body(request_body, spec))
+            {
+                content_examples(
+                    &format!("{} request body", operation_name),
+                    &request_body.content,
+                    spec,
+                    ValidationPerspective::Request,
+                    &mut examples,
+                );
+            }
+            let responses = operation
+                .responses
+                .responses
+                .iter()
+                .map(|(status_code, response)| (status_code.to_string(), response))
+                .chain(
+                    operation
+                        .responses
+                        .default
+                        .iter()
+                        .map(|response| ("default".to_string(), response)),
+                );
+            for (status_code, response) in responses {
+                let response = match crate::resolve_response(response, spec) {
+                    Some(response) => response,
+                    None => continue,
+                };
+                let location = format!("{} response {}", operation_name, status_code);
+                content_examples(
+                    &location,
+                    &response.content,
+                    spec,
+                    ValidationPerspective::Response,
+                    &mut examples,
+                );
+                for (name, header) in response.headers.iter() {
+                    if let Some(header) = header.as_item() {
+                        format_examples(
+                            &format!("{} header {}", location, name),
+                            &header.format,
+                            header.example.as_ref(),
+                            header.examples.iter(),
+                            spec,
+                            ValidationPerspective::Response,
+                            &mut examples,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    if let Some(components) = spec.components.as_ref() {
+        for (name, schema) in components.schemas.iter() {
+            if let Some(schema) = schema.as_item() {
+                schema_examples(
+                    format!("#/components/schemas/{}", name),
+                    schema,
+                    &mut examples,
+                );
+            }
+        }
+    }
+
+    let mut lints = vec![];
+    for example in examples {
+        let failures = crate::validate_schema(
+            example.value,
+            example.schema,
+            spec,
+            "/".to_string(),
+            example
+                .perspective
+                .unwrap_or(ValidationPerspective::Response),
+            json_checks,
+        );
+        for failure in failures {
+            if example.perspective.is_none()
+                && matches!(
+                    failure.r#type,
+                    TestcaseFailureType::FailedValidationReadOnlyProperty
+                        | TestcaseFailureType::FailedValidationWriteOnlyProperty
+                )
+            {
+                continue;
+            }
+            lints.push(Lint {
+                text: format!(
+                    "{} does not match its schema: {}",
+                    example.location, failure.text
+                ),
+                r#type: LintType::InvalidExample,
+            });
+        }
+    }
+    lints
+}
+
+/// Collects the examples of the parameters of a path or operation.
+fn parameter_examples<'a>(
+    location: &str,
+    parameters: &'a [ReferenceOr<openapiv3::Parameter>],
+    spec: &'a openapiv3::OpenAPI,
+    examples: &mut Vec<Example<'a>>,
+) {
+    for parameter in parameters
+        .iter()
+        .filter_map(|parameter| resolve_parameter(parameter, spec))
+    {
+        let parameter_data = parameter.parameter_data_ref();
+        format_examples(
+            &format!("{} parameter {}", location, parameter_data.name),
+            &parameter_data.format,
+            parameter_data.example.as_ref(),
+            parameter_data.examples.iter(),
+            spec,
+            ValidationPerspective::Request,
+            examples,
+        );
+    }
+}
+
+/// Collects the examples of every media type in the content.
+fn content_examples<'a>(
+    location: &str,
+    content: &'a openapiv3::Content,
+    spec: &'a openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    examples: &mut Vec<Example<'a>>,
+) {
+    for (media_type_name, media_type) in content.iter() {
+        let schema = match media_type
+            .schema
+            .as_ref()
+            .and_then(|schema| crate::resolve_schema(schema, spec))
+        {
+            Some(schema) => schema,
+            None => continue,
+        };
+        named_examples(
+            &format!("{} {}", location, media_type_name),
+            schema,
+            media_type.example.as_ref(),
+            media_type.examples.iter(),
+            spec,
+            perspective,
+            examples,
+        );
+    }
+}
+
+/// Collects the examples of a parameter or header, which are described by either a schema or
+/// content.
+fn format_examples<'a>(
+    location: &str,
+    format: &'a ParameterSchemaOrContent,
+    example: Option<&'a serde_json::Value>,
+    named: impl Iterator<Item = (&'a String, &'a ReferenceOr<openapiv3::Example>)>,
+    spec: &'a openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    examples: &mut Vec<Example<'a>>,
+) {
+    match format {
+        ParameterSchemaOrContent::Schema(schema) => {
+            if let Some(schema) = crate::resolve_schema(schema, spec) {
+                named_examples(
+                    location,
+                    schema,
+                    example,
+                    named,
+                    spec,
+                    perspective,
+                    examples,
+                );
+            }
+        }
+        ParameterSchemaOrContent::Content(content) => {
+            content_examples(location, content, spec, perspective, examples)
+        }
+    }
+}
+
+/// Collects the example and the named examples of a media type, parameter, or header.
+fn named_examples<'a>(
+    location: &str,
+    schema: &'a openapiv3::Schema,
+    example: Option<&'a serde_json::Value>,
+    named: impl Iterator<Item = (&'a String, &'a ReferenceOr<openapiv3::Example>)>,
+    spec: &'a openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    examples: &mut Vec<Example<'a>>,
+) {
+    if let Some(value) = example {
+        examples.push(Example {
+            location: format!("{} example", location),
+            value,
+            schema,
+            perspective: Some(perspective),
+        });
+    }
+    for (name, example) in named {
+        let example = match example {
+            ReferenceOr::Item(example) => Some(example),
+            ReferenceOr::Reference { reference } => reference
+                .strip_prefix("#/components/examples/")
+                .and_then(|name| spec.components.as_ref()?.examples.get(name)?.as_item()),
+        };
+        if let Some(value) = example.and_then(|example| example.value.as_ref()) {
+            examples.push(Example {
+                location: format!("{} example {}", location, name),
+                value,
+                schema,
+                perspective: Some(perspective),
+            });
+        }
+    }
+}
+
+/// Collects the example of the schema and the examples of the schemas nested in it. Referenced
+/// schemas are skipped since they are collected as component schemas.
+fn schema_examples<'a>(
+    location: String,
+    schema: &'a openapiv3::Schema,
+    examples: &mut Vec<Example<'a>>,
+) {
+    if let Some(value) = schema.schema_data.example.as_ref() {
+        examples.push(Example {
+            location: format!("{} example", location),
+            value,
+            schema,
+            perspective: None,
+        });
+    }
+    match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) => {
+            for (name, property) in object.properties.iter() {
+                if let Some(property) = property.as_item() {
+                    schema_examples(
+                        format!("{}/properties/{}", location, name),
+                        property,
+                        examples,
+                    );
+                }
+            }
+        }
+        openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) => {
+            if let Some(items) = array.items.as_ref().and_then(ReferenceOr::as_item) {
+                schema_examples(format!("{}/items", location), items, examples);
+            }
+        }
+        openapiv3::SchemaKind::OneOf { one_of: schemas }
+        | openapiv3::SchemaKind::AnyOf { any_of: schemas }
+        | openapiv3::SchemaKind::AllOf { all_of: schemas } => {
+            let keyword = match &schema.schema_kind {
+                openapiv3::SchemaKind::OneOf { .. } => "oneOf",
+                openapiv3::SchemaKind::AnyOf { .. } => "anyOf",
+                _ => "allOf",
+            };
+            for (index, schema) in schemas.iter().enumerate() {
+                if let Some(schema) = schema.as_item() {
+                    schema_examples(
+                        format!("{}/{}/{}", location, keyword, index),
+                        schema,
+                        examples,
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the names of the variables in a path template, like petId in /pets/{petId}.
+fn template_variables(path_template: &str) -> Vec<&str> {
+    path_template
+        .split('{')
+        .skip(1)
+        .filter_map(|segment| segment.split_once('}'))
+        .map(|(variable, _)| variable)
+        .collect()
+}
@@ -0,0 +1,62 @@
+use std::io::Read;
+
+/// Why a body could not be decompressed.
+pub enum Error {
+    /// The decompressed body is larger than the limit.
+    TooLarge,
+    /// The coding is not supported or the body is not valid in it.
+    Invalid(String),
+}
+
+/// Decompresses a body using the codings from its Content-Encoding header. Codings are listed in
+/// the order they were applied, so they are undone in reverse. Returns None if the body isn't
+/// compressed.
+///
+/// The decompressed body can be at most `limit` bytes, so a small compressed body can't expand to
+/// fill memory.
+pub fn decompress(
+    content_encoding: &str,
+    body: &[u8],
+    limit: usize,
+) -> Result<Option<Vec<u8>>, Error> {
+    let codings = content_encoding
+        .split(',')
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        .collect::<Vec<&str>>();
+    if codings.is_empty() {
+        return Ok(None);
+    }
+    let mut decompressed = body.to_vec();
+    for coding in codings.iter().rev() {
+        decompressed = match coding.to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => read(flate2::read::MultiGzDecoder::new(&*decompressed), limit),
+            // deflate is meant to be zlib wrapped, but some servers send raw deflate data
+            "deflate" => match read(flate2::read::ZlibDecoder::new(&*decompressed), limit) {
+                Err(Error::Invalid(_)) => {
+                    read(flate2::read::DeflateDecoder::new(&*decompressed), limit)
+                }
+                decompressed => decompressed,
+            },
+            "br" => read(brotli::Decompressor::new(&*decompressed, 4096), limit),
+            _ => return Err(Error::Invalid(format!("{} is not supported", coding))),
+        }
+        .map_err(|err| match err {
+            Error::Invalid(err) => Error::Invalid(format!("{} is not valid: {}", coding, err)),
+            err => err,
+        })?;
+    }
+    Ok(Some(decompressed))
+}
+
+fn read(reader: impl Read, limit: usize) -> Result<Vec<u8>, Error> {
+    let mut decompressed = vec![];
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|err| Error::Invalid(err.to_string()))?;
+    if decompressed.len() > limit {
+        return Err(Error::TooLarge);
+    }
+    Ok(decompressed)
+}
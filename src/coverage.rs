@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// The operations in the spec split by whether they received any requests through the proxy.
+#[derive(Debug, Clone, Serialize)]
+pub struct Coverage {
+    pub covered: Vec<OperationCoverage>,
+    pub uncovered: Vec<OperationCoverage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationCoverage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    pub method: String,
+    pub path: String,
+    /// The number of requests the operation received.
+    pub hits: usize,
+}
+
+/// Looks up how many requests every operation in the spec received. Operations are keyed by
+/// "{METHOD} {path template}" in `exercised`, the same as the metrics. Requests to methods that
+/// aren't operations in the spec don't count towards any operation.
+pub fn operation_coverage(
+    spec: &openapiv3::OpenAPI,
+    exercised: &BTreeMap<String, usize>,
+) -> Coverage {
+    let mut coverage = Coverage {
+        covered: vec![],
+        uncovered: vec![],
+    };
+    for (path_template, method, operation) in spec.operations() {
+        let method = method.to_uppercase();
+        let hits = exercised
+            .get(&format!("{} {}", method, path_template))
+            .copied()
+            .unwrap_or(0);
+        let operation = OperationCoverage {
+            operation_id: operation.operation_id.clone(),
+            method,
+            path: path_template.to_string(),
+            hits,
+        };
+        match hits {
+            0 => coverage.uncovered.push(operation),
+            _ => coverage.covered.push(operation),
+        }
+    }
+    coverage
+}
@@ -0,0 +1,55 @@
+use crate::Testcase;
+
+/// The columns of the failures CSV, in order.
+const HEADER: [&str; 7] = [
+    "correlationId",
+    "operationId",
+    "method",
+    "statusCode",
+    "failureType",
+    "jsonPointer",
+    "message",
+];
+
+/// One row per failure of the testcases, so they can be sliced in a spreadsheet. Values that
+/// weren't recorded, like the status code of a rejected request, are left empty.
+pub fn failures(testcases: &[Testcase]) -> String {
+    let mut csv = row(HEADER.iter().copied());
+    for testcase in testcases.iter() {
+        let property = |name: &str| {
+            testcase
+                .properties
+                .iter()
+                .find(|property| property.name == name)
+                .map_or("", |property| property.value.as_str())
+        };
+        for failure in testcase.failures.iter() {
+            let failure_type = failure.r#type.to_string();
+            csv.push_str(&row([
+                testcase.correlation_id().map_or("", String::as_str),
+                property("operationId"),
+                property("method"),
+                property("statusCode"),
+                failure_type.as_str(),
+                failure.json_pointer.as_deref().unwrap_or_default(),
+                failure.text.as_str(),
+            ]));
+        }
+    }
+    csv
+}
+
+/// A line of the CSV, ending with CRLF as RFC 4180 asks.
+fn row<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let fields = fields.into_iter().map(field).collect::<Vec<_>>();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Quotes a field if it contains a comma, a quote or a line break. Quotes inside it are doubled.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{JunitSuite, Session, Testcase};
+
+/// A report in the Common Test Report Format, a JSON format for test results that doesn't depend
+/// on the tool that produced them.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    results: Results,
+}
+
+#[derive(Debug, Serialize)]
+struct Results {
+    tool: Tool,
+    summary: Summary,
+    tests: Vec<Test>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    tests: usize,
+    passed: usize,
+    failed: usize,
+    pending: usize,
+    skipped: usize,
+    other: usize,
+    /// Milliseconds since the Unix epoch.
+    start: u128,
+    stop: u128,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Test {
+    name: String,
+    status: &'static str,
+    /// Milliseconds.
+    duration: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<String>,
+    suite: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// The testcase's properties. A property that is recorded more than once, like tag, is a list
+    /// of its values.
+    extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// Builds the report from the testsuites of the JUnit report. Each testsuite's name is the suite
+/// of its tests. The report stops when it is built.
+pub fn report(suites: &[JunitSuite], session: &Session) -> Report {
+    let tests = suites
+        .iter()
+        .flat_map(|suite| suite.testcases.iter().map(|testcase| test(suite, testcase)))
+        .collect::<Vec<_>>();
+    let count = |status: &str| tests.iter().filter(|test| test.status == status).count();
+    let milliseconds = |time: std::time::SystemTime| {
+        time.duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    };
+    Report {
+        results: Results {
+            tool: Tool {
+                name: "openapi-validator-proxy",
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            summary: Summary {
+                tests: tests.len(),
+                passed: count("passed"),
+                failed: count("failed"),
+                pending: 0,
+                skipped: count("skipped"),
+                other: 0,
+                start: milliseconds(session.started),
+                stop: milliseconds(std::time::SystemTime::now()),
+            },
+            tests,
+        },
+    }
+}
+
+fn test(suite: &JunitSuite, testcase: &Testcase) -> Test {
+    let status = match (testcase.failures.is_empty(), &testcase.skipped) {
+        (false, _) => "failed",
+        (true, Some(_)) => "skipped",
+        (true, None) => "passed",
+    };
+    let message = match status {
+        "failed" => Some(testcase.failure_message()),
+        "skipped" => testcase.skipped.clone(),
+        _ => None,
+    };
+    let mut extra: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+    for property in testcase.properties.iter() {
+        let value = serde_json::Value::String(property.value.clone());
+        match extra.get_mut(&property.name) {
+            Some(serde_json::Value::Array(values)) => values.push(value),
+            Some(existing) => *existing = serde_json::Value::Array(vec![existing.take(), value]),
+            None => {
+                extra.insert(property.name.clone(), value);
+            }
+        }
+    }
+    Test {
+        name: testcase.name.clone(),
+        status,
+        duration: testcase
+            .time
+            .parse::<f64>()
+            .map_or(0, |seconds| (seconds * 1000.0).round() as u64),
+        message,
+        trace: testcase
+            .reproduction
+            .as_ref()
+            .filter(|_| status == "failed")
+            .map(|reproduction| format!("Reproduce with:\n{}", reproduction)),
+        suite: suite.name.clone(),
+        tags: testcase.tags().map(str::to_string).collect(),
+        extra,
+    }
+}
@@ -0,0 +1,61 @@
+/// Headers whose values are replaced before they are included in a reproduction command.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+];
+
+/// Header names containing any of these are also treated as secrets.
+const SECRET_HEADER_FRAGMENTS: &[&str] = &["token", "secret", "password", "apikey", "api-key"];
+
+/// Headers that curl sets on its own from the URL and body.
+const SKIPPED_HEADERS: &[&str] = &["host", "content-length"];
+
+/// Builds a curl command that sends the same request the proxy sent upstream. Secret headers are
+/// redacted so the command can be shared in reports.
+pub fn reproduction_command(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+) -> String {
+    let mut command = format!("curl -X {} {}", method, shell_quote(url));
+    for (name, value) in headers {
+        let lowercase_name = name.to_lowercase();
+        if SKIPPED_HEADERS.contains(&lowercase_name.as_str()) {
+            continue;
+        }
+        let value = if is_secret_header(&lowercase_name) {
+            "[REDACTED]"
+        } else {
+            value.as_str()
+        };
+        command.push_str(&format!(
+            " -H {}",
+            shell_quote(&format!("{}: {}", name, value))
+        ));
+    }
+    if !body.is_empty() {
+        match std::str::from_utf8(body) {
+            Ok(body) => command.push_str(&format!(" --data-raw {}", shell_quote(body))),
+            Err(_) => command.push_str(&format!(
+                " --data-binary @body.bin # {} bytes of binary body omitted",
+                body.len()
+            )),
+        }
+    }
+    command
+}
+
+fn is_secret_header(lowercase_name: &str) -> bool {
+    SECRET_HEADERS.contains(&lowercase_name)
+        || SECRET_HEADER_FRAGMENTS
+            .iter()
+            .any(|fragment| lowercase_name.contains(fragment))
+}
+
+/// Quotes a value for a POSIX shell by wrapping it in single quotes.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
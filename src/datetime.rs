@@ -0,0 +1,182 @@
+use std::sync::LazyLock;
+
+use regex_lite::Regex;
+use serde::Deserialize;
+
+static DATE_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(\d{4})-(\d{2})-(\d{2})([Tt ])(\d{2}):(\d{2}):(\d{2})(\.\d+)?([Zz]|[+-](\d{2}):(\d{2}))?$",
+    )
+    .unwrap()
+});
+
+static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap());
+
+/// How strictly `date-time` and `date` strings are checked against RFC 3339.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateTimeMode {
+    /// Anything that isn't RFC 3339 is a validation failure.
+    #[default]
+    Strict,
+    /// Common deviations, like a missing timezone or a space instead of the `T` separator, are
+    /// reported separately from values that can't be read as a date at all.
+    Lenient,
+}
+
+/// The result of checking a string against a date format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DateTimeCheck {
+    Valid,
+    /// The value is not RFC 3339 but would be accepted in lenient mode. Contains a description of
+    /// the deviation.
+    Deviation(String),
+    Invalid,
+}
+
+/// Checks a `date-time` string, e.g. 2024-01-31T12:00:00Z.
+pub fn check_date_time(value: &str) -> DateTimeCheck {
+    let captures = match DATE_TIME.captures(value) {
+        Some(captures) => captures,
+        None => return DateTimeCheck::Invalid,
+    };
+    let number = |index: usize| {
+        captures
+            .get(index)
+            .map_or(0, |number| number.as_str().parse::<u32>().unwrap())
+    };
+    if !is_valid_date(number(1), number(2), number(3)) {
+        return DateTimeCheck::Invalid;
+    }
+    // A seconds value of 60 is allowed for leap seconds
+    if number(5) > 23 || number(6) > 59 || number(7) > 60 {
+        return DateTimeCheck::Invalid;
+    }
+    if number(10) > 23 || number(11) > 59 {
+        return DateTimeCheck::Invalid;
+    }
+    let mut deviations = vec![];
+    if &captures[4] == " " {
+        deviations.push("uses a space instead of T to separate the date and time");
+    }
+    if captures.get(9).is_none() {
+        deviations.push("is missing a timezone offset");
+    }
+    if deviations.is_empty() {
+        DateTimeCheck::Valid
+    } else {
+        DateTimeCheck::Deviation(deviations.join(" and "))
+    }
+}
+
+/// Checks a `date` string, e.g. 2024-01-31.
+pub fn check_date(value: &str) -> DateTimeCheck {
+    if let Some(captures) = DATE.captures(value) {
+        let number = |index: usize| captures[index].parse::<u32>().unwrap();
+        return match is_valid_date(number(1), number(2), number(3)) {
+            true => DateTimeCheck::Valid,
+            false => DateTimeCheck::Invalid,
+        };
+    }
+    // Legacy servers often send a full timestamp for a date
+    match value.get(..10).map(check_date) {
+        Some(DateTimeCheck::Valid) if check_date_time(value) != DateTimeCheck::Invalid => {
+            DateTimeCheck::Deviation("includes a time".to_string())
+        }
+        _ => DateTimeCheck::Invalid,
+    }
+}
+
+/// Formats a time as an RFC 3339 date-time in UTC with milliseconds, e.g.
+/// 2024-01-31T12:00:00.000Z.
+pub fn format_date_time(time: std::time::SystemTime) -> String {
+    let since_epoch = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Reads an RFC 3339 date-time as a point in time. Times without an offset are read as UTC, and
+/// fractions of a second beyond milliseconds are ignored.
+pub fn parse_date_time(value: &str) -> Option<std::time::SystemTime> {
+    let captures = DATE_TIME.captures(value)?;
+    if check_date_time(value) == DateTimeCheck::Invalid {
+        return None;
+    }
+    let number = |index: usize| {
+        captures
+            .get(index)
+            .map_or(0, |number| number.as_str().parse::<i64>().unwrap())
+    };
+    let days = days_from_civil(number(1), number(2) as u32, number(3) as u32);
+    let offset = match captures.get(9).map(|offset| offset.as_str()) {
+        Some(offset) if offset.starts_with('-') => -(number(10) * 3_600 + number(11) * 60),
+        Some(offset) if offset.starts_with('+') => number(10) * 3_600 + number(11) * 60,
+        _ => 0,
+    };
+    let seconds = days * 86_400 + number(5) * 3_600 + number(6) * 60 + number(7) - offset;
+    let milliseconds = captures
+        .get(8)
+        .map(|fraction| format!("{:0<3}", &fraction.as_str()[1..]))
+        .map_or(0, |fraction| fraction[..3].parse::<u64>().unwrap());
+    let since_epoch = std::time::Duration::from_secs(u64::try_from(seconds).ok()?)
+        + std::time::Duration::from_millis(milliseconds);
+    Some(std::time::UNIX_EPOCH + since_epoch)
+}
+
+/// Converts a year, month, and day in the proleptic Gregorian calendar to a number of days since
+/// 1970-01-01, using Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts a number of days since 1970-01-01 to a year, month, and day in the proleptic
+/// Gregorian calendar, using Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn is_valid_date(year: u32, month: u32, day: u32) -> bool {
+    let is_leap_year =
+        year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    day >= 1 && day <= days_in_month
+}
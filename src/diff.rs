@@ -0,0 +1,516 @@
+use crate::{
+    create_schema_for_all_of, resolve_parameter, resolve_request_body, resolve_response,
+    resolve_schema, ValidationPerspective,
+};
+use openapiv3::ReferenceOr;
+
+/// The kinds of changes between two specs that can break existing clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakingChangeType {
+    /// An operation in the old spec does not exist in the new spec.
+    RemovedOperation,
+    /// An enum in a request accepts fewer values than it used to.
+    NarrowedEnum,
+    /// A parameter, request body, or request body property is required that was not required before.
+    NewRequiredField,
+    /// A parameter or body property has a different type than it used to.
+    ChangedType,
+}
+
+impl std::fmt::Display for BreakingChangeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BreakingChangeType::RemovedOperation => write!(f, "RemovedOperation"),
+            BreakingChangeType::NarrowedEnum => write!(f, "NarrowedEnum"),
+            BreakingChangeType::NewRequiredField => write!(f, "NewRequiredField"),
+            BreakingChangeType::ChangedType => write!(f, "ChangedType"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BreakingChange {
+    pub text: String,
+    pub r#type: BreakingChangeType,
+}
+
+impl std::fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.r#type, self.text)
+    }
+}
+
+/// Compares two specs and returns the changes in `new` that would break clients of `old`.
+pub fn diff_specs(old: &openapiv3::OpenAPI, new: &openapiv3::OpenAPI) -> Vec<BreakingChange> {
+    let mut changes = vec![];
+    let specs = Specs { old, new };
+    for (path, method, old_operation) in old.operations() {
+        let operation_name = format!("{} {}", method.to_uppercase(), path);
+        let new_operation = new
+            .operations()
+            .find(|(new_path, new_method, _)| *new_path == path && *new_method == method)
+            .map(|(_, _, operation)| operation);
+        let new_operation = match new_operation {
+            Some(new_operation) => new_operation,
+            None => {
+                changes.push(BreakingChange {
+                    text: format!("{} was removed", operation_name),
+                    r#type: BreakingChangeType::RemovedOperation,
+                });
+                continue;
+            }
+        };
+        diff_parameters(
+            &operation_name,
+            old_operation,
+            new_operation,
+            &specs,
+            &mut changes,
+        );
+        diff_request_body(
+            &operation_name,
+            old_operation,
+            new_operation,
+            &specs,
+            &mut changes,
+        );
+        diff_responses(
+            &operation_name,
+            old_operation,
+            new_operation,
+            &specs,
+            &mut changes,
+        );
+    }
+    changes
+}
+
+/// The two specs being compared. References in the old operation are resolved against `old` and
+/// references in the new operation are resolved against `new`.
+struct Specs<'a> {
+    old: &'a openapiv3::OpenAPI,
+    new: &'a openapiv3::OpenAPI,
+}
+
+fn diff_parameters(
+    operation_name: &str,
+    old_operation: &openapiv3::Operation,
+    new_operation: &openapiv3::Operation,
+    specs: &Specs,
+    changes: &mut Vec<BreakingChange>,
+) {
+    let old_parameters = old_operation
+        .parameters
+        .iter()
+        .filter_map(|parameter| resolve_parameter(parameter, specs.old))
+        .collect::<Vec<&openapiv3::Parameter>>();
+    for new_parameter in new_operation
+        .parameters
+        .iter()
+        .filter_map(|parameter| resolve_parameter(parameter, specs.new))
+    {
+        let new_data = new_parameter.parameter_data_ref();
+        let location = parameter_location(new_parameter);
+        let old_parameter = old_parameters.iter().find(|old_parameter| {
+            old_parameter.parameter_data_ref().name == new_data.name
+                && parameter_location(old_parameter) == location
+        });
+        let old_parameter = match old_parameter {
+            Some(old_parameter) => old_parameter,
+            None => {
+                if new_data.required {
+                    changes.push(BreakingChange {
+                        text: format!(
+                            "{} requires new {} parameter {}",
+                            operation_name, location, new_data.name
+                        ),
+                        r#type: BreakingChangeType::NewRequiredField,
+                    });
+                }
+                continue;
+            }
+        };
+        let old_data = old_parameter.parameter_data_ref();
+        if new_data.required && !old_data.required {
+            changes.push(BreakingChange {
+                text: format!(
+                    "{} {} parameter {} is now required",
+                    operation_name, location, new_data.name
+                ),
+                r#type: BreakingChangeType::NewRequiredField,
+            });
+        }
+        if let (
+            openapiv3::ParameterSchemaOrContent::Schema(old_schema),
+            openapiv3::ParameterSchemaOrContent::Schema(new_schema),
+        ) = (&old_data.format, &new_data.format)
+        {
+            if let (Some(old_schema), Some(new_schema)) = (
+                resolve_schema(old_schema, specs.old),
+                resolve_schema(new_schema, specs.new),
+            ) {
+                diff_schema(
+                    &format!(
+                        "{} {} parameter {}",
+                        operation_name, location, new_data.name
+                    ),
+                    "",
+                    old_schema,
+                    new_schema,
+                    specs,
+                    ValidationPerspective::Request,
+                    &mut vec![],
+                    changes,
+                );
+            }
+        }
+    }
+}
+
+fn parameter_location(parameter: &openapiv3::Parameter) -> &'static str {
+    match parameter {
+        openapiv3::Parameter::Query { .. } => "query",
+        openapiv3::Parameter::Header { .. } => "header",
+        openapiv3::Parameter::Path { .. } => "path",
+        openapiv3::Parameter::Cookie { .. } => "cookie",
+    }
+}
+
+fn diff_request_body(
+    operation_name: &str,
+    old_operation: &openapiv3::Operation,
+    new_operation: &openapiv3::Operation,
+    specs: &Specs,
+    changes: &mut Vec<BreakingChange>,
+) {
+    let old_request_body = old_operation
+        .request_body
+        .as_ref()
+        .and_then(|request_body| resolve_request_body(request_body, specs.old));
+    let new_request_body = new_operation
+        .request_body
+        .as_ref()
+        .and_then(|request_body| resolve_request_body(request_body, specs.new));
+    let new_request_body = match new_request_body {
+        Some(new_request_body) => new_request_body,
+        None => return,
+    };
+    let old_required = old_request_body.is_some_and(|request_body| request_body.required);
+    if new_request_body.required && !old_required {
+        changes.push(BreakingChange {
+            text: format!("{} request body is now required", operation_name),
+            r#type: BreakingChangeType::NewRequiredField,
+        });
+    }
+    let old_request_body = match old_request_body {
+        Some(old_request_body) => old_request_body,
+        None => return,
+    };
+    diff_content(
+        &format!("{} request body", operation_name),
+        &old_request_body.content,
+        &new_request_body.content,
+        specs,
+        ValidationPerspective::Request,
+        changes,
+    );
+}
+
+fn diff_responses(
+    operation_name: &str,
+    old_operation: &openapiv3::Operation,
+    new_operation: &openapiv3::Operation,
+    specs: &Specs,
+    changes: &mut Vec<BreakingChange>,
+) {
+    for (status_code, old_response) in old_operation.responses.responses.iter() {
+        let new_response = new_operation
+            .responses
+            .responses
+            .get(status_code)
+            .and_then(|response| resolve_response(response, specs.new));
+        let old_response = resolve_response(old_response, specs.old);
+        if let (Some(old_response), Some(new_response)) = (old_response, new_response) {
+            diff_content(
+                &format!("{} {} response body", operation_name, status_code),
+                &old_response.content,
+                &new_response.content,
+                specs,
+                ValidationPerspective::Response,
+                changes,
+            );
+        }
+    }
+}
+
+fn diff_content(
+    location: &str,
+    old_content: &openapiv3::Content,
+    new_content: &openapiv3::Content,
+    specs: &Specs,
+    perspective: ValidationPerspective,
+    changes: &mut Vec<BreakingChange>,
+) {
+    for (media_type, old_media_type) in old_content.iter() {
+        let new_schema = new_content
+            .get(media_type)
+            .and_then(|media_type| media_type.schema.as_ref())
+            .and_then(|schema| resolve_schema(schema, specs.new));
+        let old_schema = old_media_type
+            .schema
+            .as_ref()
+            .and_then(|schema| resolve_schema(schema, specs.old));
+        if let (Some(old_schema), Some(new_schema)) = (old_schema, new_schema) {
+            diff_schema(
+                location,
+                "#/",
+                old_schema,
+                new_schema,
+                specs,
+                perspective,
+                &mut vec![],
+                changes,
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn diff_schema(
+    location: &str,
+    json_pointer: &str,
+    old_schema: &openapiv3::Schema,
+    new_schema: &openapiv3::Schema,
+    specs: &Specs,
+    perspective: ValidationPerspective,
+    visited: &mut Vec<String>,
+    changes: &mut Vec<BreakingChange>,
+) {
+    // allOf is compared as the object it describes so that moving properties into or out of an
+    // allOf is not reported as a change.
+    if let openapiv3::SchemaKind::AllOf { all_of } = &old_schema.schema_kind {
+        let old_schema = create_schema_for_all_of(all_of, specs.old);
+        return diff_schema(
+            location,
+            json_pointer,
+            &old_schema,
+            new_schema,
+            specs,
+            perspective,
+            visited,
+            changes,
+        );
+    }
+    if let openapiv3::SchemaKind::AllOf { all_of } = &new_schema.schema_kind {
+        let new_schema = create_schema_for_all_of(all_of, specs.new);
+        return diff_schema(
+            location,
+            json_pointer,
+            old_schema,
+            &new_schema,
+            specs,
+            perspective,
+            visited,
+            changes,
+        );
+    }
+    let (old_type, new_type) = match (&old_schema.schema_kind, &new_schema.schema_kind) {
+        (openapiv3::SchemaKind::Type(old_type), openapiv3::SchemaKind::Type(new_type)) => {
+            (old_type, new_type)
+        }
+        _ => return,
+    };
+    match (old_type, new_type) {
+        (openapiv3::Type::String(old_string), openapiv3::Type::String(new_string)) => {
+            if perspective == ValidationPerspective::Request {
+                diff_enum(
+                    location,
+                    json_pointer,
+                    &old_string.enumeration,
+                    &new_string.enumeration,
+                    changes,
+                );
+            }
+        }
+        (openapiv3::Type::Integer(old_integer), openapiv3::Type::Integer(new_integer)) => {
+            if perspective == ValidationPerspective::Request {
+                diff_enum(
+                    location,
+                    json_pointer,
+                    &old_integer.enumeration,
+                    &new_integer.enumeration,
+                    changes,
+                );
+            }
+        }
+        (openapiv3::Type::Number(_), openapiv3::Type::Number(_))
+        | (openapiv3::Type::Boolean(_), openapiv3::Type::Boolean(_)) => {}
+        (openapiv3::Type::Array(old_array), openapiv3::Type::Array(new_array)) => {
+            if let (Some(old_items), Some(new_items)) = (&old_array.items, &new_array.items) {
+                diff_schema_reference(
+                    location,
+                    &format!("{}items/", json_pointer),
+                    &old_items.clone().unbox(),
+                    &new_items.clone().unbox(),
+                    specs,
+                    perspective,
+                    visited,
+                    changes,
+                );
+            }
+        }
+        (openapiv3::Type::Object(old_object), openapiv3::Type::Object(new_object)) => {
+            if perspective == ValidationPerspective::Request {
+                for required in new_object.required.iter() {
+                    if !old_object.required.contains(required) {
+                        changes.push(BreakingChange {
+                            text: format!(
+                                "{} property {}{} is now required",
+                                location, json_pointer, required
+                            ),
+                            r#type: BreakingChangeType::NewRequiredField,
+                        });
+                    }
+                }
+            }
+            for (name, old_property) in old_object.properties.iter() {
+                if let Some(new_property) = new_object.properties.get(name) {
+                    diff_schema_reference(
+                        location,
+                        &format!("{}{}/", json_pointer, name),
+                        &old_property.clone().unbox(),
+                        &new_property.clone().unbox(),
+                        specs,
+                        perspective,
+                        visited,
+                        changes,
+                    );
+                }
+            }
+        }
+        (old_type, new_type) => {
+            changes.push(BreakingChange {
+                text: format!(
+                    "{}{} changed type from {} to {}",
+                    location,
+                    at(json_pointer),
+                    type_name(old_type),
+                    type_name(new_type)
+                ),
+                r#type: BreakingChangeType::ChangedType,
+            });
+        }
+    }
+}
+
+/// Resolves a nested schema before comparing it. References that are already being compared are
+/// skipped so recursive schemas terminate.
+#[allow(clippy::too_many_arguments)]
+fn diff_schema_reference(
+    location: &str,
+    json_pointer: &str,
+    old_schema: &ReferenceOr<openapiv3::Schema>,
+    new_schema: &ReferenceOr<openapiv3::Schema>,
+    specs: &Specs,
+    perspective: ValidationPerspective,
+    visited: &mut Vec<String>,
+    changes: &mut Vec<BreakingChange>,
+) {
+    let reference = match old_schema {
+        ReferenceOr::Reference { reference } => Some(reference.clone()),
+        ReferenceOr::Item(_) => None,
+    };
+    if let Some(reference) = &reference {
+        if visited.contains(reference) {
+            return;
+        }
+        visited.push(reference.clone());
+    }
+    if let (Some(old_schema), Some(new_schema)) = (
+        resolve_schema(old_schema, specs.old),
+        resolve_schema(new_schema, specs.new),
+    ) {
+        diff_schema(
+            location,
+            json_pointer,
+            old_schema,
+            new_schema,
+            specs,
+            perspective,
+            visited,
+            changes,
+        );
+    }
+    if reference.is_some() {
+        visited.pop();
+    }
+}
+
+fn diff_enum<T: PartialEq + std::fmt::Display>(
+    location: &str,
+    json_pointer: &str,
+    old_enumeration: &[Option<T>],
+    new_enumeration: &[Option<T>],
+    changes: &mut Vec<BreakingChange>,
+) {
+    // An empty enumeration allows every value, so it can only be narrowed by adding values to it.
+    if new_enumeration.is_empty() {
+        return;
+    }
+    let to_string = |value: &Option<T>| match value {
+        Some(value) => value.to_string(),
+        None => "null".to_string(),
+    };
+    if old_enumeration.is_empty() {
+        changes.push(BreakingChange {
+            text: format!(
+                "{}{} now only accepts {}",
+                location,
+                at(json_pointer),
+                new_enumeration
+                    .iter()
+                    .map(to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            r#type: BreakingChangeType::NarrowedEnum,
+        });
+        return;
+    }
+    let removed = old_enumeration
+        .iter()
+        .filter(|value| !new_enumeration.contains(value))
+        .map(to_string)
+        .collect::<Vec<String>>();
+    if removed.is_empty() {
+        return;
+    }
+    changes.push(BreakingChange {
+        text: format!(
+            "{}{} no longer accepts {}",
+            location,
+            at(json_pointer),
+            removed.join(", ")
+        ),
+        r#type: BreakingChangeType::NarrowedEnum,
+    });
+}
+
+/// Describes where in a schema a change was found, or nothing if it was found at the root.
+fn at(json_pointer: &str) -> String {
+    match json_pointer.trim_end_matches('/') {
+        "" | "#" => String::new(),
+        json_pointer => format!(" at {}", json_pointer),
+    }
+}
+
+fn type_name(schema_type: &openapiv3::Type) -> &'static str {
+    match schema_type {
+        openapiv3::Type::String(_) => "string",
+        openapiv3::Type::Number(_) => "number",
+        openapiv3::Type::Integer(_) => "integer",
+        openapiv3::Type::Object(_) => "object",
+        openapiv3::Type::Array(_) => "array",
+        openapiv3::Type::Boolean(_) => "boolean",
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// An address that a host and port should resolve to, supplied with --resolve host:port=addr.
+/// The address can include its own port, otherwise the port from the host is used.
+#[derive(Debug, Clone)]
+pub struct ResolveOverride {
+    pub netloc: String,
+    pub address: SocketAddr,
+}
+
+impl FromStr for ResolveOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (netloc, address) = s
+            .split_once('=')
+            .ok_or_else(|| format!("{} is not in the form host:port=addr", s))?;
+        let (host, port) = netloc
+            .rsplit_once(':')
+            .ok_or_else(|| format!("{} is missing a port", netloc))?;
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| format!("{} is not a valid port", port))?;
+        let address = match address.parse::<SocketAddr>() {
+            Ok(address) => address,
+            Err(_) => {
+                let ip = address
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .map_err(|_| format!("{} is not a valid IP address", address))?;
+                SocketAddr::new(ip, port)
+            }
+        };
+        Ok(Self {
+            netloc: format!("{}:{}", host.to_lowercase(), port),
+            address,
+        })
+    }
+}
+
+/// Resolves the upstream host for the outbound client. Overrides take precedence over DNS, and
+/// DNS results are cached for the TTL to avoid resolving the host on every request.
+#[derive(Debug)]
+pub struct CachingResolver {
+    overrides: BTreeMap<String, Vec<SocketAddr>>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>,
+}
+
+impl CachingResolver {
+    pub fn new(overrides: &[ResolveOverride], ttl: Duration) -> Self {
+        let mut by_netloc: BTreeMap<String, Vec<SocketAddr>> = BTreeMap::new();
+        for resolve_override in overrides {
+            by_netloc
+                .entry(resolve_override.netloc.clone())
+                .or_default()
+                .push(resolve_override.address);
+        }
+        Self {
+            overrides: by_netloc,
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolves a host and port to the addresses to connect to.
+    pub async fn resolve(&self, netloc: &str) -> std::io::Result<Vec<SocketAddr>> {
+        if let Some(addresses) = self.overrides.get(&netloc.to_lowercase()) {
+            return Ok(addresses.clone());
+        }
+        if self.ttl.is_zero() {
+            return tokio::net::lookup_host(netloc)
+                .await
+                .map(|addresses| addresses.collect());
+        }
+        if let Some((resolved_at, addresses)) = self.cache.lock().unwrap().get(netloc) {
+            if resolved_at.elapsed() < self.ttl {
+                return Ok(addresses.clone());
+            }
+        }
+        let addresses = tokio::net::lookup_host(netloc)
+            .await?
+            .collect::<Vec<SocketAddr>>();
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(netloc.to_string(), (Instant::now(), addresses.clone()));
+        Ok(addresses)
+    }
+}
@@ -0,0 +1,100 @@
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::IntoResponse,
+};
+use serde::Serialize;
+
+use crate::{TestcaseFailure, TestcaseFailureType};
+
+/// The body sent to the client in place of a request or response that failed validation while
+/// --enforce is set. It follows the problem details format of RFC 9457.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Problem<'a> {
+    r#type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+    correlation_id: &'a str,
+    failures: &'a [TestcaseFailure],
+}
+
+/// Whether the client asked for the request to be enforced with the enforce header. Only `true` is
+/// accepted, so a request can't opt out of --enforce.
+pub fn requested(
+    headers: &axum::http::HeaderMap,
+    proxy_headers: &crate::headers::ProxyHeaders,
+) -> bool {
+    headers
+        .get(proxy_headers.enforce())
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Whether the failures are grounds for rejecting a request or response. Bodies that are too large
+/// to validate are still forwarded.
+pub fn rejects(failures: &[TestcaseFailure]) -> bool {
+    failures
+        .iter()
+        .any(|failure| failure.r#type != TestcaseFailureType::BodyTooLargeToValidate)
+}
+
+/// The response to a request that failed validation. The request is not forwarded to the upstream.
+pub fn reject_request(
+    correlation_id: &str,
+    failures: &[TestcaseFailure],
+) -> axum::response::Response {
+    problem(
+        StatusCode::BAD_REQUEST,
+        "The request does not match the OpenAPI spec",
+        correlation_id,
+        failures,
+    )
+}
+
+/// The response to a request whose upstream response failed validation. The upstream response is
+/// not returned to the client.
+pub fn reject_response(
+    correlation_id: &str,
+    failures: &[TestcaseFailure],
+) -> axum::response::Response {
+    problem(
+        StatusCode::BAD_GATEWAY,
+        "The upstream response does not match the OpenAPI spec",
+        correlation_id,
+        failures,
+    )
+}
+
+fn problem(
+    status: StatusCode,
+    title: &'static str,
+    correlation_id: &str,
+    failures: &[TestcaseFailure],
+) -> axum::response::Response {
+    let mut types: Vec<String> = vec![];
+    for failure in failures {
+        let r#type = failure.r#type.to_string();
+        if !types.contains(&r#type) {
+            types.push(r#type);
+        }
+    }
+    let body = serde_json::to_string(&Problem {
+        r#type: "about:blank",
+        title,
+        status: status.as_u16(),
+        detail: format!("Failed validation with {}", types.join(", ")),
+        correlation_id,
+        failures,
+    })
+    .unwrap();
+    (
+        status,
+        [(
+            axum::http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        )],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,93 @@
+use axum::http::{header::CONTENT_TYPE, HeaderMap};
+use serde::{Deserialize, Serialize};
+
+/// Which bodies are embedded in the report of a failed testcase. Bodies are cut off at
+/// `max_bytes` and only bodies with a matching content type are embedded, so binary uploads don't
+/// fill the report.
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub max_bytes: usize,
+    /// Media types like application/json. A `*` matches any type or subtype, and `*+json` matches
+    /// any subtype with that suffix.
+    pub content_types: Vec<String>,
+}
+
+/// A body embedded in the report of a failed testcase, so the failure can be understood without
+/// running the scenario again.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Evidence {
+    /// What the body is, like "Request body".
+    pub name: String,
+    pub content_type: String,
+    pub body: String,
+    /// The number of bytes that were cut off the end of the body.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub truncated: usize,
+}
+
+fn is_zero(truncated: &usize) -> bool {
+    *truncated == 0
+}
+
+impl Capture {
+    /// Captures the request body and, when there is one, the response body.
+    pub fn exchange(
+        &self,
+        request: (&HeaderMap, &[u8]),
+        response: Option<(&HeaderMap, &[u8])>,
+    ) -> Vec<Evidence> {
+        let mut evidence = vec![];
+        evidence.extend(self.capture("Request body", request.0, request.1));
+        if let Some((headers, body)) = response {
+            evidence.extend(self.capture("Response body", headers, body));
+        }
+        evidence
+    }
+
+    /// Captures a body if it isn't empty and its Content-Type matches.
+    fn capture(&self, name: &str, headers: &HeaderMap, body: &[u8]) -> Option<Evidence> {
+        if body.is_empty() {
+            return None;
+        }
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        if !self
+            .content_types
+            .iter()
+            .any(|pattern| matches(pattern, content_type))
+        {
+            return None;
+        }
+        let text = String::from_utf8_lossy(body);
+        let mut end = text.len().min(self.max_bytes);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        Some(Evidence {
+            name: name.to_string(),
+            content_type: content_type.to_string(),
+            body: text[..end].to_string(),
+            truncated: text.len() - end,
+        })
+    }
+}
+
+/// Whether a Content-Type header matches a media type pattern. Parameters like charset are
+/// ignored.
+fn matches(pattern: &str, content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let (r#type, subtype) = essence.split_once('/').unwrap_or((&essence, ""));
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let (pattern_type, pattern_subtype) = pattern.split_once('/').unwrap_or((&pattern, "*"));
+    let type_matches = pattern_type == "*" || pattern_type == r#type;
+    let subtype_matches = match pattern_subtype.strip_prefix('*') {
+        Some(suffix) => subtype.ends_with(suffix),
+        None => pattern_subtype == subtype,
+    };
+    type_matches && subtype_matches
+}
@@ -0,0 +1,147 @@
+use regex_lite::Regex;
+use serde::Deserialize;
+
+/// The largest KSUID, all 20 bytes set to 0xff, encoded as base62.
+const MAX_KSUID: &str = "aWgEPTl1tmebfsQzFP4bxwgy80V";
+
+/// Validates strings that use a custom format from the spec, like `format: iban`. These are
+/// registered in the config file because openapiv3 ignores formats it doesn't know about.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FormatValidator {
+    /// The string must match the regular expression somewhere. Use ^ and $ to match the whole
+    /// string.
+    Pattern { pattern: Pattern },
+    /// The string must pass one of the checkers that come with the proxy.
+    Builtin { builtin: BuiltinFormat },
+}
+
+impl FormatValidator {
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            FormatValidator::Pattern { pattern } => pattern.0.is_match(value),
+            FormatValidator::Builtin { builtin } => builtin.is_valid(value),
+        }
+    }
+}
+
+/// A regular expression that is compiled when the config file is loaded so that invalid patterns
+/// are reported at startup.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(|err| format!("{} is not a valid pattern: {}", pattern, err))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BuiltinFormat {
+    /// An email address with a local part and a valid hostname, like `rex@example.com`.
+    Email,
+    /// A hostname made of labels of letters, digits, and hyphens, as described by RFC 1123.
+    Hostname,
+    /// An International Bank Account Number with a valid check digit, without spaces.
+    Iban,
+    Ipv4,
+    Ipv6,
+    /// A K-Sortable Unique Identifier, 27 base62 characters.
+    Ksuid,
+    /// An absolute URI, which must include a scheme.
+    Uri,
+    Uuid,
+}
+
+impl BuiltinFormat {
+    /// The checker for a format defined by the OpenAPI or JSON Schema specifications. Strings with
+    /// these formats are validated without being registered in the config file. Formats that
+    /// should be validated out of the box are added here.
+    pub fn standard(format: &str) -> Option<Self> {
+        match format {
+            "email" => Some(BuiltinFormat::Email),
+            "hostname" => Some(BuiltinFormat::Hostname),
+            "ipv4" => Some(BuiltinFormat::Ipv4),
+            "ipv6" => Some(BuiltinFormat::Ipv6),
+            "uri" => Some(BuiltinFormat::Uri),
+            "uuid" => Some(BuiltinFormat::Uuid),
+            _ => None,
+        }
+    }
+
+    pub fn is_valid(self, value: &str) -> bool {
+        match self {
+            BuiltinFormat::Email => is_email(value),
+            BuiltinFormat::Hostname => is_hostname(value),
+            BuiltinFormat::Iban => is_iban(value),
+            BuiltinFormat::Ipv4 => value.parse::<std::net::Ipv4Addr>().is_ok(),
+            BuiltinFormat::Ipv6 => value.parse::<std::net::Ipv6Addr>().is_ok(),
+            BuiltinFormat::Ksuid => is_ksuid(value),
+            BuiltinFormat::Uri => url::Url::parse(value).is_ok(),
+            BuiltinFormat::Uuid => uuid::Uuid::try_parse(value).is_ok(),
+        }
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    // The local part can be almost anything when quoted, so only the domain is checked closely
+    match value.rsplit_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !local.chars().any(|c| c.is_whitespace() || c.is_control())
+                && domain.contains('.')
+                && is_hostname(domain)
+        }
+        None => false,
+    }
+}
+
+fn is_hostname(value: &str) -> bool {
+    let value = value.strip_suffix('.').unwrap_or(value);
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+        })
+}
+
+fn is_iban(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    if !(15..=34).contains(&bytes.len())
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes[4..]
+            .iter()
+            .all(|byte| byte.is_ascii_digit() || byte.is_ascii_uppercase())
+    {
+        return false;
+    }
+    // The country code and check digits are moved to the end and letters are replaced by two
+    // digits, A = 10 through Z = 35. The result must be 1 mod 97.
+    let remainder = bytes[4..]
+        .iter()
+        .chain(&bytes[..4])
+        .fold(0u32, |remainder, byte| match byte {
+            b'0'..=b'9' => (remainder * 10 + u32::from(byte - b'0')) % 97,
+            _ => (remainder * 100 + u32::from(byte - b'A') + 10) % 97,
+        });
+    remainder == 1
+}
+
+fn is_ksuid(value: &str) -> bool {
+    // Every KSUID has the same length, so comparing the strings compares their values
+    value.len() == MAX_KSUID.len()
+        && value.bytes().all(|byte| byte.is_ascii_alphanumeric())
+        && value <= MAX_KSUID
+}
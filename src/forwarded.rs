@@ -0,0 +1,86 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, Request};
+
+/// The proxy only listens for plain HTTP.
+const PROTO: &str = "http";
+
+/// The address of the client that sent a request and the host it sent the request to. HTTP/2
+/// requests carry the host in their URI instead of a Host header.
+pub fn origin(request: &Request) -> (Option<IpAddr>, Option<String>) {
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip());
+    let host = request
+        .headers()
+        .get(axum::http::header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| request.uri().authority().map(ToString::to_string));
+    (client, host)
+}
+
+/// Adds the headers that tell the upstream who the client is and how it reached the proxy. The
+/// client's address is appended to X-Forwarded-For and Forwarded, so the addresses added by proxies
+/// in front of this one are kept. X-Forwarded-Proto and X-Forwarded-Host are only set when an
+/// earlier proxy hasn't set them already.
+pub fn add_forwarded_headers(
+    headers: &mut Vec<(String, String)>,
+    client: Option<IpAddr>,
+    host: Option<&str>,
+) {
+    if let Some(client) = client {
+        append(headers, "x-forwarded-for", &client.to_string());
+    }
+    if !contains(headers, "x-forwarded-proto") {
+        headers.push(("x-forwarded-proto".to_string(), PROTO.to_string()));
+    }
+    if let Some(host) = host.filter(|_| !contains(headers, "x-forwarded-host")) {
+        headers.push(("x-forwarded-host".to_string(), host.to_string()));
+    }
+    let mut element = vec![];
+    match client {
+        Some(IpAddr::V4(client)) => element.push(format!("for={}", client)),
+        Some(IpAddr::V6(client)) => element.push(format!("for=\"[{}]\"", client)),
+        None => {}
+    }
+    if let Some(host) = host {
+        element.push(format!("host={}", quote(host)));
+    }
+    element.push(format!("proto={}", PROTO));
+    append(headers, "forwarded", &element.join(";"));
+}
+
+fn contains(headers: &[(String, String)], name: &str) -> bool {
+    headers
+        .iter()
+        .any(|(existing, _)| existing.eq_ignore_ascii_case(name))
+}
+
+/// Adds a value to the end of a comma separated header, or adds the header if it isn't there.
+fn append(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    match headers
+        .iter_mut()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+    {
+        Some((_, existing)) => {
+            existing.push_str(", ");
+            existing.push_str(value);
+        }
+        None => headers.push((name.to_string(), value.to_string())),
+    }
+}
+
+/// Forwarded values have to be quoted unless they only contain token characters, which a host with
+/// a port doesn't.
+fn quote(value: &str) -> String {
+    let token = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c));
+    if token {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
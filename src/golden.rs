@@ -0,0 +1,165 @@
+use std::path::PathBuf;
+
+use crate::{TestcaseFailure, TestcaseFailureType, TestcaseProperty};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GoldenMode {
+    /// Store the shape of every JSON response as the golden file for its operation and status code
+    Record,
+    /// Compare the shape of every JSON response to the golden file for its operation and status code
+    Verify,
+}
+
+/// Golden files hold the normalized shape of a response body for each operation and status code.
+/// They catch changes in behavior that are still valid according to the spec, like a property
+/// that the upstream stopped sending.
+#[derive(Debug, Clone)]
+pub struct Golden {
+    pub directory: PathBuf,
+    pub mode: GoldenMode,
+}
+
+impl Golden {
+    fn path(&self, operation_id: &str, status: u16) -> PathBuf {
+        self.directory
+            .join(operation_id)
+            .join(format!("{}.json", status))
+    }
+
+    /// Records or verifies the response body depending on the mode. Bodies that are not JSON are
+    /// skipped.
+    pub fn check(
+        &self,
+        operation_id: &str,
+        status: u16,
+        body: &[u8],
+    ) -> (Vec<TestcaseFailure>, Vec<TestcaseProperty>) {
+        let mut failures = vec![];
+        let mut properties = vec![];
+        let body = match serde_json::from_slice::<serde_json::Value>(body) {
+            Ok(body) => body,
+            Err(_) => return (failures, properties),
+        };
+        let actual = shape(&body);
+        let path = self.path(operation_id, status);
+        match self.mode {
+            GoldenMode::Record => {
+                let result = std::fs::create_dir_all(path.parent().unwrap()).and_then(|_| {
+                    std::fs::write(&path, serde_json::to_string_pretty(&actual).unwrap())
+                });
+                if let Err(err) = result {
+                    tracing::error!(?path, %err, "Failed to record golden file");
+                    return (failures, properties);
+                }
+                properties.push(TestcaseProperty {
+                    name: "golden".to_string(),
+                    value: "recorded".to_string(),
+                });
+            }
+            GoldenMode::Verify => {
+                let golden = match std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|golden| serde_json::from_str::<serde_json::Value>(&golden).ok())
+                {
+                    Some(golden) => golden,
+                    None => {
+                        properties.push(TestcaseProperty {
+                            name: "golden".to_string(),
+                            value: "missing".to_string(),
+                        });
+                        return (failures, properties);
+                    }
+                };
+                properties.push(TestcaseProperty {
+                    name: "golden".to_string(),
+                    value: "verified".to_string(),
+                });
+                let mut drift = vec![];
+                compare(&golden, &actual, "", &mut drift);
+                for text in drift {
+                    failures.push(TestcaseFailure {
+                        text,
+                        r#type: TestcaseFailureType::GoldenResponseDrift,
+                        json_pointer: None,
+                        spec_pointer: None,
+                        perspective: None,
+                    });
+                }
+            }
+        }
+        (failures, properties)
+    }
+}
+
+/// Normalizes a JSON value to its shape. Scalars are replaced by the name of their type, objects
+/// keep their keys, and arrays are represented by their first item.
+pub fn shape(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Null => serde_json::Value::Null,
+        serde_json::Value::Bool(_) => serde_json::Value::String("boolean".to_string()),
+        serde_json::Value::Number(_) => serde_json::Value::String("number".to_string()),
+        serde_json::Value::String(_) => serde_json::Value::String("string".to_string()),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.iter().take(1).map(shape).collect())
+        }
+        serde_json::Value::Object(object) => serde_json::Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), shape(value)))
+                .collect(),
+        ),
+    }
+}
+
+fn type_name(shape: &serde_json::Value) -> String {
+    match shape {
+        serde_json::Value::Null => "null".to_string(),
+        serde_json::Value::Array(_) => "array".to_string(),
+        serde_json::Value::Object(_) => "object".to_string(),
+        other => other.as_str().unwrap_or_default().to_string(),
+    }
+}
+
+/// Compares two shapes and describes every difference at or below the JSON pointer.
+fn compare(
+    golden: &serde_json::Value,
+    actual: &serde_json::Value,
+    json_pointer: &str,
+    drift: &mut Vec<String>,
+) {
+    let location = if json_pointer.is_empty() {
+        "/"
+    } else {
+        json_pointer
+    };
+    match (golden, actual) {
+        (serde_json::Value::Object(golden), serde_json::Value::Object(actual)) => {
+            for (key, golden_value) in golden.iter() {
+                let json_pointer = format!("{}/{}", json_pointer, key);
+                match actual.get(key) {
+                    Some(actual_value) => compare(golden_value, actual_value, &json_pointer, drift),
+                    None => drift.push(format!("Property {} was removed", json_pointer)),
+                }
+            }
+            for key in actual.keys().filter(|key| !golden.contains_key(*key)) {
+                drift.push(format!("Property {}/{} was added", json_pointer, key));
+            }
+        }
+        (serde_json::Value::Array(golden), serde_json::Value::Array(actual)) => {
+            // An empty array doesn't say anything about the shape of its items.
+            if let (Some(golden), Some(actual)) = (golden.first(), actual.first()) {
+                compare(golden, actual, &format!("{}/0", json_pointer), drift);
+            }
+        }
+        (golden, actual) => {
+            let golden_type = type_name(golden);
+            let actual_type = type_name(actual);
+            if golden_type != actual_type {
+                drift.push(format!(
+                    "Value at {} changed from {} to {}",
+                    location, golden_type, actual_type
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    io::{Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Closes the entries, the log, and the document. It is written after every entry so the file is
+/// a complete HAR document even if the proxy is killed, and is overwritten by the next entry.
+const CLOSING: &[u8] = b"]}}";
+
+/// A HAR file that every proxied request and response is appended to as soon as the response is
+/// validated.
+#[derive(Debug)]
+pub struct Recording(Mutex<RecordingFile>);
+
+#[derive(Debug)]
+struct RecordingFile {
+    file: std::fs::File,
+    entries: usize,
+}
+
+/// A request that was proxied and the response the upstream sent back.
+pub struct Exchange<'a> {
+    pub started: std::time::SystemTime,
+    pub duration: std::time::Duration,
+    pub correlation_id: &'a str,
+    pub method: &'a str,
+    pub url: &'a url::Url,
+    /// The headers that were sent to the upstream.
+    pub request_headers: &'a [(String, String)],
+    pub request_body: &'a [u8],
+    pub status: u16,
+    pub response_headers: &'a axum::http::HeaderMap,
+    pub response_body: &'a [u8],
+}
+
+impl Recording {
+    /// Creates the file, replacing the recording of an earlier session.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut file = std::fs::File::create(path)?;
+        let creator = serde_json::to_string(&Creator {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        })?;
+        write!(
+            file,
+            r#"{{"log":{{"version":"1.2","creator":{},"entries":["#,
+            creator
+        )?;
+        file.write_all(CLOSING)?;
+        Ok(Recording(Mutex::new(RecordingFile { file, entries: 0 })))
+    }
+
+    pub fn append(&self, exchange: &Exchange) {
+        let entry = serde_json::to_vec(&Entry::from(exchange)).unwrap();
+        let mut recording = self.0.lock().unwrap();
+        let separator: &[u8] = if recording.entries == 0 { b"" } else { b"," };
+        let written = recording
+            .file
+            .seek(SeekFrom::End(-(CLOSING.len() as i64)))
+            .and_then(|_| {
+                recording
+                    .file
+                    .write_all(&[separator, &entry, CLOSING].concat())
+            });
+        match written {
+            Ok(()) => recording.entries += 1,
+            Err(err) => tracing::error!(%err, "Failed to record request"),
+        }
+    }
+}
+
+/// A HAR document. Only the fields the proxy records are modelled, and the ones it doesn't need to
+/// replay a request are optional so HAR files from browsers and other tools can be read too.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Har {
+    pub log: Log,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Log {
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Creator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    #[serde(default)]
+    pub started_date_time: String,
+    /// Milliseconds between sending the request and receiving the response.
+    #[serde(default)]
+    pub time: f64,
+    pub request: Request,
+    pub response: Response,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    pub timings: Timings,
+    #[serde(
+        rename = "_correlationId",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub correlation_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    #[serde(default)]
+    pub query_string: Vec<Header>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<PostData>,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Response {
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    pub content: Content,
+    #[serde(rename = "redirectURL", default)]
+    pub redirect_url: String,
+    pub headers_size: i64,
+    pub body_size: i64,
+}
+
+/// A header or query string parameter.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+/// The body of a request. HAR has no way to mark a request body as base64, so bodies that aren't
+/// UTF-8 are marked with the custom `_encoding` field, which other tools ignore.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(rename = "_encoding", default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+/// The body of a response.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Content {
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Timings {
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+}
+
+impl PostData {
+    /// The bytes of the body, decoding it if it was recorded as base64.
+    pub fn bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        match self.encoding.as_deref() {
+            Some("base64") => base64::engine::general_purpose::STANDARD.decode(&self.text),
+            _ => Ok(self.text.as_bytes().to_vec()),
+        }
+    }
+}
+
+/// Reads the entries of a HAR file.
+pub fn load(path: &Path) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let har: Har = serde_json::from_str(&content)
+        .map_err(|err| format!("{} is not a HAR file: {}", path.display(), err))?;
+    Ok(har.log.entries)
+}
+
+impl From<&Exchange<'_>> for Entry {
+    fn from(exchange: &Exchange) -> Self {
+        let milliseconds = exchange.duration.as_secs_f64() * 1000.0;
+        let request_mime_type = exchange
+            .request_headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default();
+        let (response_text, response_encoding) = body_text(exchange.response_body);
+        Entry {
+            started_date_time: crate::datetime::format_date_time(exchange.started),
+            time: milliseconds,
+            request: Request {
+                method: exchange.method.to_string(),
+                url: exchange.url.to_string(),
+                http_version: "HTTP/1.1".to_string(),
+                cookies: vec![],
+                headers: exchange
+                    .request_headers
+                    .iter()
+                    .map(|(name, value)| Header {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+                query_string: exchange
+                    .url
+                    .query_pairs()
+                    .map(|(name, value)| Header {
+                        name: name.to_string(),
+                        value: value.to_string(),
+                    })
+                    .collect(),
+                post_data: (!exchange.request_body.is_empty()).then(|| {
+                    let (text, encoding) = body_text(exchange.request_body);
+                    PostData {
+                        mime_type: request_mime_type,
+                        text,
+                        encoding,
+                    }
+                }),
+                headers_size: -1,
+                body_size: exchange.request_body.len() as i64,
+            },
+            response: Response {
+                status: exchange.status,
+                status_text: axum::http::StatusCode::from_u16(exchange.status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: "HTTP/1.1".to_string(),
+                cookies: vec![],
+                headers: exchange
+                    .response_headers
+                    .iter()
+                    .map(|(name, value)| Header {
+                        name: name.to_string(),
+                        value: String::from_utf8_lossy(value.as_bytes()).to_string(),
+                    })
+                    .collect(),
+                content: Content {
+                    size: exchange.response_body.len() as i64,
+                    mime_type: exchange
+                        .response_headers
+                        .get(axum::http::header::CONTENT_TYPE)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string(),
+                    text: response_text,
+                    encoding: response_encoding,
+                },
+                redirect_url: exchange
+                    .response_headers
+                    .get(axum::http::header::LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string(),
+                headers_size: -1,
+                body_size: exchange.response_body.len() as i64,
+            },
+            cache: serde_json::json!({}),
+            timings: Timings {
+                send: 0.0,
+                wait: milliseconds,
+                receive: 0.0,
+            },
+            correlation_id: Some(exchange.correlation_id.to_string()),
+        }
+    }
+}
+
+/// The text of a body and how it is encoded. Bodies that aren't UTF-8 are recorded as base64.
+fn body_text(body: &[u8]) -> (String, Option<String>) {
+    match std::str::from_utf8(body) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (
+            base64::engine::general_purpose::STANDARD.encode(body),
+            Some("base64".to_string()),
+        ),
+    }
+}
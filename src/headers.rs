@@ -0,0 +1,220 @@
+use std::str::FromStr;
+
+use axum::http::{uri::PathAndQuery, HeaderMap, HeaderName, HeaderValue, Request, Uri};
+use serde::Deserialize;
+
+use crate::TestcaseProperty;
+
+/// The names of the headers clients use to control the proxy, like OVP-Correlation-Id. Every name
+/// starts with the same prefix so it can be changed to fit an organization's header naming rules.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ProxyHeaders {
+    pub prefix: Prefix,
+    /// Remove the proxy's headers from requests before they are forwarded, for upstreams and
+    /// gateways that reject headers they don't know about.
+    pub strip_upstream: bool,
+    /// A response header, like X-Request-Id, whose value becomes the correlation ID when the
+    /// client didn't send one. For clients that can't be changed to send the proxy's header.
+    upstream_correlation_id: Option<ConfiguredHeader>,
+    /// A query parameter, like ovp_cid, that carries the correlation ID for clients that can't
+    /// set headers, like browsers driven by tests.
+    correlation_id_parameter: Option<String>,
+}
+
+impl Default for ProxyHeaders {
+    fn default() -> Self {
+        ProxyHeaders {
+            prefix: Prefix("OVP-".to_string()),
+            strip_upstream: false,
+            upstream_correlation_id: None,
+            correlation_id_parameter: None,
+        }
+    }
+}
+
+impl ProxyHeaders {
+    /// The header that names the testcase and links requests to each other.
+    pub fn correlation_id(&self) -> HeaderName {
+        self.prefix.header("Correlation-Id")
+    }
+
+    /// The correlation ID the upstream echoed in its response, if a header was configured for it
+    /// and the response has a non-empty, UTF-8 value for it.
+    pub fn upstream_correlation_id(&self, headers: &HeaderMap) -> Option<String> {
+        let ConfiguredHeader(name) = self.upstream_correlation_id.as_ref()?;
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Moves the correlation ID from the configured query parameter into the correlation ID
+    /// header, unless the header was sent too. The parameter is removed either way so it isn't
+    /// validated or forwarded to the upstream. The rest of the query is left as it was encoded.
+    pub fn move_correlation_id_parameter<B>(&self, request: &mut Request<B>) {
+        let Some(parameter) = &self.correlation_id_parameter else {
+            return;
+        };
+        let Some(query) = request.uri().query() else {
+            return;
+        };
+        let mut correlation_id = None;
+        let kept = query
+            .split('&')
+            .filter(
+                |pair| match url::form_urlencoded::parse(pair.as_bytes()).next() {
+                    Some((name, value)) if name == parameter.as_str() => {
+                        correlation_id.get_or_insert(value.to_string());
+                        false
+                    }
+                    _ => true,
+                },
+            )
+            .collect::<Vec<_>>();
+        let Some(correlation_id) = correlation_id else {
+            return;
+        };
+        let path_and_query = match kept.is_empty() {
+            true => request.uri().path().to_string(),
+            false => format!("{}?{}", request.uri().path(), kept.join("&")),
+        };
+        let mut parts = request.uri().clone().into_parts();
+        parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+        if let Ok(uri) = Uri::from_parts(parts) {
+            *request.uri_mut() = uri;
+        }
+        let header = self.correlation_id();
+        if !request.headers().contains_key(&header) {
+            if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+                request.headers_mut().insert(header, correlation_id);
+            }
+        }
+    }
+
+    /// The header that lists other headers the correlation ID is copied into.
+    pub fn fused_correlation_headers(&self) -> HeaderName {
+        self.prefix.header("Fused-Correlation-Headers")
+    }
+
+    /// The header that names the tenant whose report the testcase belongs to.
+    pub fn tenant(&self) -> HeaderName {
+        self.prefix.header("Tenant")
+    }
+
+    /// The header that makes the proxy reject the request or its response if either fails
+    /// validation, as if --enforce was set.
+    pub fn enforce(&self) -> HeaderName {
+        self.prefix.header("Enforce")
+    }
+
+    /// The header that makes the proxy forward the request without validating it, for setup and
+    /// teardown calls made by the test harness itself.
+    pub fn skip_validation(&self) -> HeaderName {
+        self.prefix.header("Skip-Validation")
+    }
+
+    /// The headers that should not be forwarded.
+    pub fn stripped(&self) -> Vec<HeaderName> {
+        match self.strip_upstream {
+            true => vec![
+                self.correlation_id(),
+                self.fused_correlation_headers(),
+                self.tenant(),
+                self.enforce(),
+                self.skip_validation(),
+            ],
+            false => vec![],
+        }
+    }
+}
+
+/// A header name prefix that is checked when the config file is loaded, so every header name
+/// built from it is valid.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Prefix(String);
+
+impl Prefix {
+    fn header(&self, name: &str) -> HeaderName {
+        HeaderName::from_str(&format!("{}{}", self.0, name)).unwrap()
+    }
+}
+
+impl TryFrom<String> for Prefix {
+    type Error = String;
+
+    fn try_from(prefix: String) -> Result<Self, Self::Error> {
+        HeaderName::from_str(&format!("{}Correlation-Id", prefix))
+            .map(|_| Prefix(prefix.clone()))
+            .map_err(|_| format!("{} is not a valid header name prefix", prefix))
+    }
+}
+
+impl FromStr for Prefix {
+    type Err = String;
+
+    fn from_str(prefix: &str) -> Result<Self, Self::Err> {
+        Prefix::try_from(prefix.to_string())
+    }
+}
+
+/// Request and response headers that are recorded as properties of every testcase, like
+/// X-Request-Id, so failures can be matched with the upstream's logs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CapturedHeaders {
+    request: Vec<ConfiguredHeader>,
+    response: Vec<ConfiguredHeader>,
+}
+
+impl CapturedHeaders {
+    /// A requestHeader-{name} property for each captured header the request has.
+    pub fn request(&self, headers: &HeaderMap) -> Vec<TestcaseProperty> {
+        properties("requestHeader", &self.request, headers)
+    }
+
+    /// A responseHeader-{name} property for each captured header the response has.
+    pub fn response(&self, headers: &HeaderMap) -> Vec<TestcaseProperty> {
+        properties("responseHeader", &self.response, headers)
+    }
+}
+
+/// Repeated headers are joined with commas, like they would be in a single header.
+fn properties(
+    prefix: &str,
+    captured: &[ConfiguredHeader],
+    headers: &HeaderMap,
+) -> Vec<TestcaseProperty> {
+    captured
+        .iter()
+        .filter_map(|ConfiguredHeader(name)| {
+            let values = headers
+                .get_all(name)
+                .iter()
+                .map(|value| String::from_utf8_lossy(value.as_bytes()))
+                .collect::<Vec<_>>();
+            (!values.is_empty()).then(|| TestcaseProperty {
+                name: format!("{}-{}", prefix, name),
+                value: values.join(", "),
+            })
+        })
+        .collect()
+}
+
+/// A header name that is checked when the config file is loaded.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+struct ConfiguredHeader(HeaderName);
+
+impl TryFrom<String> for ConfiguredHeader {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        HeaderName::from_str(&name)
+            .map(ConfiguredHeader)
+            .map_err(|_| format!("{} is not a valid header name", name))
+    }
+}
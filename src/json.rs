@@ -0,0 +1,312 @@
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+
+/// Returns the JSON pointer of every object key that appears more than once in its object.
+/// serde_json keeps the last value for a duplicate key, so these are otherwise invisible.
+pub fn duplicate_keys(body: &[u8]) -> Result<Vec<String>, serde_json::Error> {
+    let mut duplicates = vec![];
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    DuplicateKeyWalker {
+        json_pointer: String::new(),
+        duplicates: &mut duplicates,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(duplicates)
+}
+
+struct DuplicateKeyWalker<'a> {
+    json_pointer: String,
+    duplicates: &'a mut Vec<String>,
+}
+
+impl<'de> DeserializeSeed<'de> for DuplicateKeyWalker<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for DuplicateKeyWalker<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_i64<E>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_u64<E>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_f64<E>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_str<E>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut index = 0;
+        while seq
+            .next_element_seed(DuplicateKeyWalker {
+                json_pointer: format!("{}/{}", self.json_pointer, index),
+                duplicates: &mut *self.duplicates,
+            })?
+            .is_some()
+        {
+            index += 1;
+        }
+        Ok(())
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut keys = std::collections::BTreeSet::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let json_pointer = format!("{}/{}", self.json_pointer, escape(&key));
+            if !keys.insert(key) {
+                self.duplicates.push(json_pointer.clone());
+            }
+            map.next_value_seed(DuplicateKeyWalker {
+                json_pointer,
+                duplicates: &mut *self.duplicates,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes a key for use as a segment of a JSON pointer, as described in
+/// https://datatracker.ietf.org/doc/html/rfc6901#section-3
+pub fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Reverses `escape`. `~1` is replaced first, so `~01` becomes `~1` rather than `/`.
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// The number of characters shown on either side of the start of a highlighted value.
+const EXCERPT_CONTEXT: usize = 40;
+
+/// Returns the byte range of the value at the JSON pointer in the body. Empty segments are
+/// ignored, so the pointers from schema validation like /pets/0/ can be used as they are. When
+/// an object has duplicate keys the last one is used, since that is the value serde_json keeps.
+pub fn locate(body: &str, json_pointer: &str) -> Option<std::ops::Range<usize>> {
+    let segments = json_pointer
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(unescape)
+        .collect::<Vec<String>>();
+    let segments = segments.iter().map(String::as_str).collect::<Vec<&str>>();
+    let mut scanner = Scanner {
+        bytes: body.as_bytes(),
+        position: 0,
+    };
+    scanner.find(&segments)
+}
+
+/// The byte offset of a serde_json error, which is reported as a line and column.
+pub fn error_offset(content: &str, err: &serde_json::Error) -> usize {
+    content
+        .split_inclusive('\n')
+        .take(err.line().saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>()
+        + err.column().saturating_sub(1)
+}
+
+/// Shows the line of the body that contains the range with the range underlined, for example:
+///
+/// ```text
+/// {"id": 1, "name": 5}
+///                   ^
+/// ```
+///
+/// Long lines are cut down to the characters around the start of the range.
+pub fn excerpt(body: &str, range: std::ops::Range<usize>) -> String {
+    let line_start = body[..range.start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = body[range.start..]
+        .find('\n')
+        .map_or(body.len(), |index| range.start + index);
+    let before = &body[line_start..range.start];
+    let before_chars = before.chars().count();
+    let (prefix, before) = if before_chars > EXCERPT_CONTEXT {
+        let skip = before
+            .char_indices()
+            .nth(before_chars - EXCERPT_CONTEXT)
+            .map_or(0, |(index, _)| index);
+        ("...", &before[skip..])
+    } else {
+        ("", before)
+    };
+    let value = &body[range.start..range.end.min(line_end)];
+    let after = body[range.end.min(line_end)..line_end].trim_end_matches('\r');
+    let mut line = format!("{}{}{}", prefix, before, value);
+    let remaining = (EXCERPT_CONTEXT * 2).saturating_sub(value.chars().count());
+    if after.chars().count() > remaining {
+        line.extend(after.chars().take(remaining));
+        line.push_str("...");
+    } else {
+        line.push_str(after);
+    }
+    let indent = prefix.len() + before.chars().count();
+    let underline = value.chars().count().clamp(1, EXCERPT_CONTEXT * 2);
+    format!("{}\n{}{}", line, " ".repeat(indent), "^".repeat(underline))
+}
+
+/// Walks the raw bytes of a JSON document to find where values start and end, which
+/// serde_json::Value doesn't keep track of.
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Option<()> {
+        self.skip_whitespace();
+        if self.peek()? != expected {
+            return None;
+        }
+        self.position += 1;
+        Some(())
+    }
+
+    fn find(&mut self, segments: &[&str]) -> Option<std::ops::Range<usize>> {
+        self.skip_whitespace();
+        let (segment, rest) = match segments.split_first() {
+            Some(split) => split,
+            None => {
+                let start = self.position;
+                self.skip_value()?;
+                return Some(start..self.position);
+            }
+        };
+        match self.peek()? {
+            b'{' => {
+                self.position += 1;
+                let mut found = None;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek()? == b'}' {
+                        return found;
+                    }
+                    let key = self.read_string()?;
+                    self.expect(b':')?;
+                    if key == *segment {
+                        // Keep scanning in case the key appears again
+                        let value_start = self.position;
+                        found = self.find(rest);
+                        self.position = value_start;
+                    }
+                    self.skip_whitespace();
+                    self.skip_value()?;
+                    self.skip_whitespace();
+                    if self.peek()? == b',' {
+                        self.position += 1;
+                    }
+                }
+            }
+            b'[' => {
+                let index = segment.parse::<usize>().ok()?;
+                self.position += 1;
+                for _ in 0..index {
+                    self.skip_whitespace();
+                    self.skip_value()?;
+                    self.expect(b',')?;
+                }
+                self.find(rest)
+            }
+            _ => None,
+        }
+    }
+
+    /// Reads a string and returns it with its escapes decoded.
+    fn read_string(&mut self) -> Option<String> {
+        let start = self.position;
+        self.skip_string()?;
+        serde_json::from_slice(&self.bytes[start..self.position]).ok()
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        if self.peek()? != b'"' {
+            return None;
+        }
+        self.position += 1;
+        loop {
+            match self.peek()? {
+                b'\\' => self.position += 2,
+                b'"' => {
+                    self.position += 1;
+                    return Some(());
+                }
+                _ => self.position += 1,
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.skip_string(),
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.skip_string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.position += 1;
+                    if depth == 0 {
+                        return Some(());
+                    }
+                }
+            }
+            _ => {
+                while self
+                    .peek()
+                    .is_some_and(|byte| !byte.is_ascii_whitespace() && !b",]}".contains(&byte))
+                {
+                    self.position += 1;
+                }
+                Some(())
+            }
+        }
+    }
+}
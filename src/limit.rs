@@ -0,0 +1,50 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits how many requests are proxied at the same time. Requests over the limit wait in a
+/// bounded queue and are rejected once the queue is full, so a load spike can't buffer an
+/// unbounded number of bodies in memory.
+#[derive(Debug)]
+pub struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    max_queue: usize,
+    queued: AtomicUsize,
+    /// The number of seconds clients are told to wait before retrying a rejected request.
+    pub retry_after: u64,
+}
+
+impl ConcurrencyLimit {
+    pub fn new(max_concurrency: usize, max_queue: usize, retry_after: u64) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrency)),
+            max_queue,
+            queued: AtomicUsize::new(0),
+            retry_after,
+        }
+    }
+
+    /// Waits for a slot to proxy a request. Returns None if the limit is reached and the queue is
+    /// full. The slot is released when the permit is dropped.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst);
+        if queued >= self.max_queue {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        let permit = self.semaphore.clone().acquire_owned().await.ok();
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        permit
+    }
+
+    /// The number of requests waiting for a slot.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+}
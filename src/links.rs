@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use crate::{TestcaseFailure, TestcaseFailureType, TestcaseProperty};
+
+/// A link from a response that a later request with the same correlation ID is expected to follow.
+/// The parameters have already been evaluated against the request and response that the link was
+/// found in.
+#[derive(Debug, Clone)]
+pub struct PendingLink {
+    pub correlation_id: String,
+    pub name: String,
+    pub source_testcase: String,
+    pub operation_id: String,
+    pub parameters: BTreeMap<String, String>,
+}
+
+/// The request and response values that runtime expressions can refer to.
+/// https://spec.openapis.org/oas/v3.0.3#runtime-expressions
+pub struct ExpressionContext<'a> {
+    pub url: &'a str,
+    pub method: &'a str,
+    pub status: u16,
+    pub path_parameters: &'a BTreeMap<String, String>,
+    pub query: &'a [(String, String)],
+    pub request_headers: &'a axum::http::HeaderMap,
+    pub request_body: &'a [u8],
+    pub response_headers: &'a axum::http::HeaderMap,
+    pub response_body: &'a [u8],
+}
+
+/// Evaluates a runtime expression like `$response.body#/id`. Values that are not expressions are
+/// returned as constants. Returns None if the expression refers to a value that does not exist.
+pub fn evaluate_expression(
+    expression: &serde_json::Value,
+    context: &ExpressionContext,
+) -> Option<String> {
+    let expression = match expression {
+        serde_json::Value::String(expression) => expression,
+        other => return Some(json_value_to_string(other)),
+    };
+    if !expression.starts_with('$') {
+        return Some(expression.to_string());
+    }
+    match expression.as_str() {
+        "$url" => return Some(context.url.to_string()),
+        "$method" => return Some(context.method.to_string()),
+        "$statusCode" => return Some(context.status.to_string()),
+        _ => {}
+    }
+    if let Some(name) = expression.strip_prefix("$request.path.") {
+        return context.path_parameters.get(name).cloned();
+    }
+    if let Some(name) = expression.strip_prefix("$request.query.") {
+        return context
+            .query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string());
+    }
+    if let Some(name) = expression.strip_prefix("$request.header.") {
+        return header_to_string(context.request_headers, name);
+    }
+    if let Some(name) = expression.strip_prefix("$response.header.") {
+        return header_to_string(context.response_headers, name);
+    }
+    if let Some(pointer) = expression.strip_prefix("$request.body") {
+        return body_pointer_to_string(context.request_body, pointer);
+    }
+    if let Some(pointer) = expression.strip_prefix("$response.body") {
+        return body_pointer_to_string(context.response_body, pointer);
+    }
+    None
+}
+
+fn json_value_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn header_to_string(headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+fn body_pointer_to_string(body: &[u8], pointer: &str) -> Option<String> {
+    let body = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    let pointer = pointer.strip_prefix('#').unwrap_or(pointer);
+    body.pointer(pointer).map(json_value_to_string)
+}
+
+/// Finds the actual value of a parameter in the request. Parameter names can be qualified with
+/// their location, like `path.petId`, otherwise the path, query, and headers are searched in that
+/// order.
+pub fn request_parameter(name: &str, context: &ExpressionContext) -> Option<String> {
+    if let Some(name) = name.strip_prefix("path.") {
+        return context.path_parameters.get(name).cloned();
+    }
+    if let Some(name) = name.strip_prefix("query.") {
+        return context
+            .query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.to_string());
+    }
+    if let Some(name) = name.strip_prefix("header.") {
+        return header_to_string(context.request_headers, name);
+    }
+    context
+        .path_parameters
+        .get(name)
+        .cloned()
+        .or_else(|| {
+            context
+                .query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+        })
+        .or_else(|| header_to_string(context.request_headers, name))
+}
+
+/// Checks a request against the links that earlier responses with the same correlation ID pointed
+/// at its operation. The request passes if it follows at least one of those links.
+pub fn check_pending_links(
+    pending_links: &[PendingLink],
+    correlation_id: &str,
+    operation_id: &str,
+    context: &ExpressionContext,
+) -> (Vec<TestcaseFailure>, Vec<TestcaseProperty>) {
+    let mut failures = vec![];
+    let mut properties = vec![];
+    let candidates = pending_links
+        .iter()
+        .filter(|link| link.correlation_id == correlation_id && link.operation_id == operation_id)
+        .collect::<Vec<&PendingLink>>();
+    if candidates.is_empty() {
+        return (failures, properties);
+    }
+    let followed = candidates.iter().rev().find(|link| {
+        link.parameters
+            .iter()
+            .all(|(name, expected)| request_parameter(name, context).as_ref() == Some(expected))
+    });
+    match followed {
+        Some(link) => {
+            properties.push(TestcaseProperty {
+                name: "link".to_string(),
+                value: link.name.clone(),
+            });
+            properties.push(TestcaseProperty {
+                name: "linkedFrom".to_string(),
+                value: link.source_testcase.clone(),
+            });
+        }
+        None => {
+            for link in candidates {
+                let mismatched = link
+                    .parameters
+                    .iter()
+                    .filter_map(|(name, expected)| {
+                        let actual = request_parameter(name, context);
+                        if actual.as_ref() == Some(expected) {
+                            return None;
+                        }
+                        Some(format!(
+                            "{} expected {} but received {}",
+                            name,
+                            expected,
+                            actual.unwrap_or_else(|| "nothing".to_string())
+                        ))
+                    })
+                    .collect::<Vec<String>>();
+                failures.push(TestcaseFailure {
+                    text: format!(
+                        "Request did not follow link {} from testcase {}: {}",
+                        link.name,
+                        link.source_testcase,
+                        mismatched.join(", ")
+                    ),
+                    r#type: TestcaseFailureType::LinkParameterMismatch,
+                    json_pointer: None,
+                    spec_pointer: None,
+                    perspective: None,
+                });
+            }
+        }
+    }
+    (failures, properties)
+}
+
+/// Resolves the operationId that a link points to. Links can either name the operationId
+/// directly or use an operationRef like `#/paths/~1pets~1{petId}/get`.
+pub fn link_operation_id(link: &openapiv3::Link, spec: &openapiv3::OpenAPI) -> Option<String> {
+    match &link.operation {
+        openapiv3::LinkOperation::OperationId(operation_id) => Some(operation_id.to_string()),
+        openapiv3::LinkOperation::OperationRef(operation_ref) => {
+            let pointer = operation_ref.strip_prefix("#/paths/")?;
+            let (path, method) = pointer.rsplit_once('/')?;
+            let path = path.replace("~1", "/").replace("~0", "~");
+            spec.operations()
+                .find(|(template, operation_method, _)| {
+                    *template == path && operation_method.eq_ignore_ascii_case(method)
+                })
+                .and_then(|(_, _, operation)| operation.operation_id.clone())
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use clap::{ArgAction, Args};
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt};
+
+/// The level that is logged when no flags are given and RUST_LOG is not set.
+const DEFAULT_DIRECTIVES: &str = "info";
+
+/// Flags that control how much the proxy logs. They can be given before or after the subcommand.
+#[derive(Args)]
+pub struct LogArgs {
+    /// Log more. -v logs at debug level and -vv at trace level
+    #[arg(short, long, action = ArgAction::Count, global = true, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less. -q only logs warnings and errors, and -qq only logs errors
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    quiet: u8,
+
+    /// A level like warn, or comma separated directives like info,openapi_validator_proxy=debug.
+    /// Takes precedence over -v, -q, and the RUST_LOG environment variable
+    #[arg(long, value_name = "LEVEL", global = true)]
+    log_level: Option<String>,
+}
+
+impl LogArgs {
+    /// The directives to filter logs with. --log-level wins over -v and -q, which win over
+    /// RUST_LOG.
+    fn directives(&self) -> String {
+        if let Some(log_level) = &self.log_level {
+            return log_level.clone();
+        }
+        match (self.verbose, self.quiet) {
+            (0, 0) => std::env::var("RUST_LOG")
+                .ok()
+                .filter(|directives| !directives.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_DIRECTIVES.to_string()),
+            (1, _) => "debug".to_string(),
+            (_, 0) => "trace".to_string(),
+            (_, 1) => "warn".to_string(),
+            _ => "error".to_string(),
+        }
+    }
+}
+
+/// Installs the subscriber that prints logs to stderr, so stdout only has the output of commands like
+/// the report printed by replay.
+pub fn init(args: &LogArgs) -> Result<(), String> {
+    let directives = args.directives();
+    let targets = directives
+        .parse::<Targets>()
+        .map_err(|err| format!("Invalid log level {}: {}", directives, err))?;
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(std::io::stderr)
+        .finish()
+        .with(targets);
+    tracing::subscriber::set_global_default(subscriber).map_err(|err| err.to_string())
+}
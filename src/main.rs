@@ -1,19 +1,64 @@
+mod artifacts;
+mod body;
+mod cache;
+mod charset;
+mod check;
+mod compression;
+mod coverage;
+mod csv;
+mod ctrf;
+mod curl;
+mod datetime;
+mod diff;
+mod dns;
+mod enforce;
+mod evidence;
+mod formats;
+mod forwarded;
+mod golden;
+mod har;
+mod headers;
+mod json;
+mod limit;
+mod links;
+mod logging;
+mod parameters;
+mod query;
+mod remote;
+mod replay;
+mod results;
+mod runs;
+mod security;
+mod sequences;
+mod source;
+mod suggestions;
+mod summary;
+mod tls;
+mod trace;
+mod upstream;
+mod validate;
+mod version;
+
 use askama::Template;
 use axum::{
-    extract::{Request, State},
+    extract::{FromRef, Path, Query, Request, State},
     http::{HeaderName, HeaderValue},
     response::IntoResponse,
-    routing::{delete, get, head, options, patch, post, put},
+    routing::{any, get, post},
     Router,
 };
 use axum_macros::debug_handler;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use futures_util::{FutureExt, StreamExt};
 use openapiv3::ReferenceOr;
-use std::{path::PathBuf, str::FromStr, sync::Arc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::Arc,
+};
 use tokio::{signal, sync::Mutex};
-use tracing::{debug, error, info, instrument, Level};
-use tracing_subscriber::FmtSubscriber;
-use ureq::OrAnyStatus;
+use tracing::{debug, error, info, instrument};
 
 #[derive(Parser)]
 #[command(
@@ -23,82 +68,1064 @@ use ureq::OrAnyStatus;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    log: logging::LogArgs,
 }
 
+// Commands is parsed once at startup, so the size of the Proxy variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Starts the proxy server with the given file as input
-    Proxy {
+    Proxy(ProxyArgs),
+    /// Starts the proxy server and records every proxied request and response to a HAR file
+    Record {
+        /// Filepath of the HAR file to write the traffic to
+        #[arg(value_name = "HAR")]
+        har: PathBuf,
+
+        #[command(flatten)]
+        proxy: ProxyArgs,
+    },
+    /// Starts the proxy server, sends it the requests recorded in a HAR file, and prints the JUnit
+    /// report once they have been validated. Exits with 1 if any testcase failed
+    Replay {
+        /// Filepath of the HAR file to replay, like one written by record
+        #[arg(value_name = "HAR")]
+        har: PathBuf,
+
+        #[command(flatten)]
+        proxy: ProxyArgs,
+
+        /// Filepath to write the JUnit report to instead of printing it
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Compares two specs and reports changes that would break clients of the old spec
+    Diff {
+        /// Filepath of the old OpenAPI spec
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// Filepath of the new OpenAPI spec
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+    },
+    /// Checks a spec for problems that would cause requests to be routed or validated unexpectedly
+    Check {
         /// Filepath of the OpenAPI spec
         #[arg(value_name = "FILE")]
         file: PathBuf,
-
+    },
+    /// Checks that a spec can be read and resolved by the proxy, printing a diagnostic for every
+    /// problem
+    Validate {
         /// Filepath of the OpenAPI spec
-        #[arg(value_name = "UPSTREAM")]
-        upstream: url::Url,
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Serves the reports for testcases saved with --results without proxying anything
+    ServeReport {
+        /// Filepath of the saved results
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
 
-        /// Port to run the proxy server on
+        /// Port to serve the reports on
         #[arg(short, long, default_value = "3000")]
-        port: Option<u16>,
+        port: u16,
     },
 }
 
+/// The arguments of the commands that start the proxy.
+#[derive(Args)]
+struct ProxyArgs {
+    /// Filepath or http(s) URL of the OpenAPI spec
+    #[arg(value_name = "FILE")]
+    file: source::SpecSource,
+
+    /// Filepath of the OpenAPI spec
+    #[arg(value_name = "UPSTREAM")]
+    upstream: url::Url,
+
+    /// Port to run the proxy server on
+    #[arg(short, long, default_value = "3000")]
+    port: Option<u16>,
+
+    /// Filepath of a YAML config file
+    #[arg(short, long, value_name = "CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Validate against the spec even if its version is not supported. Every testcase gets an
+    /// unsupportedSpecVersion property
+    #[arg(long)]
+    force: bool,
+
+    /// Fetch the documents that $refs on http(s) URLs point to when the proxy starts
+    #[arg(long)]
+    allow_remote_refs: bool,
+
+    /// Read the spec again every SECONDS and validate against the new version if it changed
+    #[arg(long, value_name = "SECONDS")]
+    spec_refresh_interval: Option<u64>,
+
+    /// Directory of golden response files used to detect drift in responses
+    #[arg(long, value_name = "DIR", requires = "golden_mode")]
+    golden_dir: Option<PathBuf>,
+
+    /// Whether to record golden files or verify responses against them
+    #[arg(long, value_enum, requires = "golden_dir")]
+    golden_mode: Option<golden::GoldenMode>,
+
+    /// Directory to write the request and response bodies of failed testcases to
+    #[arg(long, value_name = "DIR")]
+    artifacts_dir: Option<PathBuf>,
+
+    /// Embed the request and response bodies of failed testcases in the JUnit report, cut off
+    /// after this many bytes
+    #[arg(long, value_name = "BYTES")]
+    evidence_max_bytes: Option<usize>,
+
+    /// Comma separated media types of the bodies embedded by --evidence-max-bytes. A * matches
+    /// any type or subtype, and *+json any subtype with that suffix
+    #[arg(
+        long,
+        value_name = "TYPES",
+        value_delimiter = ',',
+        default_value = "application/json,application/*+json,application/xml,application/*+xml,application/x-www-form-urlencoded,text/*",
+        requires = "evidence_max_bytes"
+    )]
+    evidence_content_types: Vec<String>,
+
+    /// Resolve a host and port to a specific address instead of using DNS. Can be repeated
+    #[arg(long, value_name = "HOST:PORT=ADDR")]
+    resolve: Vec<dns::ResolveOverride>,
+
+    /// PEM file of CA certificates to trust for https upstreams, in addition to the public roots
+    #[arg(long, value_name = "FILE", conflicts_with = "insecure_skip_verify")]
+    ca_bundle: Option<PathBuf>,
+
+    /// Don't verify the certificates of https upstreams. Only use this for test environments
+    #[arg(long)]
+    insecure_skip_verify: bool,
+
+    /// Use HTTP/2 for every upstream connection without negotiating it first. Needed for
+    /// upstreams that only accept cleartext HTTP/2 (h2c)
+    #[arg(long)]
+    http2_prior_knowledge: bool,
+
+    /// Seconds to cache DNS results for the upstream. Set to 0 to resolve on every request
+    #[arg(long, value_name = "SECONDS", default_value = "60")]
+    dns_cache_ttl: u64,
+
+    /// Maximum number of requests to proxy at the same time. Unlimited if not set
+    #[arg(long, value_name = "REQUESTS")]
+    max_concurrency: Option<usize>,
+
+    /// Maximum number of requests to queue once --max-concurrency is reached. Requests beyond
+    /// this are rejected with a 503
+    #[arg(long, value_name = "REQUESTS", default_value = "100")]
+    max_queue: usize,
+
+    /// Seconds sent in the Retry-After header of rejected requests
+    #[arg(long, value_name = "SECONDS", default_value = "1")]
+    retry_after: u64,
+
+    /// Number of validated bodies to remember so identical bodies aren't validated again. Set
+    /// to 0 to validate every body
+    #[arg(long, value_name = "BODIES", default_value = "10000")]
+    validation_cache_size: usize,
+
+    /// File to save every testcase to as it is recorded, so the reports can be served later
+    /// with serve-report
+    #[arg(long, value_name = "FILE")]
+    results: Option<PathBuf>,
+
+    /// File to write the JUnit report to when the proxy shuts down
+    #[arg(long, value_name = "FILE")]
+    junit_output: Option<PathBuf>,
+
+    /// Format of the report written on shutdown, for CI servers that don't read JUnit
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "junit")]
+    report_format: ReportFormat,
+
+    /// Split the JUnit report written on shutdown into one testsuite per tag or path
+    #[arg(long, value_enum, value_name = "GROUPING")]
+    junit_group_by: Option<ReportGrouping>,
+
+    /// Merge the testcases of requests that share a correlation ID into one testcase with a step
+    /// for each request in the JUnit report written on shutdown
+    #[arg(long)]
+    junit_merge_correlated: bool,
+
+    /// Reject requests that fail validation with a 400 instead of forwarding them, and replace
+    /// responses that fail validation with a 502
+    #[arg(long)]
+    enforce: bool,
+
+    /// What to do with requests whose path isn't in the spec
+    #[arg(long, value_enum, value_name = "MODE", default_value = "fail")]
+    unmatched_paths: UnmatchedPaths,
+
+    /// Don't add X-Forwarded-For, X-Forwarded-Proto, X-Forwarded-Host, and Forwarded headers to
+    /// upstream requests
+    #[arg(long)]
+    no_forwarded_headers: bool,
+
+    /// Send a W3C traceparent header to the upstream, propagating the client's trace or starting
+    /// a new one, and record its trace ID as a testcase property
+    #[arg(long)]
+    trace_context: bool,
+
+    /// Prefix of the proxy's own headers, like OVP-Correlation-Id. Overrides headers.prefix from
+    /// the config file
+    #[arg(long, value_name = "PREFIX")]
+    header_prefix: Option<headers::Prefix>,
+
+    /// Remove the proxy's own headers from requests before they are forwarded, like
+    /// headers.stripUpstream in the config file
+    #[arg(long)]
+    strip_proxy_headers: bool,
+
+    /// Largest request body to buffer and validate. Larger bodies are streamed to the upstream
+    /// without being validated
+    #[arg(long, value_name = "BYTES", default_value = "16777216")]
+    max_request_body: usize,
+
+    /// Largest response body to buffer and validate. Larger bodies are streamed to the client
+    /// without being validated
+    #[arg(long, value_name = "BYTES", default_value = "16777216")]
+    max_response_body: usize,
+
+    /// Forward the Host header the client sent instead of using the upstream's host
+    #[arg(long, conflicts_with = "override_host")]
+    preserve_host: bool,
+
+    /// Host header to send to the upstream instead of the upstream's host
+    #[arg(long, value_name = "HOST")]
+    override_host: Option<String>,
+
+    /// Path prefix to remove from requests before they are matched against the spec. Defaults to
+    /// the paths of the spec's servers and of the upstream URL
+    #[arg(long, value_name = "PATH")]
+    base_path: Option<String>,
+}
+
+/// How requests to paths that aren't in the spec are handled. They are always forwarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum UnmatchedPaths {
+    /// Record a PathNotFound failure
+    Fail,
+    /// Record a skipped testcase without any failures
+    Skip,
+    /// Don't record a testcase
+    Forward,
+}
+
+/// Configuration that can be supplied to the proxy with the --config flag.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Config {
+    validation: ValidationToggles,
+    /// Consumer URLs that webhooks are forwarded to after they are validated, keyed by the name
+    /// of the webhook in the spec.
+    webhooks: BTreeMap<String, url::Url>,
+    /// Invariants that are checked across requests that share a correlation ID.
+    sequences: Vec<sequences::Sequence>,
+    /// Report duplicate keys in JSON bodies. serde_json keeps the last value for a duplicate key
+    /// so these are ignored by default.
+    strict_json: bool,
+    /// Report int64 values that JavaScript consumers can't represent exactly.
+    int64_precision_warnings: bool,
+    /// How strictly date-time and date strings are checked.
+    date_time_format: datetime::DateTimeMode,
+    /// Settings that only apply to a single operation, keyed by operationId.
+    operations: BTreeMap<String, OperationConfig>,
+    /// Validators for custom string formats used by the spec, keyed by the name of the format.
+    formats: BTreeMap<String, formats::FormatValidator>,
+    /// Names of the headers clients use to control the proxy.
+    headers: headers::ProxyHeaders,
+    /// Failure types that are removed from testcases, like MissingContentTypeHeader. Each removed
+    /// failure is counted with a suppressedFailure property.
+    suppressed_failures: Vec<TestcaseFailureType>,
+    /// The severity of failure types that shouldn't fail the testcase. Every other failure type
+    /// is an error.
+    severities: BTreeMap<TestcaseFailureType, Severity>,
+    /// Upstreams that requests under a path prefix are sent to instead of the UPSTREAM argument.
+    upstreams: Vec<upstream::Route>,
+    /// Request and response headers recorded as properties of every testcase.
+    captured_headers: headers::CapturedHeaders,
+}
+
+/// Whether a failure fails its testcase. Warnings are only noted in the report, so new checks can
+/// be rolled out without breaking builds right away.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+/// Settings that override the top level of the config file for a single operation.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct OperationConfig {
+    date_time_format: Option<datetime::DateTimeMode>,
+}
+
+/// Optional checks performed on JSON bodies in addition to schema validation.
+#[derive(Debug, Clone, Default)]
+struct JsonChecks {
+    /// Report objects that contain the same key more than once.
+    duplicate_keys: bool,
+    /// Report int64 values above 2^53 - 1, the largest integer a JavaScript number holds exactly.
+    int64_precision: bool,
+    date_time: datetime::DateTimeMode,
+    formats: Arc<BTreeMap<String, formats::FormatValidator>>,
+}
+
+impl JsonChecks {
+    /// Applies the overrides from the config file for the operation, if there are any.
+    fn for_operation(
+        &self,
+        operation_id: Option<&str>,
+        operations: &BTreeMap<String, OperationConfig>,
+    ) -> Self {
+        let operation = operation_id.and_then(|operation_id| operations.get(operation_id));
+        Self {
+            date_time: operation
+                .and_then(|operation| operation.date_time_format)
+                .unwrap_or(self.date_time),
+            ..self.clone()
+        }
+    }
+}
+
+/// ValidationToggles controls which families of validation are performed. Every family is enabled
+/// by default. These can be set in the config file or changed while the proxy is running with the
+/// /_ovp/validation endpoint.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ValidationToggles {
+    status_codes: bool,
+    headers: bool,
+    parameters: bool,
+    security: bool,
+    request_body: bool,
+    response_body: bool,
+}
+
+impl Default for ValidationToggles {
+    fn default() -> Self {
+        Self {
+            status_codes: true,
+            headers: true,
+            parameters: true,
+            security: true,
+            request_body: true,
+            response_body: true,
+        }
+    }
+}
+
+impl ValidationToggles {
+    fn is_enabled(&self, family: Option<ValidationFamily>) -> bool {
+        match family {
+            Some(ValidationFamily::StatusCodes) => self.status_codes,
+            Some(ValidationFamily::Headers) => self.headers,
+            Some(ValidationFamily::Parameters) => self.parameters,
+            Some(ValidationFamily::Security) => self.security,
+            Some(ValidationFamily::RequestBody) => self.request_body,
+            Some(ValidationFamily::ResponseBody) => self.response_body,
+            None => true,
+        }
+    }
+}
+
+/// The families of validation that can be turned on or off with ValidationToggles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValidationFamily {
+    StatusCodes,
+    Headers,
+    Parameters,
+    Security,
+    RequestBody,
+    ResponseBody,
+}
+
 #[derive(Clone)]
 struct AppState {
-    spec: openapiv3::OpenAPI,
+    /// The spec requests are validated against. It is replaced when the spec is refreshed.
+    spec: Arc<std::sync::RwLock<Arc<LoadedSpec>>>,
     upstream: url::Url,
+    /// Upstreams for path prefixes, which take precedence over `upstream`.
+    upstreams: Vec<upstream::Route>,
+    /// Removed from request paths before they are matched against the spec, instead of the base
+    /// paths of the spec and the upstream.
+    base_path: Option<String>,
     testcases: Arc<Mutex<Vec<Testcase>>>,
-    wayfinder: wayfind::Router<()>,
+    metrics: Arc<std::sync::Mutex<Metrics>>,
+    validation_toggles: Arc<Mutex<ValidationToggles>>,
+    webhook_consumers: BTreeMap<String, url::Url>,
+    pending_links: Arc<Mutex<Vec<links::PendingLink>>>,
+    sequences: Vec<sequences::Sequence>,
+    sequence_events: Arc<Mutex<Vec<sequences::SequenceEvent>>>,
+    golden: Option<golden::Golden>,
+    /// Where the bodies of failed testcases are written, if anywhere.
+    artifacts: Option<artifacts::Artifacts>,
+    /// Which bodies of failed testcases are embedded in the report, if any.
+    evidence: Option<evidence::Capture>,
+    /// The client used for requests to the upstream and webhook consumers.
+    client: upstream::Client,
+    concurrency_limit: Option<Arc<limit::ConcurrencyLimit>>,
+    validation_cache: Arc<cache::ValidationCache>,
+    json_checks: JsonChecks,
+    operations: BTreeMap<String, OperationConfig>,
+    runs: Arc<Mutex<runs::Runs>>,
+    session: Session,
+    proxy_headers: headers::ProxyHeaders,
+    captured_headers: headers::CapturedHeaders,
+    /// Where testcases are saved as they are recorded, if anywhere.
+    results: Option<Arc<results::ResultsFile>>,
+    /// Where proxied requests and responses are recorded, if anywhere.
+    recording: Option<Arc<har::Recording>>,
+    /// Whether traffic that fails validation is rejected instead of passed through.
+    enforce: bool,
+    unmatched_paths: UnmatchedPaths,
+    /// Whether the forwarded headers are added to upstream requests.
+    forwarded_headers: bool,
+    /// Whether upstream requests carry a W3C trace context.
+    trace_context: bool,
+    /// The Host header sent to the upstream.
+    host: upstream::Host,
+    body_limits: body::Limits,
+    suppressed_failures: Vec<TestcaseFailureType>,
+    severities: BTreeMap<TestcaseFailureType, Severity>,
 }
 
 impl std::fmt::Debug for AppState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AppState")
-            .field("spec", &self.spec)
+            .field("spec", &self.current_spec())
             .field("upstream", &self.upstream)
+            .field("upstreams", &self.upstreams)
+            .field("base_path", &self.base_path)
             .field("testcases", &self.testcases)
+            .field("metrics", &self.metrics)
+            .field("validation_toggles", &self.validation_toggles)
+            .field("webhook_consumers", &self.webhook_consumers)
+            .field("pending_links", &self.pending_links)
+            .field("sequences", &self.sequences)
+            .field("sequence_events", &self.sequence_events)
+            .field("golden", &self.golden)
+            .field("artifacts", &self.artifacts)
+            .field("evidence", &self.evidence)
+            .field("client", &self.client)
+            .field("concurrency_limit", &self.concurrency_limit)
+            .field("validation_cache", &self.validation_cache)
+            .field("json_checks", &self.json_checks)
+            .field("operations", &self.operations)
+            .field("runs", &self.runs)
+            .field("session", &self.session)
+            .field("proxy_headers", &self.proxy_headers)
+            .field("captured_headers", &self.captured_headers)
+            .field("results", &self.results)
+            .field("recording", &self.recording)
+            .field("enforce", &self.enforce)
+            .field("unmatched_paths", &self.unmatched_paths)
+            .field("forwarded_headers", &self.forwarded_headers)
+            .field("trace_context", &self.trace_context)
+            .field("host", &self.host)
+            .field("body_limits", &self.body_limits)
+            .field("suppressed_failures", &self.suppressed_failures)
+            .field("severities", &self.severities)
+            .finish()
+    }
+}
+
+impl AppState {
+    /// The spec as it is now. Requests keep the spec they started with if it is refreshed while
+    /// they are handled.
+    fn current_spec(&self) -> Arc<LoadedSpec> {
+        self.spec.read().unwrap().clone()
+    }
+}
+
+/// A spec and everything the proxy derives from it. It is replaced as a whole when the spec is
+/// refreshed.
+struct LoadedSpec {
+    spec: openapiv3::OpenAPI,
+    /// Added to every testcase when the proxy was forced to validate against a spec whose version
+    /// is not supported.
+    unsupported_version: Option<TestcaseProperty>,
+    webhooks: BTreeMap<String, openapiv3::PathItem>,
+    wayfinder: wayfind::Router<()>,
+    /// The paths of the spec's servers, which the spec's path templates are relative to.
+    base_paths: Vec<String>,
+    /// The document as it was read, so a refresh can tell whether it changed.
+    content: String,
+    /// The file or URL the document was read from, which diagnostics show as its name.
+    name: String,
+}
+
+impl std::fmt::Debug for LoadedSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoadedSpec")
+            .field("spec", &self.spec)
+            .field("unsupported_version", &self.unsupported_version)
+            .field("webhooks", &self.webhooks)
             .field("wayfinder", &"wayfinder::Router<()>")
+            .field("base_paths", &self.base_paths)
+            .field("name", &self.name)
             .finish()
     }
 }
 
+/// The part of the state the reports are rendered from. The serve-report command serves the
+/// reports from saved testcases with only this state.
+#[derive(Clone, Debug)]
+struct ReportState {
+    testcases: Arc<Mutex<Vec<Testcase>>>,
+    runs: Arc<Mutex<runs::Runs>>,
+    session: Session,
+}
+
+/// When and where the testcases were recorded. Report aggregators use this to merge the reports of
+/// parallel shards.
+#[derive(Clone, Debug)]
+struct Session {
+    started: std::time::SystemTime,
+    hostname: String,
+}
+
+impl Session {
+    fn new(started: std::time::SystemTime) -> Self {
+        Session {
+            started,
+            hostname: hostname(),
+        }
+    }
+}
+
+/// The name of the machine the proxy is running on, or localhost if it can't be found.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+impl FromRef<AppState> for ReportState {
+    fn from_ref(state: &AppState) -> Self {
+        ReportState {
+            testcases: state.testcases.clone(),
+            runs: state.runs.clone(),
+            session: state.session.clone(),
+        }
+    }
+}
+
+/// Concurrency metrics for the requests that are being proxied. These are exposed through the
+/// /_ovp/metrics endpoint.
+#[derive(Debug, Default)]
+struct Metrics {
+    in_flight: usize,
+    peak_in_flight: usize,
+    total_requests: usize,
+    /// The number of requests rejected because the concurrency limit and queue were full.
+    rejected_requests: usize,
+    /// The number of active requests for each operation, keyed by "{method} {path template}".
+    active_operations: BTreeMap<String, usize>,
+    /// The number of requests every operation has received, keyed the same as active_operations.
+    /// Operations that haven't received any requests aren't included.
+    exercised_operations: BTreeMap<String, usize>,
+    request_body_bytes: BodySizes,
+    response_body_bytes: BodySizes,
+}
+
+/// The sizes of the bodies the proxy has buffered, as they were sent. Streamed bodies aren't
+/// included because their size isn't known.
+#[derive(Debug, Default, Serialize)]
+struct BodySizes {
+    count: usize,
+    total: usize,
+    min: Option<usize>,
+    max: Option<usize>,
+}
+
+impl BodySizes {
+    fn record(&mut self, bytes: usize) {
+        self.count += 1;
+        self.total += bytes;
+        self.min = Some(self.min.map_or(bytes, |min| min.min(bytes)));
+        self.max = Some(self.max.map_or(bytes, |max| max.max(bytes)));
+    }
+}
+
+/// InFlightGuard marks a request as in-flight for as long as it is alive. Dropping the guard
+/// removes the request from the metrics, even if the handler returns early.
+struct InFlightGuard {
+    metrics: Arc<std::sync::Mutex<Metrics>>,
+    operation: Option<String>,
+}
+
+impl InFlightGuard {
+    fn new(metrics: Arc<std::sync::Mutex<Metrics>>) -> Self {
+        let mut m = metrics.lock().unwrap();
+        m.in_flight += 1;
+        m.total_requests += 1;
+        m.peak_in_flight = m.peak_in_flight.max(m.in_flight);
+        drop(m);
+        Self {
+            metrics,
+            operation: None,
+        }
+    }
+
+    fn set_operation(&mut self, operation: String) {
+        let mut m = self.metrics.lock().unwrap();
+        *m.active_operations.entry(operation.clone()).or_insert(0) += 1;
+        *m.exercised_operations.entry(operation.clone()).or_insert(0) += 1;
+        self.operation = Some(operation);
+    }
+
+    /// Records the size of a request body, and returns it as a testcase property.
+    fn request_body(&self, bytes: usize) -> TestcaseProperty {
+        self.metrics
+            .lock()
+            .unwrap()
+            .request_body_bytes
+            .record(bytes);
+        TestcaseProperty {
+            name: "requestBodyBytes".to_string(),
+            value: bytes.to_string(),
+        }
+    }
+
+    /// Records the size of a response body, and returns it as a testcase property.
+    fn response_body(&self, bytes: usize) -> TestcaseProperty {
+        self.metrics
+            .lock()
+            .unwrap()
+            .response_body_bytes
+            .record(bytes);
+        TestcaseProperty {
+            name: "responseBodyBytes".to_string(),
+            value: bytes.to_string(),
+        }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut m = self.metrics.lock().unwrap();
+        m.in_flight -= 1;
+        if let Some(operation) = &self.operation {
+            if let Some(count) = m.active_operations.get_mut(operation) {
+                *count -= 1;
+                if *count == 0 {
+                    m.active_operations.remove(operation);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Template)]
 #[template(path = "junit.xml")]
 struct JunitTemplate {
+    suites: Vec<JunitSuite>,
+}
+
+#[derive(Debug, Clone)]
+struct JunitSuite {
+    name: String,
     testcases: Vec<Testcase>,
     failed_testcases: usize,
+    /// The total time of the testcases in seconds.
+    time: String,
+    /// When the session started, in the ISO 8601 format without a timezone that JUnit uses. The
+    /// time is in UTC.
+    timestamp: String,
+    hostname: String,
+}
+
+/// An xUnit.net v2 report, for .NET pipelines that can't read JUnit. Its collections are the
+/// testsuites of the JUnit report.
+#[derive(Debug, Clone, Template)]
+#[template(path = "xunit.xml")]
+struct XunitTemplate {
+    suites: Vec<JunitSuite>,
+    run_date: String,
+    run_time: String,
+    hostname: String,
+    totals: ReportTotals,
+}
+
+/// An NUnit 3 report, for Azure DevOps. Its TestFixture suites are the testsuites of the JUnit
+/// report.
+#[derive(Debug, Clone, Template)]
+#[template(path = "nunit.xml")]
+struct NunitTemplate {
+    suites: Vec<JunitSuite>,
+    start_time: String,
+    end_time: String,
+    hostname: String,
+    totals: ReportTotals,
 }
 
+/// The number of testcases in all the testsuites of a report by result, and their total time.
 #[derive(Debug, Clone)]
+struct ReportTotals {
+    total: usize,
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    time: String,
+}
+
+impl ReportTotals {
+    fn new(suites: &[JunitSuite]) -> Self {
+        let time = suites
+            .iter()
+            .flat_map(|suite| suite.testcases.iter())
+            .filter_map(|testcase| testcase.time.parse::<f64>().ok())
+            .sum::<f64>();
+        ReportTotals {
+            total: suites.iter().map(|suite| suite.testcases.len()).sum(),
+            passed: suites.iter().map(JunitSuite::passed).sum(),
+            failed: suites.iter().map(|suite| suite.failed_testcases).sum(),
+            skipped: suites.iter().map(JunitSuite::skipped).sum(),
+            time: format!("{:.2}", time),
+        }
+    }
+
+    fn nunit_result(&self) -> &'static str {
+        match self.failed {
+            0 => "Passed",
+            _ => "Failed",
+        }
+    }
+}
+
+impl JunitSuite {
+    /// The number of testcases that were neither failed nor skipped.
+    fn passed(&self) -> usize {
+        self.testcases.len() - self.failed_testcases - self.skipped()
+    }
+
+    /// The number of skipped testcases without failures.
+    fn skipped(&self) -> usize {
+        self.testcases
+            .iter()
+            .filter(|testcase| testcase.failures.is_empty() && testcase.skipped.is_some())
+            .count()
+    }
+
+    fn new(name: String, testcases: Vec<Testcase>, session: &Session) -> Self {
+        let failed_testcases = testcases
+            .iter()
+            .filter(|testcase| !testcase.failures.is_empty())
+            .count();
+        let time = testcases
+            .iter()
+            .filter_map(|testcase| testcase.time.parse::<f64>().ok())
+            .sum::<f64>();
+        Self {
+            name,
+            testcases,
+            failed_testcases,
+            time: format!("{:.2}", time),
+            timestamp: datetime::format_date_time(session.started)[..19].to_string(),
+            hostname: session.hostname.clone(),
+        }
+    }
+}
+
+/// Query parameters accepted by the report endpoints.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ReportQuery {
+    /// Only include testcases for operations with this tag.
+    tag: Option<String>,
+    group_by: Option<ReportGrouping>,
+    /// Merge the testcases of requests that share a correlation ID into one testcase.
+    merge_correlated: bool,
+}
+
+/// How a JUnit report is split into testsuites. Without a grouping every testcase is in one
+/// testsuite.
+#[derive(Debug, Clone, Copy, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+enum ReportGrouping {
+    /// One testsuite per tag. Testcases for operations with several tags are included in each of
+    /// their testsuites.
+    Tag,
+    /// One testsuite per first segment of the path template, like /pets. Testcases that didn't
+    /// match an operation are grouped by the path they requested.
+    Path,
+}
+
+/// The formats the report written on shutdown can be in.
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum ReportFormat {
+    Junit,
+    /// xUnit.net v2
+    Xunit,
+    /// NUnit 3
+    Nunit,
+    /// Common Test Report Format JSON
+    Ctrf,
+}
+
+impl std::fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportFormat::Junit => write!(f, "JUnit"),
+            ReportFormat::Xunit => write!(f, "xUnit.net"),
+            ReportFormat::Nunit => write!(f, "NUnit"),
+            ReportFormat::Ctrf => write!(f, "CTRF"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 struct Testcase {
     name: String,
     failures: Vec<TestcaseFailure>,
     properties: Vec<TestcaseProperty>,
     time: String,
+    /// A curl command that reproduces the request, only included when the testcase failed.
+    reproduction: Option<String>,
+    /// Why the request wasn't validated, for requests that are only recorded so they show up in
+    /// the report.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+    /// Failures whose type is configured as a warning. They don't fail the testcase.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<TestcaseFailure>,
+    /// When the testcase was recorded, as an RFC 3339 date-time in UTC. Missing from testcases
+    /// saved by older versions of the proxy.
+    #[serde(
+        rename = "recordedAt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    recorded_at: Option<String>,
+    /// The request and response bodies of a failed testcase, when --evidence-max-bytes is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    evidence: Vec<evidence::Evidence>,
+}
+
+impl Testcase {
+    /// The name of the run that was active when the testcase was recorded.
+    fn run(&self) -> Option<&String> {
+        self.properties
+            .iter()
+            .find(|property| property.name == "run")
+            .map(|property| &property.value)
+    }
+
+    /// The OVP-Correlation-Id of the request the testcase was recorded for.
+    fn correlation_id(&self) -> Option<&String> {
+        self.properties
+            .iter()
+            .find(|property| property.name == "correlationId")
+            .map(|property| &property.value)
+    }
+
+    /// The tenant whose client sent the request, if it sent one.
+    fn tenant(&self) -> Option<&String> {
+        self.properties
+            .iter()
+            .find(|property| property.name == "tenant")
+            .map(|property| &property.value)
+    }
+
+    /// The first segment of the path template the testcase matched, or of its path if it didn't
+    /// match one.
+    fn path_group(&self) -> String {
+        let path = ["pathTemplate", "path"].iter().find_map(|name| {
+            self.properties
+                .iter()
+                .find(|property| property.name == *name)
+                .map(|property| property.value.as_str())
+        });
+        let segment = path
+            .unwrap_or_default()
+            .split('/')
+            .find(|segment| !segment.is_empty())
+            .unwrap_or_default();
+        format!("/{}", segment)
+    }
+
+    /// The tags of the operation the testcase matched.
+    fn tags(&self) -> impl Iterator<Item = &str> {
+        self.properties
+            .iter()
+            .filter(|property| property.name == "tag")
+            .map(|property| property.value.as_str())
+    }
+
+    /// The value of the first property with the name.
+    fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| property.value.as_str())
+    }
+
+    /// A readable summary of the exchange for the testcase's system-out, which CI servers show
+    /// inline: the request line, the response status, their content types and where each failure
+    /// was found.
+    fn summary(&self) -> String {
+        let with_content_type = |line: String, content_type: Option<&str>| match content_type {
+            Some(content_type) if !content_type.is_empty() => {
+                format!("{} ({})", line, content_type)
+            }
+            _ => line,
+        };
+        let mut lines = vec![];
+        if let (Some(method), Some(path)) = (self.property("method"), self.property("path")) {
+            let query = self
+                .property("query")
+                .map(|query| format!("?{}", query))
+                .unwrap_or_default();
+            lines.push(with_content_type(
+                format!("Request: {} {}{}", method, path, query),
+                self.property("requestContentType"),
+            ));
+        }
+        if let Some(status) = self.property("statusCode") {
+            lines.push(with_content_type(
+                format!("Response: {}", status),
+                self.property("responseContentType"),
+            ));
+        }
+        if let Some(status) = self.property("enforcedStatus") {
+            lines.push(format!("Enforced: {}", status));
+        }
+        if !self.failures.is_empty() {
+            lines.push("Failures:".to_string());
+        }
+        for failure in self.failures.iter() {
+            let mut line = format!("- {}", failure.r#type);
+            if let Some(json_pointer) = &failure.json_pointer {
+                line.push_str(&format!(" at {}", json_pointer));
+            }
+            if let Some(spec_pointer) = &failure.spec_pointer {
+                line.push_str(&format!(" (defined at {})", spec_pointer));
+            }
+            lines.push(line);
+        }
+        if !self.evidence.is_empty() {
+            let evidence = self
+                .evidence
+                .iter()
+                .map(|evidence| evidence.name.as_str())
+                .collect::<Vec<_>>();
+            lines.push(format!("Evidence: {}", evidence.join(", ")));
+        }
+        lines.join("\n")
+    }
+
+    /// The result of the testcase in an NUnit report.
+    fn nunit_result(&self) -> &'static str {
+        match (self.failures.is_empty(), &self.skipped) {
+            (false, _) => "Failed",
+            (true, Some(_)) => "Skipped",
+            (true, None) => "Passed",
+        }
+    }
+
+    /// The result of the testcase in an xUnit.net report.
+    fn xunit_result(&self) -> &'static str {
+        match (self.failures.is_empty(), &self.skipped) {
+            (false, _) => "Fail",
+            (true, Some(_)) => "Skip",
+            (true, None) => "Pass",
+        }
+    }
+
+    /// Every failure of the testcase with its type, and the evidence, for report formats that only
+    /// allow a single failure per testcase.
+    fn failure_message(&self) -> String {
+        let mut message = self
+            .failures
+            .iter()
+            .map(|failure| format!("{}:\n{}", failure.r#type, failure.text))
+            .collect::<Vec<_>>();
+        for evidence in self.evidence.iter() {
+            let truncated = match evidence.truncated {
+                0 => String::new(),
+                truncated => format!("\n[{} more bytes]", truncated),
+            };
+            message.push(format!(
+                "{} ({}):\n{}{}",
+                evidence.name, evidence.content_type, evidence.body, truncated
+            ));
+        }
+        message.join("\n\n")
+    }
+
+    /// The classname of the testcase in the JUnit report, which CI servers group testcases by. It
+    /// is the operationId of the operation the testcase matched, or its first tag if it doesn't
+    /// have one.
+    fn classname(&self) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|property| property.name == "operationId")
+            .map(|property| property.value.as_str())
+            .or_else(|| self.tags().next())
+    }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Deserialize, Serialize)]
 struct TestcaseProperty {
     name: String,
     value: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct TestcaseFailure {
     text: String,
     r#type: TestcaseFailureType,
+    /// Where in the body the failure was found. Used to highlight the value in the report.
+    json_pointer: Option<String>,
+    /// Where in the spec the definition that caused the failure is, for failures that point to a
+    /// problem in the spec rather than the traffic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spec_pointer: Option<String>,
+    /// Whether a body failure was found in a request or a response body, which are toggled
+    /// separately. Failures that aren't about a body don't have one.
+    #[serde(skip)]
+    perspective: Option<ValidationPerspective>,
+}
+
+impl TestcaseFailure {
+    /// The family of validation this failure belongs to.
+    fn family(&self) -> Option<ValidationFamily> {
+        self.r#type.family(self.perspective)
+    }
 }
 
 /// An enum describing the type of test failure that occurred.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 enum TestcaseFailureType {
     /// The requested path was not found in the OpenAPI spec. This response was not validated
     /// and may be missing relevant testcase properties.
     PathNotFound,
-    /// The HTTP method used in the request is not one of the expected values:
-    /// DELETE, GET, HEAD, OPTIONS, PATCH, POST, PUT, or TRACE.
+    /// The spec doesn't define an operation for the HTTP method used in the request, either on
+    /// this path or at all because the method is not one of DELETE, GET, HEAD, OPTIONS, PATCH,
+    /// POST, PUT, or TRACE. Requests with extension methods like PURGE are still forwarded.
     InvalidHTTPMethod,
     /// The status code returned by the upstream server does not have a matching response in the OpenAPI spec.
+    /// Responses for the range of the status code, like 4XX, and default responses also match.
     InvalidStatusCode,
     /// The OpenAPI spec contained a missing inline response definition or referenced a response that did not exist.
     MissingResponseDefinition,
@@ -106,26 +1133,218 @@ enum TestcaseFailureType {
     /// issue when the response body is not empty.
     MissingContentTypeHeader,
     /// The upstream server included a Content-Type header in the response that does not match any
-    /// content types defined in the OpenAPI spec.
+    /// content types defined in the OpenAPI spec, or included more than one distinct Content-Type
+    /// header.
     MismatchedContentTypeHeader,
     /// The upstream server included a non-empty response body when the OpenAPI spec expects an empty body.
     MismatchNonEmptyBody,
     /// The OpenAPI spec contained a missing inline schema definition or referenced a schema that did not exist.
     MissingSchemaDefinition,
-    /// The response body could not be deserialized as JSON.
+    /// The response body could not be deserialized as JSON. For NDJSON bodies this is reported for
+    /// each line that could not be deserialized.
     FailedJSONDeserialization,
     /// The response body contains a null value when the OpenAPI spec did not allow null values.
+    #[serde(rename = "FailedValidation.UnexpectedNull")]
     FailedValidationUnexpectedNull,
     /// The response body contained a boolean value when the OpenAPI spec expected a different type.
+    #[serde(rename = "FailedValidation.UnexpectedBoolean")]
     FailedValidationUnexpectedBoolean,
-    /// The response body contained a number value when the OpenAPI spec expected a different type.
+    /// The response body contained a number value when the OpenAPI spec expected a different type,
+    /// or a fractional number like 1.5 when the OpenAPI spec expected an integer.
+    #[serde(rename = "FailedValidation.UnexpectedNumber")]
     FailedValidationUnexpectedNumber,
     /// The response body contained a string value when the OpenAPI spec expected a different type.
+    #[serde(rename = "FailedValidation.UnexpectedString")]
     FailedValidationUnexpectedString,
+    /// The response body contained a number that is below the minimum or above the maximum of its
+    /// schema, or that does not fit in its int32, int64, float, or double format.
+    #[serde(rename = "FailedValidation.OutOfRange")]
+    FailedValidationOutOfRange,
+    /// The response body contained a number that is not a multiple of the multipleOf of its
+    /// schema.
+    #[serde(rename = "FailedValidation.NotMultipleOf")]
+    FailedValidationNotMultipleOf,
+    /// The response body contained an array with fewer items than the minItems or more items than
+    /// the maxItems of its schema.
+    #[serde(rename = "FailedValidation.InvalidArrayLength")]
+    FailedValidationInvalidArrayLength,
+    /// The response body contained an array with the same item more than once when its schema
+    /// requires uniqueItems.
+    #[serde(rename = "FailedValidation.DuplicateArrayItem")]
+    FailedValidationDuplicateArrayItem,
     /// The response body contained a property that was not defined in the OpenAPI spec.
+    #[serde(rename = "FailedValidation.UnexpectedProperty")]
     FailedValidationUnexpectedProperty,
-    /// The OpenAPI spec contained a schema with an unsupported kind, such as anyOf, oneOf, or not.
+    /// The response body contained an object without a property that the OpenAPI spec lists as
+    /// required.
+    #[serde(rename = "FailedValidation.MissingRequiredProperty")]
+    FailedValidationMissingRequiredProperty,
+    /// The OpenAPI spec contained a schema with an unsupported kind, such as not.
+    #[serde(rename = "FailedValidation.UnsupportedSchemaKind")]
     FailedValidationUnsupportedSchemaKind,
+    /// The response body contained a value that did not match any of the schemas in a oneOf or
+    /// anyOf. The failure includes why each schema was rejected and which one came closest to
+    /// matching.
+    #[serde(rename = "FailedValidation.NoMatchingSchema")]
+    FailedValidationNoMatchingSchema,
+    /// The response body contained a value that matched more than one of the schemas in a oneOf.
+    #[serde(rename = "FailedValidation.MultipleMatchingSchemas")]
+    FailedValidationMultipleMatchingSchemas,
+    /// The response body contained a value that matched the schema of a not, which it must not
+    /// match.
+    #[serde(rename = "FailedValidation.MatchedNotSchema")]
+    FailedValidationMatchedNotSchema,
+    /// A request body contained a property that the OpenAPI spec marks as readOnly, which may only
+    /// be sent in responses.
+    #[serde(rename = "FailedValidation.ReadOnlyProperty")]
+    FailedValidationReadOnlyProperty,
+    /// A response body contained a property that the OpenAPI spec marks as writeOnly, which may
+    /// only be sent in requests.
+    #[serde(rename = "FailedValidation.WriteOnlyProperty")]
+    FailedValidationWriteOnlyProperty,
+    /// A request was sent to /_ovp/callback/{operationId} but the operation does not exist or does
+    /// not declare a callback for the HTTP method that was used.
+    CallbackNotFound,
+    /// A request was sent to /_ovp/webhook/{name} but the spec does not declare a webhook with
+    /// that name for the HTTP method that was used.
+    WebhookNotFound,
+    /// The request did not include a header that the OpenAPI spec marks as required.
+    MissingRequiredHeader,
+    /// A query, header, or path parameter of the request did not match its schema once it was
+    /// decoded according to its style and explode, or a required query or path parameter was
+    /// missing.
+    InvalidParameter,
+    /// The request did not carry the credentials of any of the security requirements of the
+    /// operation, like an Authorization header or an API key.
+    #[serde(rename = "Request.MissingSecurityCredentials")]
+    RequestMissingSecurityCredentials,
+    /// An earlier response with the same correlation ID contained a link to this operation, but
+    /// the request did not use the parameter values that the link specified.
+    LinkParameterMismatch,
+    /// A sequence from the config file expected this request to respond with a different status
+    /// code because of an earlier request with the same correlation ID.
+    SequenceViolation,
+    /// The shape of the response body is different than the golden file that was recorded for
+    /// the operation and status code. Only reported with --golden-mode verify.
+    GoldenResponseDrift,
+    /// A header in the request or response had a value that is not valid UTF-8, so it could not
+    /// be validated or forwarded.
+    MalformedHeader,
+    /// The body could not be transcoded to UTF-8 using the charset from its Content-Type header,
+    /// either because the charset is not supported or the body is not valid in that charset. Also
+    /// reported when a response body could not be decompressed using its Content-Encoding header.
+    DecodingFailed,
+    /// The JSON body contains an object with the same key more than once. Only reported when
+    /// strictJson is enabled in the config file.
+    DuplicateJSONKey,
+    /// An int64 value is larger than JavaScript numbers can represent exactly, so web clients will
+    /// silently round it. Only reported when int64PrecisionWarnings is enabled in the config file.
+    Int64PrecisionLoss,
+    /// The response body contained a date-time or date string that is not valid RFC 3339.
+    #[serde(rename = "FailedValidation.InvalidDateTime")]
+    FailedValidationInvalidDateTime,
+    /// A date-time or date string deviates from RFC 3339 in a way that is tolerated because the
+    /// operation uses the lenient dateTimeFormat, like a missing timezone. These are reported
+    /// separately so they can be tracked without being mixed in with hard failures.
+    LenientDateTime,
+    /// The response body contained a string that did not pass the checker for its format, like
+    /// `email` or `uuid`, or the validator registered for its custom format in the config file.
+    #[serde(rename = "FailedValidation.InvalidFormat")]
+    FailedValidationInvalidFormat,
+    /// The request or response body was larger than --max-request-body or --max-response-body,
+    /// as it was sent or once it was decompressed. The body was forwarded without being validated.
+    BodyTooLargeToValidate,
+    /// The proxy could not handle the request. The client received a 502 when the upstream could
+    /// not be reached or its response ended early, a 400 when the request body could not be read,
+    /// and a 500 for anything unexpected. The failure contains the diagnostic. This is a problem
+    /// with the proxy or its setup rather than the API, and the request was not validated.
+    InternalProxyError,
+}
+
+/// Whether a body being validated was sent in a request or a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValidationPerspective {
+    Request,
+    Response,
+}
+
+impl ValidationPerspective {
+    /// Returns true if a property with the schema must not be sent in this perspective, because
+    /// it is readOnly and in a request or writeOnly and in a response.
+    fn forbids(self, schema_data: &openapiv3::SchemaData) -> bool {
+        match self {
+            ValidationPerspective::Request => schema_data.read_only,
+            ValidationPerspective::Response => schema_data.write_only,
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationPerspective {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValidationPerspective::Request => write!(f, "request"),
+            ValidationPerspective::Response => write!(f, "response"),
+        }
+    }
+}
+
+impl TestcaseFailureType {
+    /// The family of validation this failure belongs to. Failures without a family, like
+    /// PathNotFound, are always reported. Body failures belong to the request body family when
+    /// they were found in a request, and to the response body family otherwise.
+    fn family(&self, perspective: Option<ValidationPerspective>) -> Option<ValidationFamily> {
+        match self {
+            TestcaseFailureType::PathNotFound
+            | TestcaseFailureType::InvalidHTTPMethod
+            | TestcaseFailureType::CallbackNotFound
+            | TestcaseFailureType::WebhookNotFound
+            | TestcaseFailureType::LinkParameterMismatch
+            | TestcaseFailureType::SequenceViolation
+            | TestcaseFailureType::GoldenResponseDrift
+            | TestcaseFailureType::BodyTooLargeToValidate
+            | TestcaseFailureType::InternalProxyError => None,
+            TestcaseFailureType::InvalidStatusCode
+            | TestcaseFailureType::MissingResponseDefinition => Some(ValidationFamily::StatusCodes),
+            TestcaseFailureType::MissingContentTypeHeader
+            | TestcaseFailureType::MismatchedContentTypeHeader
+            | TestcaseFailureType::MissingRequiredHeader
+            | TestcaseFailureType::MalformedHeader => Some(ValidationFamily::Headers),
+            TestcaseFailureType::InvalidParameter => Some(ValidationFamily::Parameters),
+            TestcaseFailureType::RequestMissingSecurityCredentials => {
+                Some(ValidationFamily::Security)
+            }
+            TestcaseFailureType::MismatchNonEmptyBody
+            | TestcaseFailureType::MissingSchemaDefinition
+            | TestcaseFailureType::FailedJSONDeserialization
+            | TestcaseFailureType::DecodingFailed
+            | TestcaseFailureType::DuplicateJSONKey
+            | TestcaseFailureType::Int64PrecisionLoss
+            | TestcaseFailureType::LenientDateTime
+            | TestcaseFailureType::FailedValidationInvalidDateTime
+            | TestcaseFailureType::FailedValidationInvalidFormat
+            | TestcaseFailureType::FailedValidationUnexpectedNull
+            | TestcaseFailureType::FailedValidationUnexpectedBoolean
+            | TestcaseFailureType::FailedValidationUnexpectedNumber
+            | TestcaseFailureType::FailedValidationUnexpectedString
+            | TestcaseFailureType::FailedValidationOutOfRange
+            | TestcaseFailureType::FailedValidationNotMultipleOf
+            | TestcaseFailureType::FailedValidationInvalidArrayLength
+            | TestcaseFailureType::FailedValidationDuplicateArrayItem
+            | TestcaseFailureType::FailedValidationUnexpectedProperty
+            | TestcaseFailureType::FailedValidationMissingRequiredProperty
+            | TestcaseFailureType::FailedValidationUnsupportedSchemaKind
+            | TestcaseFailureType::FailedValidationNoMatchingSchema
+            | TestcaseFailureType::FailedValidationMultipleMatchingSchemas
+            | TestcaseFailureType::FailedValidationMatchedNotSchema
+            | TestcaseFailureType::FailedValidationReadOnlyProperty
+            | TestcaseFailureType::FailedValidationWriteOnlyProperty => match perspective {
+                Some(ValidationPerspective::Request) => Some(ValidationFamily::RequestBody),
+                Some(ValidationPerspective::Response) | None => {
+                    Some(ValidationFamily::ResponseBody)
+                }
+            },
+        }
+    }
 }
 
 impl std::fmt::Display for TestcaseFailureType {
@@ -163,13 +1382,74 @@ impl std::fmt::Display for TestcaseFailureType {
             TestcaseFailureType::FailedValidationUnexpectedProperty => {
                 write!(f, "FailedValidation.UnexpectedProperty")
             }
+            TestcaseFailureType::FailedValidationOutOfRange => {
+                write!(f, "FailedValidation.OutOfRange")
+            }
+            TestcaseFailureType::FailedValidationNotMultipleOf => {
+                write!(f, "FailedValidation.NotMultipleOf")
+            }
+            TestcaseFailureType::FailedValidationInvalidArrayLength => {
+                write!(f, "FailedValidation.InvalidArrayLength")
+            }
+            TestcaseFailureType::FailedValidationDuplicateArrayItem => {
+                write!(f, "FailedValidation.DuplicateArrayItem")
+            }
+            TestcaseFailureType::FailedValidationMissingRequiredProperty => {
+                write!(f, "FailedValidation.MissingRequiredProperty")
+            }
             TestcaseFailureType::FailedValidationUnsupportedSchemaKind => {
                 write!(f, "FailedValidation.UnsupportedSchemaKind")
             }
+            TestcaseFailureType::FailedValidationNoMatchingSchema => {
+                write!(f, "FailedValidation.NoMatchingSchema")
+            }
+            TestcaseFailureType::FailedValidationMultipleMatchingSchemas => {
+                write!(f, "FailedValidation.MultipleMatchingSchemas")
+            }
+            TestcaseFailureType::FailedValidationMatchedNotSchema => {
+                write!(f, "FailedValidation.MatchedNotSchema")
+            }
+            TestcaseFailureType::FailedValidationReadOnlyProperty => {
+                write!(f, "FailedValidation.ReadOnlyProperty")
+            }
+            TestcaseFailureType::FailedValidationWriteOnlyProperty => {
+                write!(f, "FailedValidation.WriteOnlyProperty")
+            }
+            TestcaseFailureType::FailedValidationInvalidDateTime => {
+                write!(f, "FailedValidation.InvalidDateTime")
+            }
+            TestcaseFailureType::FailedValidationInvalidFormat => {
+                write!(f, "FailedValidation.InvalidFormat")
+            }
+            TestcaseFailureType::CallbackNotFound => write!(f, "CallbackNotFound"),
+            TestcaseFailureType::WebhookNotFound => write!(f, "WebhookNotFound"),
+            TestcaseFailureType::MissingRequiredHeader => write!(f, "MissingRequiredHeader"),
+            TestcaseFailureType::InvalidParameter => write!(f, "InvalidParameter"),
+            TestcaseFailureType::RequestMissingSecurityCredentials => {
+                write!(f, "Request.MissingSecurityCredentials")
+            }
+            TestcaseFailureType::GoldenResponseDrift => write!(f, "GoldenResponseDrift"),
+            TestcaseFailureType::MalformedHeader => write!(f, "MalformedHeader"),
+            TestcaseFailureType::DecodingFailed => write!(f, "DecodingFailed"),
+            TestcaseFailureType::DuplicateJSONKey => write!(f, "DuplicateJSONKey"),
+            TestcaseFailureType::Int64PrecisionLoss => write!(f, "Int64PrecisionLoss"),
+            TestcaseFailureType::LenientDateTime => write!(f, "LenientDateTime"),
+            TestcaseFailureType::LinkParameterMismatch => write!(f, "LinkParameterMismatch"),
+            TestcaseFailureType::SequenceViolation => write!(f, "SequenceViolation"),
+            TestcaseFailureType::BodyTooLargeToValidate => write!(f, "BodyTooLargeToValidate"),
+            TestcaseFailureType::InternalProxyError => write!(f, "InternalProxyError"),
         }
     }
 }
 
+/// A response from the upstream. The body is decompressed, and is None when it was too large for
+/// the validation buffer or couldn't be decompressed, so it isn't validated.
+struct UpstreamResponse {
+    status: u16,
+    headers: axum::http::HeaderMap,
+    body: Option<Vec<u8>>,
+}
+
 struct ValidatedResponse {
     body: Vec<u8>,
     failures: Vec<TestcaseFailure>,
@@ -178,30 +1458,305 @@ struct ValidatedResponse {
     method: axum::http::Method,
     properties: Vec<TestcaseProperty>,
     status: u16,
+    operation_id: Option<String>,
+    /// The links defined by the spec response that matched, keyed by the name of the link.
+    links: Vec<(String, openapiv3::Link)>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    logging::init(&cli.log)?;
 
     match &cli.command {
-        Commands::Proxy {
-            file,
-            upstream,
-            port,
-        } => {
-            println!(
-                "Starting proxy server with file: {:?}, upstream: {}",
-                file, upstream
+        Commands::Proxy(proxy) => run_proxy(proxy, ProxyMode::Serve).await?,
+        Commands::Record { har, proxy } => run_proxy(proxy, ProxyMode::Record { har }).await?,
+        Commands::Replay { har, proxy, output } => {
+            run_proxy(
+                proxy,
+                ProxyMode::Replay {
+                    har,
+                    output: output.as_ref(),
+                },
+            )
+            .await?
+        }
+        Commands::Diff { old, new } => {
+            let (old, _) = parse_versioned_spec(&std::fs::read_to_string(old)?, false)?;
+            let (new, _) = parse_versioned_spec(&std::fs::read_to_string(new)?, false)?;
+            let changes = diff::diff_specs(&old, &new);
+            for change in changes.iter() {
+                println!("{}", change);
+            }
+            if !changes.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Check { file } => {
+            let (spec, _) = parse_versioned_spec(&std::fs::read_to_string(file)?, false)?;
+            let lints = check::check_spec(&spec);
+            for lint in lints.iter() {
+                println!("{}", lint);
+            }
+            if !lints.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Validate { file } => {
+            let content = std::fs::read_to_string(file)?;
+            let problems = validate::validate_spec(&file.display().to_string(), &content);
+            for problem in problems.iter() {
+                println!("{}", validate::render(problem));
+            }
+            match problems.len() {
+                0 => println!("{} is valid", file.display()),
+                1 => {
+                    println!("Found 1 problem in {}", file.display());
+                    std::process::exit(1);
+                }
+                count => {
+                    println!("Found {} problems in {}", count, file.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::ServeReport { file, port } => {
+            let (session, testcases) = results::load(file)?;
+            eprintln!(
+                "Serving reports for {} testcases from {:?} on port {}",
+                testcases.len(),
+                file,
+                port
             );
-            let metadata = std::fs::metadata(file)?;
-            if metadata.is_file() {
-                let content = std::fs::read_to_string(file)?;
-                let spec = parse_openapi_spec(&content)?;
-                start_server(spec, upstream.clone(), port.unwrap_or(3000)).await;
-            } else {
-                return Err(format!("Error: {:?} is not a file", file).into());
+            serve_report(session, testcases, *port).await;
+        }
+    }
+    Ok(())
+}
+
+/// What the proxy does with traffic besides validating it.
+enum ProxyMode<'a> {
+    /// Proxies requests until it is shut down.
+    Serve,
+    /// Proxies requests until it is shut down, recording them to a HAR file.
+    Record { har: &'a PathBuf },
+    /// Sends itself the requests recorded in a HAR file and shuts down once they are validated.
+    Replay {
+        har: &'a PathBuf,
+        output: Option<&'a PathBuf>,
+    },
+}
+
+async fn run_proxy(
+    proxy: &ProxyArgs,
+    mode: ProxyMode<'_>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ProxyArgs {
+        file,
+        upstream,
+        port,
+        config,
+        force,
+        allow_remote_refs,
+        spec_refresh_interval,
+        golden_dir,
+        golden_mode,
+        artifacts_dir,
+        evidence_max_bytes,
+        evidence_content_types,
+        resolve,
+        ca_bundle,
+        insecure_skip_verify,
+        http2_prior_knowledge,
+        dns_cache_ttl,
+        max_concurrency,
+        max_queue,
+        retry_after,
+        validation_cache_size,
+        results,
+        junit_output,
+        report_format,
+        junit_group_by,
+        junit_merge_correlated,
+        enforce,
+        unmatched_paths,
+        no_forwarded_headers,
+        trace_context,
+        header_prefix,
+        strip_proxy_headers,
+        max_request_body,
+        max_response_body,
+        preserve_host,
+        override_host,
+        base_path,
+    } = proxy;
+    eprintln!(
+        "Starting proxy server with file: {}, upstream: {}",
+        file, upstream
+    );
+    let content = file.read()?;
+    let spec = match load_spec(file.name(), content.clone(), *allow_remote_refs, *force) {
+        Ok(spec) => spec,
+        Err(err) => {
+            let problems = validate::read_problems(&file.name(), &content, *force);
+            // Errors like a remote reference that can't be fetched aren't in the document
+            if problems.is_empty() {
+                return Err(err);
+            }
+            for problem in problems.iter() {
+                eprintln!("{}", validate::render(problem));
             }
+            std::process::exit(1);
+        }
+    };
+    for problem in validate::startup_problems(&file.name(), &content, &spec.spec) {
+        eprintln!("{}", validate::render(&problem));
+    }
+    let refresh = spec_refresh_interval.map(|seconds| SpecRefresh {
+        source: file.clone(),
+        interval: std::time::Duration::from_secs(seconds),
+        allow_remote_refs: *allow_remote_refs,
+        force: *force,
+    });
+    let mut config: Config = match config {
+        Some(config) => {
+            let content = std::fs::read_to_string(config)?;
+            serde_yaml::from_str(&content)?
+        }
+        None => Config::default(),
+    };
+    // The flags let the same config file be used in environments with different header rules
+    if let Some(prefix) = header_prefix {
+        config.headers.prefix = prefix.clone();
+    }
+    config.headers.strip_upstream |= *strip_proxy_headers;
+    let golden = match (golden_dir, golden_mode) {
+        (Some(directory), Some(mode)) => Some(golden::Golden {
+            directory: directory.clone(),
+            mode: *mode,
+        }),
+        _ => None,
+    };
+    let client = upstream::client(
+        dns::CachingResolver::new(resolve, std::time::Duration::from_secs(*dns_cache_ttl)),
+        tls::client_config(ca_bundle.as_deref(), *insecure_skip_verify)?,
+        *http2_prior_knowledge,
+    );
+    let concurrency_limit = max_concurrency.map(|max_concurrency| {
+        limit::ConcurrencyLimit::new(max_concurrency, *max_queue, *retry_after)
+    });
+    let artifacts = match artifacts_dir {
+        Some(directory) => {
+            std::fs::create_dir_all(directory)?;
+            Some(artifacts::Artifacts {
+                directory: directory.clone(),
+            })
+        }
+        None => None,
+    };
+    let evidence = evidence_max_bytes.map(|max_bytes| evidence::Capture {
+        max_bytes,
+        content_types: evidence_content_types.clone(),
+    });
+    let session = Session::new(std::time::SystemTime::now());
+    let results = match results {
+        Some(results) => Some(results::ResultsFile::create(results, &session)?),
+        None => None,
+    };
+    let recording = match mode {
+        ProxyMode::Record { har } => Some(har::Recording::create(har)?),
+        _ => None,
+    };
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port.unwrap_or(3000))).await?;
+    let shutdown: std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send>> = match mode {
+        ProxyMode::Replay { har, .. } => {
+            let entries = har::load(har)?;
+            let proxy = url::Url::parse(&format!("http://{}", listener.local_addr()?))?;
+            let correlation_id_header = config.headers.correlation_id();
+            Box::pin(async move {
+                let replayed = tokio::task::spawn_blocking(move || {
+                    replay::replay(&entries, &proxy, &correlation_id_header)
+                });
+                if let Err(err) = replayed.await {
+                    error!(%err, "Failed to replay the recorded requests");
+                }
+            })
+        }
+        _ => Box::pin(shutdown_signal()),
+    };
+    let state = start_server(
+        listener,
+        shutdown,
+        spec,
+        refresh,
+        upstream.clone(),
+        base_path.clone(),
+        config,
+        golden,
+        artifacts,
+        evidence,
+        client,
+        concurrency_limit,
+        cache::ValidationCache::new(*validation_cache_size),
+        session,
+        results,
+        recording,
+        *enforce,
+        *unmatched_paths,
+        !*no_forwarded_headers,
+        *trace_context,
+        match (preserve_host, override_host) {
+            (_, Some(host)) => upstream::Host::Override(host.clone()),
+            (true, None) => upstream::Host::Preserve,
+            (false, None) => upstream::Host::Upstream,
+        },
+        body::Limits {
+            request: *max_request_body,
+            response: *max_response_body,
+        },
+    )
+    .await;
+    // Testcases from tenants are only included in their own reports
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.tenant().is_none())
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    let failed = testcases
+        .iter()
+        .any(|testcase| !testcase.failures.is_empty());
+    let query = ReportQuery {
+        group_by: *junit_group_by,
+        merge_correlated: *junit_merge_correlated,
+        ..ReportQuery::default()
+    };
+    let report = match report_format {
+        ReportFormat::Junit => junit_xml(testcases, &query, &state.session),
+        ReportFormat::Xunit => xunit_xml(testcases, &query, &state.session),
+        ReportFormat::Nunit => nunit_xml(testcases, &query, &state.session),
+        ReportFormat::Ctrf => {
+            let suites = report_suites(testcases, &query, &state.session);
+            serde_json::to_string_pretty(&ctrf::report(&suites, &state.session))?
+        }
+    };
+    if let Some(junit_output) = junit_output {
+        std::fs::write(junit_output, &report)?;
+        eprintln!(
+            "Wrote the {} report to {}",
+            report_format,
+            junit_output.display()
+        );
+    }
+    if let ProxyMode::Replay { output, .. } = mode {
+        match output {
+            Some(output) => std::fs::write(output, report)?,
+            None => println!("{}", report),
+        }
+        if failed {
+            std::process::exit(1);
         }
     }
     Ok(())
@@ -217,38 +1772,334 @@ fn parse_openapi_spec(content: &str) -> Result<openapiv3::OpenAPI, Box<dyn std::
     }
 }
 
-async fn start_server(spec: openapiv3::OpenAPI, upstream: url::Url, port: u16) {
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::DEBUG)
-        .finish();
+/// Checks the version the document declares before it is parsed, so documents in versions that
+/// aren't supported are rejected with a clear error instead of a parse error. With `force`, these
+/// documents are read as well as they can be and their version is returned so it can be recorded
+/// on every testcase.
+fn parse_versioned_spec(
+    content: &str,
+    force: bool,
+) -> Result<(openapiv3::OpenAPI, Option<String>), Box<dyn std::error::Error>> {
+    match version::detect(content)? {
+        version::SpecVersion::Supported(version) => {
+            let spec = parse_openapi_spec(content)
+                .map_err(|err| format!("Failed to read the document as {}: {}", version, err))?;
+            Ok((spec, None))
+        }
+        version::SpecVersion::Convertible(version) => {
+            eprintln!("Converting the {} document to OpenAPI 3.0", version);
+            let spec = version::convert(content)
+                .map_err(|err| format!("Failed to convert the {} document: {}", version, err))?;
+            Ok((spec, None))
+        }
+        version::SpecVersion::Unsupported(version) if force => {
+            eprintln!(
+                "Validating against {} even though it is not supported",
+                version
+            );
+            let spec = version::convert(content)
+                .map_err(|err| format!("Failed to read the document as {}: {}", version, err))?;
+            Ok((spec, Some(version)))
+        }
+        version::SpecVersion::Unsupported(version) => {
+            Err(version::unsupported_message(&version).into())
+        }
+    }
+}
+
+/// The webhooks section of an OpenAPI 3.1 document. openapiv3 only models OpenAPI 3.0 documents
+/// so the webhooks need to be parsed separately from the rest of the spec.
+#[derive(Debug, Default, Deserialize)]
+struct WebhooksDocument {
+    #[serde(default)]
+    webhooks: BTreeMap<String, ReferenceOr<openapiv3::PathItem>>,
+}
 
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+fn parse_webhooks(
+    content: &str,
+) -> Result<BTreeMap<String, openapiv3::PathItem>, Box<dyn std::error::Error>> {
+    let document: WebhooksDocument = if content.starts_with("{") {
+        serde_json::from_str(content)?
+    } else {
+        serde_yaml::from_str(content)?
+    };
+    let mut webhooks = BTreeMap::new();
+    for (name, webhook) in document.webhooks {
+        match webhook {
+            ReferenceOr::Item(item) => {
+                webhooks.insert(name, item);
+            }
+            ReferenceOr::Reference { reference } => {
+                eprintln!(
+                    "Skipping webhook {} because references ({}) are not supported for webhooks",
+                    name, reference
+                );
+            }
+        }
+    }
+    Ok(webhooks)
+}
 
+/// Parses the spec, and builds the router that matches request paths to it.
+fn load_spec(
+    name: String,
+    content: String,
+    allow_remote_refs: bool,
+    force: bool,
+) -> Result<LoadedSpec, Box<dyn std::error::Error>> {
+    let bundled = remote::bundle(&content, allow_remote_refs)?;
+    let document = bundled.as_deref().unwrap_or(&content);
+    let (spec, unsupported_version) = parse_versioned_spec(document, force)?;
+    let webhooks = parse_webhooks(document)?;
     let mut wayfinder = wayfind::Router::new();
     for (path_template, _) in spec.paths.paths.iter() {
         let path_template = path_template.to_string();
         wayfinder.insert(&path_template, ()).unwrap();
     }
+    Ok(LoadedSpec {
+        base_paths: server_base_paths(&spec),
+        spec,
+        unsupported_version: unsupported_version.map(|version| TestcaseProperty {
+            name: "unsupportedSpecVersion".to_string(),
+            value: version,
+        }),
+        webhooks,
+        wayfinder,
+        content,
+        name,
+    })
+}
+
+/// The paths of the spec's servers, like /api/v3 for https://example.com/api/v3. Server variables
+/// are replaced with their defaults.
+fn server_base_paths(spec: &openapiv3::OpenAPI) -> Vec<String> {
+    spec.servers
+        .iter()
+        .filter_map(|server| {
+            let mut url = server.url.clone();
+            for (name, variable) in server.variables.iter().flatten() {
+                url = url.replace(&format!("{{{}}}", name), &variable.default);
+            }
+            // Server URLs can be relative to where the spec is served from
+            let path = match url.split_once("://") {
+                Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+                None => url.as_str(),
+            };
+            let path = path.trim_matches('/');
+            (!path.is_empty()).then(|| format!("/{}", path))
+        })
+        .collect()
+}
+
+/// The path a request is matched against the spec with. The longest base path that the request
+/// path is under is removed. Base paths only match whole segments, so /api doesn't match /apis.
+fn spec_path(path: &str, base_paths: &[&str]) -> String {
+    base_paths
+        .iter()
+        .filter_map(|base_path| path.strip_prefix(base_path.trim_end_matches('/')))
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .min_by_key(|rest| rest.len())
+        .map(|rest| match rest {
+            "" => "/".to_string(),
+            rest => rest.to_string(),
+        })
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// How often the spec is read again while the proxy is running.
+#[derive(Clone)]
+struct SpecRefresh {
+    source: source::SpecSource,
+    interval: std::time::Duration,
+    allow_remote_refs: bool,
+    force: bool,
+}
+
+/// Reads the spec on every interval and swaps it in when the document changed. A spec that can't
+/// be read or parsed is logged and the proxy keeps validating against the last good one.
+async fn refresh_spec(refresh: SpecRefresh, state: AppState) {
+    let mut interval = tokio::time::interval(refresh.interval);
+    // The first tick completes right away, and the spec was just loaded
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let current = state.current_spec();
+        let SpecRefresh {
+            source,
+            allow_remote_refs,
+            force,
+            ..
+        } = refresh.clone();
+        let loaded = tokio::task::spawn_blocking(move || {
+            let content = source.read().map_err(|err| err.to_string())?;
+            if content == current.content {
+                return Ok(None);
+            }
+            load_spec(source.name(), content, allow_remote_refs, force)
+                .map(Some)
+                .map_err(|err| err.to_string())
+        })
+        .await;
+        match loaded {
+            Ok(Ok(Some(spec))) => {
+                *state.spec.write().unwrap() = Arc::new(spec);
+                // Cached results were validated against the old spec
+                state.validation_cache.clear();
+                info!(source = %refresh.source, "Reloaded the spec");
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(err)) => error!(%err, "Failed to refresh the spec"),
+            Err(err) => error!(%err, "Failed to refresh the spec"),
+        }
+    }
+}
 
+#[allow(clippy::too_many_arguments)]
+/// Serves the proxy on the listener until the shutdown future completes, and returns its state so
+/// the testcases can be reported on afterwards.
+async fn start_server(
+    listener: tokio::net::TcpListener,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+    spec: LoadedSpec,
+    refresh: Option<SpecRefresh>,
+    upstream: url::Url,
+    base_path: Option<String>,
+    config: Config,
+    golden: Option<golden::Golden>,
+    artifacts: Option<artifacts::Artifacts>,
+    evidence: Option<evidence::Capture>,
+    client: upstream::Client,
+    concurrency_limit: Option<limit::ConcurrencyLimit>,
+    validation_cache: cache::ValidationCache,
+    session: Session,
+    results: Option<results::ResultsFile>,
+    recording: Option<har::Recording>,
+    enforce: bool,
+    unmatched_paths: UnmatchedPaths,
+    forwarded_headers: bool,
+    trace_context: bool,
+    host: upstream::Host,
+    body_limits: body::Limits,
+) -> AppState {
     let state = AppState {
-        spec,
+        spec: Arc::new(std::sync::RwLock::new(Arc::new(spec))),
         upstream,
+        upstreams: config.upstreams,
+        base_path,
         testcases: Arc::new(Mutex::new(vec![])),
-        wayfinder,
+        metrics: Arc::new(std::sync::Mutex::new(Metrics::default())),
+        validation_toggles: Arc::new(Mutex::new(config.validation)),
+        webhook_consumers: config.webhooks,
+        pending_links: Arc::new(Mutex::new(vec![])),
+        sequences: config.sequences,
+        sequence_events: Arc::new(Mutex::new(vec![])),
+        golden,
+        artifacts,
+        evidence,
+        client,
+        concurrency_limit: concurrency_limit.map(Arc::new),
+        validation_cache: Arc::new(validation_cache),
+        json_checks: JsonChecks {
+            duplicate_keys: config.strict_json,
+            int64_precision: config.int64_precision_warnings,
+            date_time: config.date_time_format,
+            formats: Arc::new(config.formats),
+        },
+        operations: config.operations,
+        runs: Arc::new(Mutex::new(runs::Runs::default())),
+        session,
+        proxy_headers: config.headers,
+        captured_headers: config.captured_headers,
+        results: results.map(Arc::new),
+        recording: recording.map(Arc::new),
+        enforce,
+        unmatched_paths,
+        forwarded_headers,
+        trace_context,
+        host,
+        body_limits,
+        suppressed_failures: config.suppressed_failures,
+        severities: config.severities,
     };
+    let shutdown_state = state.clone();
+    let example_lints = check::check_examples(&state.current_spec().spec, &state.json_checks);
+    if !example_lints.is_empty() {
+        eprintln!("The spec contains examples that don't match their schemas:");
+        for lint in example_lints.iter() {
+            eprintln!("  {}", lint);
+        }
+    }
 
     let app = Router::new()
-        .route("/_ovp/junit", get(junit))
-        .route("/*path", delete(root))
-        .route("/*path", get(root))
-        .route("/*path", head(root))
-        .route("/*path", options(root))
-        .route("/*path", patch(root))
-        .route("/*path", post(root))
-        .route("/*path", put(root))
-        .with_state(state);
+        .merge(report_routes())
+        .route("/_ovp/metrics", get(metrics))
+        .route("/_ovp/coverage", get(coverage))
+        .route("/_ovp/suggestions", get(suggestions))
+        .route("/_ovp/runs/:name/start", post(start_run))
+        .route("/_ovp/runs/:name/stop", post(stop_run))
+        .route(
+            "/_ovp/validation",
+            get(get_validation_toggles).put(put_validation_toggles),
+        )
+        .route("/_ovp/callback/:operation_id", any(callback))
+        .route("/_ovp/webhook/:name", any(webhook))
+        // Every method is proxied, including TRACE and extension methods like PURGE, so methods
+        // the spec doesn't define are reported as InvalidHTTPMethod
+        .route("/*path", any(root))
+        .with_state(state.clone());
+    let refresh = refresh.map(|refresh| tokio::spawn(refresh_spec(refresh, state)));
 
     // Run the Axum server
+    // The address of each client is needed for the forwarded headers
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown)
+    .await
+    .unwrap();
+    if let Some(refresh) = refresh {
+        refresh.abort();
+    }
+
+    let suggestions = suggest_requests(&shutdown_state);
+    if !suggestions.is_empty() {
+        eprintln!(
+            "Suggested requests for operations that were never exercised:\n{}",
+            serde_json::to_string_pretty(&suggestions).unwrap()
+        );
+    }
+    shutdown_state
+}
+
+/// Serves the reports for testcases saved by an earlier session of the proxy. Nothing is proxied.
+async fn serve_report(session: Option<Session>, testcases: Vec<Testcase>, port: u16) {
+    // The runs are recreated in the order they were first seen. None of them are active.
+    let mut runs = runs::Runs::default();
+    for run in testcases.iter().filter_map(|testcase| testcase.run()) {
+        runs.start(run);
+    }
+    if let Some(active) = runs.active().map(str::to_string) {
+        runs.stop(&active);
+    }
+    // Files saved before the session was written to them started before their first testcase
+    let session = session.unwrap_or_else(|| {
+        Session::new(
+            testcases
+                .iter()
+                .filter_map(|testcase| testcase.recorded_at.as_deref())
+                .filter_map(datetime::parse_date_time)
+                .min()
+                .unwrap_or_else(std::time::SystemTime::now),
+        )
+    });
+    let state = ReportState {
+        testcases: Arc::new(Mutex::new(testcases)),
+        runs: Arc::new(Mutex::new(runs)),
+        session,
+    };
+    let app = report_routes().with_state(state);
+
     let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
         .await
         .unwrap();
@@ -258,352 +2109,3326 @@ async fn start_server(spec: openapiv3::OpenAPI, upstream: url::Url, port: u16) {
         .unwrap();
 }
 
+/// The routes that render reports. These are shared by the proxy and the serve-report command.
+fn report_routes<S>() -> Router<S>
+where
+    ReportState: FromRef<S>,
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/_ovp/junit", get(junit))
+        .route("/_ovp/xunit", get(xunit))
+        .route("/_ovp/nunit", get(nunit))
+        .route("/_ovp/ctrf", get(ctrf))
+        .route("/_ovp/results", get(list_results))
+        .route("/_ovp/testcases", get(list_testcases))
+        .route("/_ovp/failures.csv", get(failures_csv))
+        .route("/_ovp/summary", get(summary))
+        .route("/_ovp/testcases/:correlation_id", get(correlated_testcases))
+        .route("/_ovp/runs", get(list_runs))
+        .route("/_ovp/runs/:name/junit", get(run_junit))
+        .route("/_ovp/tenants", get(list_tenants))
+        .route("/_ovp/tenants/:tenant/junit", get(tenant_junit))
+}
+
+fn suggest_requests(state: &AppState) -> Vec<suggestions::Suggestion> {
+    let exercised = state
+        .metrics
+        .lock()
+        .unwrap()
+        .exercised_operations
+        .keys()
+        .cloned()
+        .collect();
+    suggestions::suggest_requests(&state.current_spec().spec, &exercised)
+}
+
 #[instrument(skip_all)]
-#[debug_handler(state = AppState)]
-async fn junit(state: State<AppState>) -> impl IntoResponse {
-    let testcases = state.testcases.lock().await.clone();
-    let testcases_with_failures = testcases
+#[debug_handler(state = ReportState)]
+async fn junit(state: State<ReportState>, Query(query): Query<ReportQuery>) -> impl IntoResponse {
+    // Testcases from tenants are only included in their own reports
+    let testcases = state
+        .testcases
+        .lock()
+        .await
         .iter()
-        .filter(|testcase| !testcase.failures.is_empty())
-        .count() as usize;
-    let template = JunitTemplate {
-        testcases,
-        failed_testcases: testcases_with_failures,
+        .filter(|testcase| testcase.tenant().is_none())
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    render_junit(testcases, &query, &state.session)
+}
+
+/// Returns the xUnit.net v2 report for the testcases that don't belong to a tenant.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn xunit(state: State<ReportState>, Query(query): Query<ReportQuery>) -> impl IntoResponse {
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.tenant().is_none())
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    xml_response(xunit_xml(testcases, &query, &state.session))
+}
+
+/// Returns the NUnit 3 report for the testcases that don't belong to a tenant.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn nunit(state: State<ReportState>, Query(query): Query<ReportQuery>) -> impl IntoResponse {
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.tenant().is_none())
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    xml_response(nunit_xml(testcases, &query, &state.session))
+}
+
+/// Returns the Common Test Report Format report for the testcases that don't belong to a tenant.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn ctrf(state: State<ReportState>, Query(query): Query<ReportQuery>) -> impl IntoResponse {
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.tenant().is_none())
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    let suites = report_suites(testcases, &query, &state.session);
+    axum::Json(ctrf::report(&suites, &state.session))
+}
+
+fn xml_response(rendered: String) -> impl IntoResponse {
+    (
+        axum::http::StatusCode::OK,
+        [(
+            axum::http::header::CONTENT_TYPE,
+            HeaderValue::from_static("application/xml"),
+        )],
+        rendered,
+    )
+}
+
+/// Returns every testcase as JSON, in the same format they are saved in with --results.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn list_results(state: State<ReportState>) -> impl IntoResponse {
+    axum::Json(state.testcases.lock().await.clone())
+}
+
+/// Returns the testcases that match the query as JSON, in the same format as /_ovp/results.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn list_testcases(
+    state: State<ReportState>,
+    Query(query): Query<query::TestcaseQuery>,
+) -> axum::response::Response {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(text) => return (axum::http::StatusCode::BAD_REQUEST, text).into_response(),
+    };
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| filter.matches(testcase))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    axum::Json(testcases).into_response()
+}
+
+/// Returns a CSV with a row for every failure of the testcases that match the query. It takes the
+/// same query as /_ovp/testcases.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn failures_csv(
+    state: State<ReportState>,
+    Query(query): Query<query::TestcaseQuery>,
+) -> axum::response::Response {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(text) => return (axum::http::StatusCode::BAD_REQUEST, text).into_response(),
+    };
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| filter.matches(testcase))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            HeaderValue::from_static("text/csv; charset=utf-8"),
+        )],
+        csv::failures(&testcases),
+    )
+        .into_response()
+}
+
+/// Returns the number of testcases and failures that match the query, counted by failure type,
+/// operation and status code. It takes the same query as /_ovp/testcases.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn summary(
+    state: State<ReportState>,
+    Query(query): Query<query::TestcaseQuery>,
+) -> axum::response::Response {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(text) => return (axum::http::StatusCode::BAD_REQUEST, text).into_response(),
     };
-    let rendered = template.render().unwrap();
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| filter.matches(testcase))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    axum::Json(summary::summary(&testcases)).into_response()
+}
+
+/// Returns the testcases recorded for requests with the correlation ID as JSON, so a test can
+/// check the result of its own request. Responds with a 404 when there are none.
+#[instrument(skip(state))]
+#[debug_handler(state = ReportState)]
+async fn correlated_testcases(
+    state: State<ReportState>,
+    Path(correlation_id): Path<String>,
+) -> axum::response::Response {
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.correlation_id() == Some(&correlation_id))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    if testcases.is_empty() {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    }
+    axum::Json(testcases).into_response()
+}
+
+fn render_junit(
+    testcases: Vec<Testcase>,
+    query: &ReportQuery,
+    session: &Session,
+) -> impl IntoResponse {
+    let rendered = junit_xml(testcases, query, session);
     let mut header_map = axum::http::HeaderMap::new();
     header_map.insert("Content-Type", HeaderValue::from_static("application/xml"));
 
     (axum::http::StatusCode::OK, header_map, rendered)
 }
 
-#[instrument(skip_all)]
-#[debug_handler(state = AppState)]
-async fn root(state: State<AppState>, request: Request) -> impl IntoResponse {
-    inner_handler(state, request).await
+fn junit_xml(testcases: Vec<Testcase>, query: &ReportQuery, session: &Session) -> String {
+    let template = JunitTemplate {
+        suites: report_suites(testcases, query, session),
+    };
+    template.render().unwrap()
 }
 
-async fn inner_handler(
-    State(AppState {
-        spec,
-        upstream,
-        testcases,
-        wayfinder,
-    }): State<AppState>,
-    request: Request,
-) -> impl IntoResponse {
-    let mut failures = vec![];
-    let mut properties = vec![];
-    let method = request.method().clone();
-    let path = request.uri().path();
-    let upstream_path = upstream.path();
-    // We are stripping the upstream path from the request path so that we can match it against the OpenAPI spec.
-    // We still use the full path to make the actual request to the upstream server.
-    let path = match path.strip_prefix(upstream_path) {
-        Some(p) => {
-            // Make sure the path starts with a slash
-            if p.starts_with("/") {
-                p.to_string()
-            } else {
-                format!("/{}", p)
-            }
-        }
-        None => path.to_string(),
+fn xunit_xml(testcases: Vec<Testcase>, query: &ReportQuery, session: &Session) -> String {
+    let suites = report_suites(testcases, query, session);
+    // The run date and time are in UTC, like the JUnit timestamp
+    let started = datetime::format_date_time(session.started);
+    let template = XunitTemplate {
+        run_date: started[..10].to_string(),
+        run_time: started[11..19].to_string(),
+        hostname: session.hostname.clone(),
+        totals: ReportTotals::new(&suites),
+        suites,
     };
+    template.render().unwrap()
+}
 
-    let path_and_query = request.uri().path_and_query().unwrap();
-    let url = upstream.join(path_and_query.as_str()).unwrap();
-    info!(
-        method = method.as_str(),
-        url = url.to_string(),
-        "Handling request"
-    );
-    properties.push(TestcaseProperty {
-        name: "path".to_string(),
-        value: path.to_string(),
-    });
-    properties.push(TestcaseProperty {
-        name: "method".to_string(),
-        value: method.to_string(),
-    });
+fn nunit_xml(testcases: Vec<Testcase>, query: &ReportQuery, session: &Session) -> String {
+    let suites = report_suites(testcases, query, session);
+    // NUnit writes its times in UTC as "2024-01-31 12:00:00Z". The run ends when it is reported.
+    let nunit_time = |time: std::time::SystemTime| {
+        let time = datetime::format_date_time(time);
+        format!("{} {}Z", &time[..10], &time[11..19])
+    };
+    let template = NunitTemplate {
+        start_time: nunit_time(session.started),
+        end_time: nunit_time(std::time::SystemTime::now()),
+        hostname: session.hostname.clone(),
+        totals: ReportTotals::new(&suites),
+        suites,
+    };
+    template.render().unwrap()
+}
 
-    let wayfinder_path = wayfind::Path::new(&path).unwrap();
-    let wayfinder_match = wayfinder.search(&wayfinder_path).unwrap();
-    match &wayfinder_match {
-        Some(wayfound) => {
-            for parameter in wayfound.parameters.iter() {
-                properties.push(TestcaseProperty {
-                    name: format!("pathParameter-{}", parameter.key),
-                    value: parameter.value.to_string(),
-                });
+/// The testsuites of a report, after the testcases are filtered, merged and grouped as the query
+/// asks.
+fn report_suites(
+    testcases: Vec<Testcase>,
+    query: &ReportQuery,
+    session: &Session,
+) -> Vec<JunitSuite> {
+    let testcases = match &query.tag {
+        Some(tag) => testcases
+            .into_iter()
+            .filter(|testcase| testcase.tags().any(|testcase_tag| testcase_tag == tag))
+            .collect(),
+        None => testcases,
+    };
+    let testcases = match query.merge_correlated {
+        true => merge_correlated(testcases),
+        false => testcases,
+    };
+    match query.group_by {
+        Some(ReportGrouping::Tag) => {
+            let mut by_tag: BTreeMap<&str, Vec<Testcase>> = BTreeMap::new();
+            for testcase in testcases.iter() {
+                let mut tags = testcase.tags().peekable();
+                if tags.peek().is_none() {
+                    by_tag.entry("untagged").or_default().push(testcase.clone());
+                }
+                for tag in tags {
+                    by_tag.entry(tag).or_default().push(testcase.clone());
+                }
             }
+            by_tag
+                .into_iter()
+                .map(|(tag, testcases)| JunitSuite::new(tag.to_string(), testcases, session))
+                .collect()
         }
-        None => {
-            failures.push(TestcaseFailure {
-                text: "Path not found".to_string(),
-                r#type: TestcaseFailureType::PathNotFound,
-            });
+        Some(ReportGrouping::Path) => {
+            let mut by_path: BTreeMap<String, Vec<Testcase>> = BTreeMap::new();
+            for testcase in testcases {
+                by_path
+                    .entry(testcase.path_group())
+                    .or_default()
+                    .push(testcase);
+            }
+            by_path
+                .into_iter()
+                .map(|(path, testcases)| JunitSuite::new(path, testcases, session))
+                .collect()
         }
+        None => vec![JunitSuite::new(
+            "openapi-validator-proxy".to_string(),
+            testcases,
+            session,
+        )],
     }
-    let wayfinder_path = wayfinder_match.map(|m| m.route.to_string());
+}
 
-    let mut outgoing_request = ureq::request(method.as_str(), url.as_str());
-    for (key, value) in request.headers() {
-        let key = key.as_str();
-        let value = value.to_str().unwrap();
-        outgoing_request = outgoing_request.set(key, value);
-    }
-    // The correlation ID is what is used to specify the name of the testcase. If the client
-    // supplied one, use that. Otherwise, generate a new one.
-    let correlation_id = match outgoing_request.header("OVP-Correlation-Id") {
-        Some(correlation_id) => correlation_id.to_string(),
-        None => {
-            let generated_uuid = uuid::Uuid::new_v4().to_string();
-            outgoing_request = outgoing_request.set("OVP-Correlation-Id", &generated_uuid);
-            generated_uuid
+/// Merges the testcases of requests that share a correlation ID into one testcase named after the
+/// correlation ID, so a scenario that spans several requests is reported as a single test. The
+/// merged testcase is placed where the first request's testcase was.
+fn merge_correlated(testcases: Vec<Testcase>) -> Vec<Testcase> {
+    let mut scenarios: Vec<Vec<Testcase>> = vec![];
+    let mut positions: BTreeMap<String, usize> = BTreeMap::new();
+    for testcase in testcases {
+        let position = testcase
+            .correlation_id()
+            .and_then(|correlation_id| positions.get(correlation_id))
+            .copied();
+        match position {
+            Some(position) => scenarios[position].push(testcase),
+            None => {
+                if let Some(correlation_id) = testcase.correlation_id() {
+                    positions.insert(correlation_id.clone(), scenarios.len());
+                }
+                scenarios.push(vec![testcase]);
+            }
         }
-    };
-    // If the client supplied a list of headers to fuse, add them to the outgoing request
-    if let Some(fuse_headers) = outgoing_request.header("OVP-Fused-Correlation-Headers") {
-        let fuse_headers = fuse_headers.to_string();
-        for header in fuse_headers.split(",") {
-            let header = header.trim();
-            if header.is_empty() {
-                continue;
+    }
+    scenarios
+        .into_iter()
+        .map(|mut steps| match steps.len() {
+            1 => steps.remove(0),
+            _ => merge_steps(steps),
+        })
+        .collect()
+}
+
+/// Combines the testcases of a scenario into numbered steps. The name of each step is recorded as
+/// a step-N property, and its failures, warnings and evidence are prefixed with the step so they
+/// can be told apart. Properties that every step has in common are kept.
+fn merge_steps(steps: Vec<Testcase>) -> Testcase {
+    let correlation_id = steps[0].correlation_id().cloned().unwrap_or_default();
+    let mut properties = steps[0]
+        .properties
+        .iter()
+        .filter(|property| steps.iter().all(|step| step.properties.contains(property)))
+        .cloned()
+        .collect::<Vec<TestcaseProperty>>();
+    let mut failures = vec![];
+    let mut warnings = vec![];
+    let mut evidence = vec![];
+    let mut time = 0.0;
+    for (index, step) in steps.iter().enumerate() {
+        let name = step
+            .name
+            .strip_suffix(&format!(" {}", correlation_id))
+            .unwrap_or(&step.name);
+        let prefix = format!("Step {} ({})", index + 1, name);
+        properties.push(TestcaseProperty {
+            name: format!("step-{}", index + 1),
+            value: name.to_string(),
+        });
+        for failure in step.failures.iter() {
+            let mut text = format!("{}: {}", prefix, failure.text);
+            if let Some(reproduction) = &step.reproduction {
+                text = format!("{}\n\nReproduce with:\n{}", text, reproduction);
             }
-            outgoing_request = outgoing_request.set(header, &correlation_id);
+            failures.push(TestcaseFailure {
+                text,
+                ..failure.clone()
+            });
         }
+        warnings.extend(step.warnings.iter().map(|warning| TestcaseFailure {
+            text: format!("{}: {}", prefix, warning.text),
+            ..warning.clone()
+        }));
+        evidence.extend(step.evidence.iter().map(|body| evidence::Evidence {
+            name: format!("{} {}", prefix, body.name.to_lowercase()),
+            ..body.clone()
+        }));
+        time += step.time.parse::<f64>().unwrap_or_default();
+    }
+    Testcase {
+        name: correlation_id,
+        failures,
+        properties,
+        time: format!("{:.2}", time),
+        // Each failure carries the reproduction of its own step
+        reproduction: None,
+        skipped: steps
+            .iter()
+            .all(|step| step.skipped.is_some())
+            .then(|| steps[0].skipped.clone())
+            .flatten(),
+        warnings,
+        recorded_at: steps[0].recorded_at.clone(),
+        evidence,
     }
+}
 
-    properties.push(TestcaseProperty {
-        name: "correlationId".to_string(),
-        value: correlation_id.to_string(),
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn metrics(state: State<AppState>) -> impl IntoResponse {
+    let metrics = state.metrics.lock().unwrap();
+    let queued = state
+        .concurrency_limit
+        .as_ref()
+        .map(|concurrency_limit| concurrency_limit.queued())
+        .unwrap_or(0);
+    let (validation_cache_hits, validation_cache_misses) = state.validation_cache.stats();
+    axum::Json(serde_json::json!({
+        "inFlight": metrics.in_flight,
+        "peakInFlight": metrics.peak_in_flight,
+        "totalRequests": metrics.total_requests,
+        "queued": queued,
+        "rejectedRequests": metrics.rejected_requests,
+        "activeOperations": metrics.active_operations,
+        "requestBodyBytes": metrics.request_body_bytes,
+        "responseBodyBytes": metrics.response_body_bytes,
+        "validationCacheHits": validation_cache_hits,
+        "validationCacheMisses": validation_cache_misses,
+    }))
+}
+
+/// Returns skeleton requests for the operations in the spec that have not been exercised yet.
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn coverage(state: State<AppState>) -> impl IntoResponse {
+    let exercised = state.metrics.lock().unwrap().exercised_operations.clone();
+    axum::Json(coverage::operation_coverage(
+        &state.current_spec().spec,
+        &exercised,
+    ))
+}
+
+async fn suggestions(state: State<AppState>) -> impl IntoResponse {
+    axum::Json(suggest_requests(&state))
+}
+
+/// Lists every run that has been started with the number of testcases recorded in it.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn list_runs(state: State<ReportState>) -> impl IntoResponse {
+    let runs = state.runs.lock().await;
+    let testcases = state.testcases.lock().await;
+    let runs = runs
+        .names()
+        .iter()
+        .map(|name| {
+            serde_json::json!({
+                "name": name,
+                "active": runs.active() == Some(name.as_str()),
+                "testcases": testcases.iter().filter(|testcase| testcase.run() == Some(name)).count(),
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+    axum::Json(runs)
+}
+
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn start_run(state: State<AppState>, Path(name): Path<String>) -> impl IntoResponse {
+    info!(name, "Starting run");
+    state.runs.lock().await.start(&name);
+    axum::http::StatusCode::NO_CONTENT
+}
+
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn stop_run(state: State<AppState>, Path(name): Path<String>) -> impl IntoResponse {
+    if !state.runs.lock().await.stop(&name) {
+        return axum::http::StatusCode::NOT_FOUND;
+    }
+    info!(name, "Stopped run");
+    axum::http::StatusCode::NO_CONTENT
+}
+
+/// Returns the JUnit report for the testcases that were recorded while the run was active.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn run_junit(
+    state: State<ReportState>,
+    Path(name): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> axum::response::Response {
+    if !state.runs.lock().await.names().contains(&name) {
+        return axum::http::StatusCode::NOT_FOUND.into_response();
+    }
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.run() == Some(&name))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    render_junit(testcases, &query, &state.session).into_response()
+}
+
+/// Lists every tenant that has sent a request with the number of testcases recorded for it.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn list_tenants(state: State<ReportState>) -> impl IntoResponse {
+    let testcases = state.testcases.lock().await;
+    let mut tenants: BTreeMap<&str, usize> = BTreeMap::new();
+    for tenant in testcases.iter().filter_map(|testcase| testcase.tenant()) {
+        *tenants.entry(tenant).or_default() += 1;
+    }
+    let tenants = tenants
+        .into_iter()
+        .map(|(name, testcases)| serde_json::json!({"name": name, "testcases": testcases}))
+        .collect::<Vec<serde_json::Value>>();
+    axum::Json(tenants)
+}
+
+/// Returns the JUnit report for the testcases recorded for the tenant. Tenants don't need to be
+/// registered, so a tenant that hasn't sent any requests gets an empty report.
+#[instrument(skip_all)]
+#[debug_handler(state = ReportState)]
+async fn tenant_junit(
+    state: State<ReportState>,
+    Path(tenant): Path<String>,
+    Query(query): Query<ReportQuery>,
+) -> impl IntoResponse {
+    let testcases = state
+        .testcases
+        .lock()
+        .await
+        .iter()
+        .filter(|testcase| testcase.tenant() == Some(&tenant))
+        .cloned()
+        .collect::<Vec<Testcase>>();
+    render_junit(testcases, &query, &state.session)
+}
+
+/// Tags a testcase with the tenant named by the client, so teams sharing the proxy get separate
+/// reports.
+fn tenant_property(
+    headers: &axum::http::HeaderMap,
+    proxy_headers: &headers::ProxyHeaders,
+) -> Option<TestcaseProperty> {
+    headers
+        .get(proxy_headers.tenant())
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|tenant| !tenant.is_empty())
+        .map(|tenant| TestcaseProperty {
+            name: "tenant".to_string(),
+            value: tenant.to_string(),
+        })
+}
+
+/// Removes the failures whose type is suppressed in the config file. A suppressedFailure property
+/// is returned for each one so they can still be counted.
+fn suppress_failures(
+    failures: &mut Vec<TestcaseFailure>,
+    suppressed: &[TestcaseFailureType],
+) -> Vec<TestcaseProperty> {
+    let mut properties = vec![];
+    failures.retain(|failure| {
+        if !suppressed.contains(&failure.r#type) {
+            return true;
+        }
+        properties.push(TestcaseProperty {
+            name: "suppressedFailure".to_string(),
+            value: failure.r#type.to_string(),
+        });
+        false
+    });
+    properties
+}
+
+/// Removes the failures whose type is configured as a warning and returns them.
+fn take_warnings(
+    failures: &mut Vec<TestcaseFailure>,
+    severities: &BTreeMap<TestcaseFailureType, Severity>,
+) -> Vec<TestcaseFailure> {
+    let (warnings, errors) = std::mem::take(failures).into_iter().partition(|failure| {
+        severities.get(&failure.r#type).copied().unwrap_or_default() == Severity::Warning
+    });
+    *failures = errors;
+    warnings
+}
+
+/// Adds a testcase to the report, saving it to the results file if there is one.
+fn record_testcase(
+    testcases: &mut Vec<Testcase>,
+    results: Option<&results::ResultsFile>,
+    mut testcase: Testcase,
+) {
+    testcase.recorded_at = Some(datetime::format_date_time(std::time::SystemTime::now()));
+    if let Some(results) = results {
+        results.append(&testcase);
+    }
+    testcases.push(testcase);
+}
+
+/// Tags a testcase with the run that is active while it is handled.
+async fn run_property(runs: &Mutex<runs::Runs>) -> Option<TestcaseProperty> {
+    runs.lock().await.active().map(|run| TestcaseProperty {
+        name: "run".to_string(),
+        value: run.to_string(),
+    })
+}
+
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn get_validation_toggles(state: State<AppState>) -> impl IntoResponse {
+    let toggles = state.validation_toggles.lock().await.clone();
+    axum::Json(toggles)
+}
+
+/// Replaces the current validation toggles. Families that are omitted from the body are enabled.
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn put_validation_toggles(
+    state: State<AppState>,
+    axum::Json(toggles): axum::Json<ValidationToggles>,
+) -> impl IntoResponse {
+    info!(?toggles, "Updating validation toggles");
+    let mut current = state.validation_toggles.lock().await;
+    *current = toggles.clone();
+    axum::Json(toggles)
+}
+
+/// Reads a request body into the validation buffer. Bodies larger than `limit` are reported as
+/// BodyTooLargeToValidate and returned as a stream instead, with an empty buffer.
+async fn buffer_request_body(
+    body: axum::body::Body,
+    limit: usize,
+    failures: &mut Vec<TestcaseFailure>,
+) -> Result<(axum::body::Bytes, Option<axum::body::Body>), HandlerError> {
+    let buffered = body::buffer(body, limit).await.map_err(|err| {
+        HandlerError::new(
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Failed to read the request body: {}", err),
+        )
+    })?;
+    match buffered {
+        body::Buffered::Complete(body) => Ok((body, None)),
+        body::Buffered::Streaming(stream) => {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "Request body is larger than the {} bytes allowed by --max-request-body and was not validated",
+                    limit
+                ),
+                r#type: TestcaseFailureType::BodyTooLargeToValidate,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            Ok((axum::body::Bytes::new(), Some(stream)))
+        }
+    }
+}
+
+/// Reads the rest of a request body without keeping it, so the sender can finish sending a body
+/// that was too large to validate.
+async fn discard_request_body(body: axum::body::Body) -> Result<(), HandlerError> {
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        chunk.map_err(|err| {
+            HandlerError::new(
+                axum::http::StatusCode::BAD_REQUEST,
+                format!("Failed to read the request body: {}", err),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Receives callback requests that the upstream server sends for an operation. The upstream
+/// should be configured to send its callbacks to /_ovp/callback/{operationId}. The request is
+/// validated against the callback declared by that operation and recorded as its own testcase.
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn callback(
+    state: State<AppState>,
+    Path(operation_id): Path<String>,
+    mut request: Request,
+) -> axum::response::Response {
+    state
+        .proxy_headers
+        .move_correlation_id_parameter(&mut request);
+    let (method, uri, headers) = request_summary(&request);
+    let handler = handle_callback(state.clone(), operation_id, request);
+    contain_internal_errors(&state, method, uri, headers, handler).await
+}
+
+async fn handle_callback(
+    state: State<AppState>,
+    operation_id: String,
+    request: Request,
+) -> Result<axum::response::Response, HandlerError> {
+    let time_start = std::time::Instant::now();
+    let mut failures = vec![];
+    let mut properties = vec![];
+    properties.extend(run_property(&state.runs).await);
+    properties.extend(tenant_property(request.headers(), &state.proxy_headers));
+    let spec = state.current_spec();
+    properties.extend(spec.unsupported_version.clone());
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let headers = request.headers().clone();
+    properties.extend(state.captured_headers.request(&headers));
+    let (correlation_id, correlation_id_failure) =
+        read_correlation_id(&headers, &state.proxy_headers);
+    failures.extend(correlation_id_failure);
+    info!(
+        method = method.as_str(),
+        operation_id, "Handling callback request"
+    );
+    properties.push(TestcaseProperty {
+        name: "path".to_string(),
+        value: path.clone(),
+    });
+    properties.push(TestcaseProperty {
+        name: "method".to_string(),
+        value: method.to_string(),
+    });
+    properties.push(TestcaseProperty {
+        name: "operationId".to_string(),
+        value: operation_id.clone(),
+    });
+    properties.push(TestcaseProperty {
+        name: "correlationId".to_string(),
+        value: correlation_id.clone(),
     });
-    let testcase_name = format!("{} {} {}", method, path_and_query, correlation_id);
-    let body = axum::body::to_bytes(request.into_body(), usize::MAX)
+    let (body, streamed_body) = buffer_request_body(
+        request.into_body(),
+        state.body_limits.request,
+        &mut failures,
+    )
+    .await?;
+    let body_too_large = streamed_body.is_some();
+    if let Some(streamed_body) = streamed_body {
+        discard_request_body(streamed_body).await?;
+    }
+
+    let callback_operation = find_callback_operation(&spec.spec, &operation_id, &method);
+    match callback_operation {
+        None => {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "Operation {} does not declare a {} callback",
+                    operation_id, method
+                ),
+                r#type: TestcaseFailureType::CallbackNotFound,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
+        Some((name, expression, callback_operation, spec_pointer)) => {
+            properties.push(TestcaseProperty {
+                name: "callback".to_string(),
+                value: name.to_string(),
+            });
+            properties.push(TestcaseProperty {
+                name: "callbackExpression".to_string(),
+                value: expression.to_string(),
+            });
+            properties.extend(operation_properties(callback_operation));
+            // Bodies that are too large to validate were already reported
+            if !body_too_large {
+                let (mut callback_failures, mut callback_properties) = validate_request_body(
+                    &headers,
+                    &body,
+                    callback_operation,
+                    &spec.spec,
+                    &state
+                        .json_checks
+                        .for_operation(Some(&operation_id), &state.operations),
+                    &state.validation_cache,
+                    format!("callback {} {} {}", operation_id, name, method),
+                );
+                locate_in_spec(&mut callback_failures, &spec_pointer);
+                show_spec_locations(&mut callback_failures, &spec);
+                failures.append(&mut callback_failures);
+                properties.append(&mut callback_properties);
+            }
+        }
+    }
+    let toggles = state.validation_toggles.lock().await.clone();
+    failures.retain(|failure| toggles.is_enabled(failure.family()));
+    properties.extend(suppress_failures(&mut failures, &state.suppressed_failures));
+    let warnings = take_warnings(&mut failures, &state.severities);
+    if let Some(artifacts) = state.artifacts.as_ref().filter(|_| !failures.is_empty()) {
+        properties.extend(artifacts.write(&correlation_id, &body, None));
+    }
+    let evidence = match state.evidence.as_ref().filter(|_| !failures.is_empty()) {
+        Some(evidence) => evidence.exchange((&headers, &body), None),
+        None => vec![],
+    };
+
+    let mut cases = state.testcases.lock().await;
+    // Link the callback to the request that triggered it. The upstream is expected to echo the
+    // OVP-Correlation-Id header it received.
+    let originating_testcase =
+        cases.iter().rev().find(|testcase| {
+            testcase.properties.iter().any(|property| {
+                property.name == "correlationId" && property.value == correlation_id
+            }) && testcase
+                .properties
+                .iter()
+                .any(|property| property.name == "operationId" && property.value == operation_id)
+        });
+    if let Some(originating_testcase) = originating_testcase {
+        properties.push(TestcaseProperty {
+            name: "originatingTestcase".to_string(),
+            value: originating_testcase.name.clone(),
+        });
+        // Callbacks are sent by the upstream, which usually doesn't know about tenants, so they
+        // belong to the tenant of the request that triggered them
+        if !properties.iter().any(|property| property.name == "tenant") {
+            if let Some(tenant) = originating_testcase.tenant() {
+                properties.push(TestcaseProperty {
+                    name: "tenant".to_string(),
+                    value: tenant.clone(),
+                });
+            }
+        }
+    }
+    properties.sort();
+    record_testcase(
+        &mut cases,
+        state.results.as_deref(),
+        Testcase {
+            name: format!("{} {} {}", method, path, correlation_id),
+            failures,
+            properties,
+            time: format!("{:.2}", time_start.elapsed().as_secs_f64()),
+            reproduction: None,
+            skipped: None,
+            warnings,
+            recorded_at: None,
+            evidence,
+        },
+    );
+
+    Ok(axum::http::StatusCode::OK.into_response())
+}
+
+/// A property for each of the operation's tags so reports can be filtered and grouped by tag, and
+/// a deprecated property when the operation is deprecated so remaining usage can be tracked.
+fn operation_properties(operation: &openapiv3::Operation) -> Vec<TestcaseProperty> {
+    let mut properties = operation
+        .tags
+        .iter()
+        .map(|tag| TestcaseProperty {
+            name: "tag".to_string(),
+            value: tag.to_string(),
+        })
+        .collect::<Vec<TestcaseProperty>>();
+    if operation.deprecated {
+        properties.push(TestcaseProperty {
+            name: "deprecated".to_string(),
+            value: "true".to_string(),
+        });
+    }
+    properties
+}
+
+/// Finds the callback operation for the HTTP method among the callbacks declared by the operation
+/// with the given operationId. Returns the name of the callback, its expression, the operation,
+/// and where the operation is in the spec.
+fn find_callback_operation<'a>(
+    spec: &'a openapiv3::OpenAPI,
+    operation_id: &str,
+    method: &axum::http::Method,
+) -> Option<(&'a str, &'a str, &'a openapiv3::Operation, String)> {
+    let (path_template, operation_method, operation) = spec
+        .paths
+        .iter()
+        .filter_map(|(path_template, path)| path.as_item().map(|path| (path_template, path)))
+        .flat_map(|(path_template, path)| {
+            path.iter()
+                .map(move |(method, operation)| (path_template, method, operation))
+        })
+        .find(|(_, _, operation)| operation.operation_id.as_deref() == Some(operation_id))?;
+    for (name, callback) in operation.callbacks.iter() {
+        for (expression, path) in callback.iter() {
+            if let Some(callback_operation) = operation_for_method(path, method) {
+                let spec_pointer = format!(
+                    "#/paths/{}/{}/callbacks/{}/{}/{}",
+                    json::escape(path_template),
+                    operation_method,
+                    json::escape(name),
+                    json::escape(expression),
+                    method.as_str().to_lowercase()
+                );
+                return Some((name, expression, callback_operation, spec_pointer));
+            }
+        }
+    }
+    None
+}
+
+/// Receives webhook requests for the webhooks declared in an OpenAPI 3.1 spec. The request is
+/// validated against the webhook with the given name and recorded as its own testcase. If a
+/// consumer URL is configured for the webhook, the request is forwarded to it and the consumer's
+/// response is returned.
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn webhook(
+    state: State<AppState>,
+    Path(name): Path<String>,
+    mut request: Request,
+) -> axum::response::Response {
+    state
+        .proxy_headers
+        .move_correlation_id_parameter(&mut request);
+    let (method, uri, headers) = request_summary(&request);
+    let handler = handle_webhook(state.clone(), name, request);
+    contain_internal_errors(&state, method, uri, headers, handler).await
+}
+
+async fn handle_webhook(
+    state: State<AppState>,
+    name: String,
+    request: Request,
+) -> Result<axum::response::Response, HandlerError> {
+    let time_start = std::time::Instant::now();
+    let mut failures = vec![];
+    let mut properties = vec![];
+    properties.extend(run_property(&state.runs).await);
+    properties.extend(tenant_property(request.headers(), &state.proxy_headers));
+    let spec = state.current_spec();
+    properties.extend(spec.unsupported_version.clone());
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let headers = request.headers().clone();
+    properties.extend(state.captured_headers.request(&headers));
+    let (correlation_id, correlation_id_failure) =
+        read_correlation_id(&headers, &state.proxy_headers);
+    failures.extend(correlation_id_failure);
+    let (client, host) = forwarded::origin(&request);
+    info!(method = method.as_str(), name, "Handling webhook request");
+    properties.push(TestcaseProperty {
+        name: "path".to_string(),
+        value: path.clone(),
+    });
+    properties.push(TestcaseProperty {
+        name: "method".to_string(),
+        value: method.to_string(),
+    });
+    properties.push(TestcaseProperty {
+        name: "webhook".to_string(),
+        value: name.clone(),
+    });
+    properties.push(TestcaseProperty {
+        name: "correlationId".to_string(),
+        value: correlation_id.clone(),
+    });
+    // Bodies over the limit are streamed to the consumer. They are left out of artifacts.
+    let (body, mut streamed_body) = buffer_request_body(
+        request.into_body(),
+        state.body_limits.request,
+        &mut failures,
+    )
+    .await?;
+
+    let webhook_operation = spec
+        .webhooks
+        .get(&name)
+        .and_then(|path| operation_for_method(path, &method));
+    match webhook_operation {
+        None => {
+            failures.push(TestcaseFailure {
+                text: format!("Spec does not declare a {} webhook named {}", method, name),
+                r#type: TestcaseFailureType::WebhookNotFound,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
+        Some(webhook_operation) => {
+            if let Some(operation_id) = &webhook_operation.operation_id {
+                properties.push(TestcaseProperty {
+                    name: "operationId".to_string(),
+                    value: operation_id.to_string(),
+                });
+            }
+            properties.extend(operation_properties(webhook_operation));
+            failures.extend(validate_request_headers(
+                &headers,
+                webhook_operation,
+                &spec.spec,
+            ));
+            // Bodies that are too large to validate were already reported
+            if streamed_body.is_none() {
+                let (mut body_failures, mut body_properties) = validate_request_body(
+                    &headers,
+                    &body,
+                    webhook_operation,
+                    &spec.spec,
+                    &state.json_checks.for_operation(
+                        webhook_operation.operation_id.as_deref(),
+                        &state.operations,
+                    ),
+                    &state.validation_cache,
+                    format!("webhook {} {}", name, method),
+                );
+                locate_in_spec(
+                    &mut body_failures,
+                    &format!(
+                        "#/webhooks/{}/{}",
+                        json::escape(&name),
+                        method.as_str().to_lowercase()
+                    ),
+                );
+                show_spec_locations(&mut body_failures, &spec);
+                failures.append(&mut body_failures);
+                properties.append(&mut body_properties);
+            }
+        }
+    }
+    let toggles = state.validation_toggles.lock().await.clone();
+    failures.retain(|failure| toggles.is_enabled(failure.family()));
+
+    let mut response = (
+        axum::http::StatusCode::OK,
+        axum::http::HeaderMap::new(),
+        axum::body::Body::empty(),
+    );
+    // Only forwarded webhooks have a response from the consumer. Consumer responses over the limit
+    // are streamed to the sender and left out of artifacts and evidence.
+    let mut consumer_body = None;
+    if let Some(consumer) = state.webhook_consumers.get(&name) {
+        // The Host header should be derived from the consumer URL
+        let mut skip = state.proxy_headers.stripped();
+        skip.push(axum::http::header::HOST);
+        let (mut outgoing_headers, mut header_failures) = request_headers(&headers, &skip);
+        if state.forwarded_headers {
+            forwarded::add_forwarded_headers(&mut outgoing_headers, client, host.as_deref());
+        }
+        header_failures.retain(|failure| toggles.is_enabled(failure.family()));
+        failures.append(&mut header_failures);
+        let consumer_response = match upstream::send(
+            &state.client,
+            method.as_str(),
+            consumer,
+            &outgoing_headers,
+            streamed_body.take().unwrap_or_else(|| body.clone().into()),
+        )
         .await
-        .unwrap();
+        {
+            Ok(consumer_response) => {
+                let (parts, body) = consumer_response.into_parts();
+                body::buffer(body, state.body_limits.response)
+                    .await
+                    .map(|body| (parts, body))
+                    .map_err(Into::into)
+            }
+            Err(err) => Err(err),
+        };
+        response = match consumer_response {
+            Ok((consumer_response, consumer_response_body)) => {
+                let status = consumer_response.status;
+                let (response_headers, mut header_failures) =
+                    response_headers(&consumer_response.headers);
+                header_failures.retain(|failure| toggles.is_enabled(failure.family()));
+                failures.append(&mut header_failures);
+                properties.push(TestcaseProperty {
+                    name: "consumerStatusCode".to_string(),
+                    value: status.as_u16().to_string(),
+                });
+                properties.extend(state.captured_headers.response(&response_headers));
+                let client_body = match consumer_response_body {
+                    body::Buffered::Complete(buffered) => {
+                        consumer_body = Some(buffered.clone());
+                        buffered.into()
+                    }
+                    body::Buffered::Streaming(stream) => stream,
+                };
+                (status, response_headers, client_body)
+            }
+            Err(e) => {
+                error!("Failed to forward webhook to {}: {}", consumer, e);
+                consumer_body = Some(axum::body::Bytes::new());
+                (
+                    axum::http::StatusCode::BAD_GATEWAY,
+                    axum::http::HeaderMap::new(),
+                    axum::body::Body::empty(),
+                )
+            }
+        };
+    }
+    if let Some(streamed_body) = streamed_body {
+        discard_request_body(streamed_body).await?;
+    }
+    properties.extend(suppress_failures(&mut failures, &state.suppressed_failures));
+    let warnings = take_warnings(&mut failures, &state.severities);
+    if let Some(artifacts) = state.artifacts.as_ref().filter(|_| !failures.is_empty()) {
+        properties.extend(artifacts.write(&correlation_id, &body, consumer_body.as_deref()));
+    }
+    let evidence = match state.evidence.as_ref().filter(|_| !failures.is_empty()) {
+        Some(evidence) => evidence.exchange(
+            (&headers, &body),
+            consumer_body
+                .as_deref()
+                .map(|consumer_body| (&response.1, consumer_body)),
+        ),
+        None => vec![],
+    };
+
+    properties.sort();
+    record_testcase(
+        &mut *state.testcases.lock().await,
+        state.results.as_deref(),
+        Testcase {
+            name: format!("{} {} {}", method, path, correlation_id),
+            failures,
+            properties,
+            time: format!("{:.2}", time_start.elapsed().as_secs_f64()),
+            reproduction: None,
+            skipped: None,
+            warnings,
+            recorded_at: None,
+            evidence,
+        },
+    );
+
+    Ok(response.into_response())
+}
+
+/// Checks that every header parameter the operation marks as required was sent with the request.
+fn validate_request_headers(
+    headers: &axum::http::HeaderMap,
+    operation: &openapiv3::Operation,
+    spec: &openapiv3::OpenAPI,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    for parameter in operation.parameters.iter() {
+        let parameter = match resolve_parameter(parameter, spec) {
+            Some(parameter) => parameter,
+            None => continue,
+        };
+        if let openapiv3::Parameter::Header { parameter_data, .. } = parameter {
+            if parameter_data.required && !headers.contains_key(parameter_data.name.as_str()) {
+                failures.push(TestcaseFailure {
+                    text: format!(
+                        "Request did not include required header {}",
+                        parameter_data.name
+                    ),
+                    r#type: TestcaseFailureType::MissingRequiredHeader,
+                    json_pointer: None,
+                    spec_pointer: None,
+                    perspective: None,
+                });
+            }
+        }
+    }
+    failures
+}
+
+/// Validates the body of a proxied request, a callback or a webhook. `scope` identifies the
+/// operation in the validation cache. Failures caused by the spec are located relative to the
+/// operation.
+fn validate_request_body(
+    headers: &axum::http::HeaderMap,
+    body: &[u8],
+    operation: &openapiv3::Operation,
+    spec: &openapiv3::OpenAPI,
+    json_checks: &JsonChecks,
+    validation_cache: &cache::ValidationCache,
+    scope: String,
+) -> (Vec<TestcaseFailure>, Vec<TestcaseProperty>) {
+    let mut failures = vec![];
+    let mut properties = vec![];
+    let request_body_reference = operation.request_body.as_ref();
+    let request_body =
+        request_body_reference.and_then(|request_body| resolve_request_body(request_body, spec));
+    let empty_content = openapiv3::Content::default();
+    let content = request_body
+        .map(|request_body| &request_body.content)
+        .unwrap_or(&empty_content);
+    if let Some(failure) = validate_single_content_type(headers, ValidationPerspective::Request) {
+        failures.push(failure);
+    }
+    let request_content_type = headers.get("Content-Type");
+    if request_content_type.is_none() && !content.is_empty() {
+        failures.push(TestcaseFailure {
+            text: "Request did not include a Content-Type header".to_string(),
+            r#type: TestcaseFailureType::MissingContentTypeHeader,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return (failures, properties);
+    }
+    let request_content_type = match request_content_type.map(|v| v.to_str()) {
+        Some(Ok(request_content_type)) => request_content_type,
+        Some(Err(_)) => {
+            failures.push(TestcaseFailure {
+                text: "Request included a Content-Type header that is not valid UTF-8".to_string(),
+                r#type: TestcaseFailureType::MalformedHeader,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            return (failures, properties);
+        }
+        None => "",
+    };
+    properties.push(TestcaseProperty {
+        name: "requestContentType".to_string(),
+        value: request_content_type.to_string(),
+    });
+    let mut body_failures = validation_cache.get_or_validate(
+        cache::CacheKey::new(scope, request_content_type, body),
+        || {
+            validate_body(
+                body,
+                request_content_type,
+                content,
+                spec,
+                ValidationPerspective::Request,
+                json_checks,
+            )
+        },
+    );
+    if let Some(request_body_reference) = request_body_reference {
+        let request_body_segment = spec_segment(request_body_reference, "/requestBody");
+        locate_in_spec(
+            &mut body_failures,
+            &format!("{}/content", request_body_segment),
+        );
+    }
+    failures.extend(body_failures);
+    (failures, properties)
+}
+
+#[instrument(skip_all)]
+#[debug_handler(state = AppState)]
+async fn root(state: State<AppState>, mut request: Request) -> axum::response::Response {
+    state
+        .proxy_headers
+        .move_correlation_id_parameter(&mut request);
+    // The permit is held until the request has been proxied and validated
+    let permit = match &state.concurrency_limit {
+        Some(concurrency_limit) => match concurrency_limit.acquire().await {
+            Some(permit) => Some(permit),
+            None => {
+                state.metrics.lock().unwrap().rejected_requests += 1;
+                info!("Rejecting request because the concurrency limit was reached");
+                return (
+                    axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                    [(
+                        axum::http::header::RETRY_AFTER,
+                        concurrency_limit.retry_after.to_string(),
+                    )],
+                    "The proxy is handling too many requests",
+                )
+                    .into_response();
+            }
+        },
+        None => None,
+    };
+    let (method, uri, headers) = request_summary(&request);
+    // Chunked responses are passed to the client as soon as the upstream starts sending them, while
+    // the handler keeps running until the exchange has been validated
+    let (passthrough, passthrough_response) = tokio::sync::oneshot::channel();
+    let handler = inner_handler(state.clone(), request, passthrough);
+    let handled = tokio::spawn(async move {
+        let _permit = permit;
+        contain_internal_errors(&state, method, uri, headers, handler).await
+    });
+    match passthrough_response.await {
+        Ok(response) => response,
+        Err(_) => handled.await.unwrap_or_else(|err| {
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                err.to_string(),
+            )
+                .into_response()
+        }),
+    }
+}
+
+/// The parts of a request that are needed to report it if handling it fails.
+fn request_summary(
+    request: &Request,
+) -> (axum::http::Method, axum::http::Uri, axum::http::HeaderMap) {
+    (
+        request.method().clone(),
+        request.uri().clone(),
+        request.headers().clone(),
+    )
+}
+
+/// A request the proxy couldn't handle, like one whose body couldn't be read or that the upstream
+/// didn't answer. The client receives the status, and the request is recorded as a testcase with
+/// an InternalProxyError failure that carries the text.
+#[derive(Debug)]
+struct HandlerError {
+    status: axum::http::StatusCode,
+    text: String,
+}
+
+impl HandlerError {
+    fn new(status: axum::http::StatusCode, text: String) -> Self {
+        HandlerError { status, text }
+    }
+}
+
+/// Records the requests a handler failed on, so instead of the client's connection being dropped
+/// without a trace, the rest of the suite keeps running and the problem shows up in the report.
+/// A panic is a bug in the proxy rather than a failure the handler expected. It is caught as a
+/// last resort so it only affects the request that caused it, and the client receives a 500.
+async fn contain_internal_errors(
+    state: &AppState,
+    method: axum::http::Method,
+    uri: axum::http::Uri,
+    headers: axum::http::HeaderMap,
+    handler: impl std::future::Future<Output = Result<axum::response::Response, HandlerError>>,
+) -> axum::response::Response {
     let time_start = std::time::Instant::now();
-    let response = outgoing_request.send_bytes(&body).or_any_status().unwrap();
+    let (status, text) = match std::panic::AssertUnwindSafe(handler).catch_unwind().await {
+        Ok(Ok(response)) => return response,
+        Ok(Err(err)) => (err.status, err.text),
+        Err(panic) => {
+            let diagnostic = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "The handler panicked".to_string());
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "The proxy failed while handling the request: {}",
+                    diagnostic
+                ),
+            )
+        }
+    };
+    error!(%text, "Failed to handle request");
+    let (correlation_id, _) = read_correlation_id(&headers, &state.proxy_headers);
+    let mut properties = vec![];
+    properties.extend(run_property(&state.runs).await);
+    properties.extend(tenant_property(&headers, &state.proxy_headers));
+    properties.extend(state.captured_headers.request(&headers));
+    properties.extend(state.current_spec().unsupported_version.clone());
+    properties.push(TestcaseProperty {
+        name: "path".to_string(),
+        value: uri.path().to_string(),
+    });
+    properties.push(TestcaseProperty {
+        name: "method".to_string(),
+        value: method.to_string(),
+    });
+    properties.push(TestcaseProperty {
+        name: "correlationId".to_string(),
+        value: correlation_id.clone(),
+    });
+    properties.sort();
+    let path_and_query = uri
+        .path_and_query()
+        .map_or(uri.path(), |path_and_query| path_and_query.as_str());
+    let mut cases = state.testcases.lock().await;
+    record_testcase(
+        &mut cases,
+        state.results.as_deref(),
+        Testcase {
+            name: format!("{} {} {}", method, path_and_query, correlation_id),
+            failures: vec![TestcaseFailure {
+                text: text.clone(),
+                r#type: TestcaseFailureType::InternalProxyError,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            }],
+            properties,
+            time: format!("{:.2}", time_start.elapsed().as_secs_f64()),
+            reproduction: None,
+            skipped: None,
+            warnings: vec![],
+            recorded_at: None,
+            evidence: vec![],
+        },
+    );
+    (status, text).into_response()
+}
+
+async fn inner_handler(
+    State(AppState {
+        spec: current_spec,
+        upstream,
+        upstreams,
+        base_path,
+        testcases,
+        metrics,
+        validation_toggles,
+        pending_links,
+        sequences,
+        sequence_events,
+        golden,
+        artifacts,
+        evidence,
+        client,
+        validation_cache,
+        json_checks,
+        operations,
+        runs,
+        proxy_headers,
+        captured_headers,
+        results,
+        recording,
+        enforce,
+        unmatched_paths,
+        forwarded_headers,
+        trace_context,
+        host: upstream_host,
+        body_limits,
+        suppressed_failures,
+        severities,
+        ..
+    }): State<AppState>,
+    request: Request,
+    passthrough: tokio::sync::oneshot::Sender<axum::response::Response>,
+) -> Result<axum::response::Response, HandlerError> {
+    // The testcase's time is the whole time the proxy spent on the request, and the properties
+    // break it down into the upstream's latency and the proxy's own validation
+    let handler_start = std::time::Instant::now();
+    let mut in_flight = InFlightGuard::new(metrics);
+    let current_spec = current_spec.read().unwrap().clone();
+    let LoadedSpec {
+        spec,
+        unsupported_version,
+        wayfinder,
+        base_paths,
+        ..
+    } = &*current_spec;
+    let mut failures = vec![];
+    let mut warnings = vec![];
+    let mut properties = vec![];
+    let skip_validation = skip_validation_requested(request.headers(), &proxy_headers);
+    let enforce =
+        !skip_validation && (enforce || enforce::requested(request.headers(), &proxy_headers));
+    properties.extend(run_property(&runs).await);
+    properties.extend(tenant_property(request.headers(), &proxy_headers));
+    properties.extend(unsupported_version.clone());
+    let method = request.method().clone();
+    let path = request.uri().path();
+    let path_and_query = request.uri().path_and_query().cloned().ok_or_else(|| {
+        HandlerError::new(
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Request target {} does not have a path", request.uri()),
+        )
+    })?;
+    let route = upstream::find_route(&upstreams, path);
+    let url = match route {
+        Some(route) => route.url(path, path_and_query.query()),
+        None => upstream.join(path_and_query.as_str()).map_err(|err| {
+            HandlerError::new(
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "Failed to join {} to the upstream {}: {}",
+                    path_and_query, upstream, err
+                ),
+            )
+        })?,
+    };
+    // The path templates in the spec are relative to the base path. The upstream's path is where
+    // the API is mounted, which is usually the same.
+    let path = match &base_path {
+        Some(base_path) => spec_path(path, &[base_path]),
+        None => {
+            let mut candidates = base_paths.iter().map(String::as_str).collect::<Vec<&str>>();
+            if route.is_none() {
+                candidates.push(upstream.path());
+            }
+            spec_path(path, &candidates)
+        }
+    };
+    info!(
+        method = method.as_str(),
+        url = url.to_string(),
+        "Handling request"
+    );
+    properties.push(TestcaseProperty {
+        name: "path".to_string(),
+        value: path.to_string(),
+    });
+    properties.push(TestcaseProperty {
+        name: "method".to_string(),
+        value: method.to_string(),
+    });
+    if let Some(query) = path_and_query.query() {
+        properties.push(TestcaseProperty {
+            name: "query".to_string(),
+            value: query.to_string(),
+        });
+    }
+    properties.push(TestcaseProperty {
+        name: "upstreamUrl".to_string(),
+        value: url.to_string(),
+    });
+
+    // Paths whose percent-encoding isn't valid UTF-8 can't be matched to a path template
+    let wayfinder_path = wayfind::Path::new(&path).map_err(|err| {
+        HandlerError::new(
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Path {} could not be decoded: {}", path, err),
+        )
+    })?;
+    let wayfinder_match = wayfinder.search(&wayfinder_path).map_err(|err| {
+        HandlerError::new(
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("Path {} could not be matched: {}", path, err),
+        )
+    })?;
+    let mut path_parameters = BTreeMap::new();
+    match &wayfinder_match {
+        Some(wayfound) => {
+            // Harness requests that skip validation don't count towards coverage
+            if !skip_validation {
+                in_flight.set_operation(format!("{} {}", method, wayfound.route));
+            }
+            properties.push(TestcaseProperty {
+                name: "pathTemplate".to_string(),
+                value: wayfound.route.to_string(),
+            });
+            for parameter in wayfound.parameters.iter() {
+                properties.push(TestcaseProperty {
+                    name: format!("pathParameter-{}", parameter.key),
+                    value: parameter.value.to_string(),
+                });
+                path_parameters.insert(parameter.key.to_string(), parameter.value.to_string());
+            }
+        }
+        None if unmatched_paths == UnmatchedPaths::Fail => {
+            failures.push(TestcaseFailure {
+                text: "Path not found".to_string(),
+                r#type: TestcaseFailureType::PathNotFound,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
+        None => {}
+    }
+    // Paths that aren't in the spec can be left out of the report, or reported as skipped
+    let unmatched = wayfinder_match.is_none();
+    let skipped = match skip_validation {
+        true => Some(format!(
+            "Validation was skipped with the {} header",
+            proxy_headers.skip_validation()
+        )),
+        false => (unmatched && unmatched_paths == UnmatchedPaths::Skip)
+            .then(|| "Path not found in the spec".to_string()),
+    };
+    let wayfinder_path = wayfinder_match.map(|m| m.route.to_string());
+    let query = url
+        .query_pairs()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Vec<(String, String)>>();
+    let path_item = wayfinder_path
+        .as_ref()
+        .and_then(|route| spec.paths.paths.get(route))
+        .and_then(ReferenceOr::as_item);
+    let operation = path_item.and_then(|path_item| operation_for_method(path_item, &method));
+    let request_validation_start = std::time::Instant::now();
+    if let (Some(path_item), Some(operation), false) = (path_item, operation, skip_validation) {
+        let request_parameters = parameters::RequestParameters {
+            query: &query,
+            headers: request.headers(),
+            path: &path_parameters,
+        };
+        failures.extend(security::validate_security(
+            operation,
+            &request_parameters,
+            spec,
+        ));
+        failures.extend(parameters::validate_parameters(
+            path_item,
+            operation,
+            &request_parameters,
+            spec,
+            &json_checks.for_operation(operation.operation_id.as_deref(), &operations),
+        ));
+    }
+    let mut request_validation = request_validation_start.elapsed();
+
+    // The correlation ID is set separately because one is generated if the client didn't send it
+    let mut skip = proxy_headers.stripped();
+    skip.push(proxy_headers.correlation_id());
+    let (mut outgoing_headers, mut header_failures) = request_headers(request.headers(), &skip);
+    failures.append(&mut header_failures);
+    let host = match &upstream_host {
+        upstream::Host::Upstream => Some(upstream::authority(&url)),
+        upstream::Host::Preserve => None,
+        upstream::Host::Override(host) => Some(host.clone()),
+    };
+    if let Some(host) = host {
+        set_header(
+            &mut outgoing_headers,
+            axum::http::header::HOST.as_str(),
+            &host,
+        );
+    }
+    // The correlation ID is what is used to specify the name of the testcase. If the client
+    // supplied one, use that. Otherwise, generate a new one.
+    let (mut correlation_id, correlation_id_failure) =
+        read_correlation_id(request.headers(), &proxy_headers);
+    failures.extend(correlation_id_failure);
+    let correlation_id_generated = !request
+        .headers()
+        .contains_key(proxy_headers.correlation_id());
+    if !proxy_headers.strip_upstream {
+        set_header(
+            &mut outgoing_headers,
+            proxy_headers.correlation_id().as_str(),
+            &correlation_id,
+        );
+    }
+    // If the client supplied a list of headers to fuse, add them to the outgoing request
+    let fuse_headers = request
+        .headers()
+        .get(proxy_headers.fused_correlation_headers())
+        .and_then(|value| value.to_str().ok());
+    if let Some(fuse_headers) = fuse_headers {
+        let fuse_headers = fuse_headers.to_string();
+        for header in fuse_headers.split(",") {
+            let header = header.trim();
+            if header.is_empty() {
+                continue;
+            }
+            set_header(&mut outgoing_headers, header, &correlation_id);
+        }
+    }
+    if trace_context {
+        let trace = trace::context(request.headers(), &correlation_id);
+        set_header(&mut outgoing_headers, "traceparent", &trace.traceparent);
+        if !trace.propagated {
+            outgoing_headers.retain(|(name, _)| name != "tracestate");
+        }
+        properties.push(TestcaseProperty {
+            name: "traceId".to_string(),
+            value: trace.trace_id,
+        });
+    }
+    if forwarded_headers {
+        let (client, host) = forwarded::origin(&request);
+        forwarded::add_forwarded_headers(&mut outgoing_headers, client, host.as_deref());
+    }
+
+    properties.push(TestcaseProperty {
+        name: "correlationId".to_string(),
+        value: correlation_id.to_string(),
+    });
+    let mut testcase_name = format!("{} {} {}", method, path_and_query, correlation_id);
+    let request_headers = request.headers().clone();
+    properties.extend(captured_headers.request(&request_headers));
+    // Bodies over the limit are streamed to the upstream. They are left out of the reproduction
+    // command, recordings and artifacts.
+    let (body, streamed_request_body) =
+        buffer_request_body(request.into_body(), body_limits.request, &mut failures).await?;
+    if streamed_request_body.is_none() {
+        properties.push(in_flight.request_body(body.len()));
+    }
+    // Requests for operations without a request body are only validated when they send one
+    let body_validation_start = std::time::Instant::now();
+    if let (Some(operation), Some(route), None, false) = (
+        operation.filter(|operation| operation.request_body.is_some() || !body.is_empty()),
+        wayfinder_path.as_ref(),
+        streamed_request_body.as_ref(),
+        skip_validation,
+    ) {
+        let (mut body_failures, mut body_properties) = validate_request_body(
+            &request_headers,
+            &body,
+            operation,
+            spec,
+            &json_checks.for_operation(operation.operation_id.as_deref(), &operations),
+            &validation_cache,
+            format!("request {} {}", method, route),
+        );
+        locate_in_spec(
+            &mut body_failures,
+            &format!(
+                "#/paths/{}/{}",
+                json::escape(route),
+                method.as_str().to_lowercase()
+            ),
+        );
+        failures.append(&mut body_failures);
+        properties.append(&mut body_properties);
+    }
+    request_validation += body_validation_start.elapsed();
+    properties.push(timing_property("requestValidationMs", request_validation));
+    let reproduction =
+        curl::reproduction_command(method.as_str(), url.as_str(), &outgoing_headers, &body);
+    if enforce {
+        let toggles = validation_toggles.lock().await.clone();
+        failures.retain(|failure| toggles.is_enabled(failure.family()));
+        properties.extend(suppress_failures(&mut failures, &suppressed_failures));
+        warnings.extend(take_warnings(&mut failures, &severities));
+        if enforce::rejects(&failures) {
+            info!(correlation_id, "Rejecting request that failed validation");
+            let mut response = enforce::reject_request(&correlation_id, &failures);
+            if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+                response
+                    .headers_mut()
+                    .append(proxy_headers.correlation_id(), correlation_id);
+            }
+            if let Some(operation) = operation {
+                if let Some(operation_id) = &operation.operation_id {
+                    properties.push(TestcaseProperty {
+                        name: "operationId".to_string(),
+                        value: operation_id.to_string(),
+                    });
+                }
+                properties.extend(operation_properties(operation));
+            }
+            if let Some(artifacts) = artifacts.as_ref() {
+                properties.extend(artifacts.write(&correlation_id, &body, None));
+            }
+            let evidence = match evidence.as_ref() {
+                Some(evidence) => evidence.exchange((&request_headers, &body), None),
+                None => vec![],
+            };
+            properties.push(TestcaseProperty {
+                name: "enforcedStatus".to_string(),
+                value: response.status().as_u16().to_string(),
+            });
+            properties.sort();
+            let mut cases = testcases.lock().await;
+            record_testcase(
+                &mut cases,
+                results.as_deref(),
+                Testcase {
+                    name: testcase_name,
+                    failures,
+                    properties,
+                    time: format!("{:.2}", handler_start.elapsed().as_secs_f64()),
+                    reproduction: Some(reproduction),
+                    skipped: None,
+                    warnings,
+                    recorded_at: None,
+                    evidence,
+                },
+            );
+            return Ok(response);
+        }
+    }
+    let started = std::time::SystemTime::now();
+    let time_start = std::time::Instant::now();
+    let response = upstream::send(
+        &client,
+        method.as_str(),
+        &url,
+        &outgoing_headers,
+        streamed_request_body.unwrap_or_else(|| body.clone().into()),
+    )
+    .await
+    .map_err(|err| {
+        HandlerError::new(
+            axum::http::StatusCode::BAD_GATEWAY,
+            format!("Failed to send the request upstream: {}", err),
+        )
+    })?;
+    let (parts, response_body) = response.into_parts();
+    // Clients that can't send a correlation ID are named by the one the upstream echoes instead
+    if let Some(upstream_correlation_id) = proxy_headers
+        .upstream_correlation_id(&parts.headers)
+        .filter(|_| correlation_id_generated)
+    {
+        correlation_id = upstream_correlation_id;
+        testcase_name = format!("{} {} {}", method, path_and_query, correlation_id);
+        if let Some(property) = properties
+            .iter_mut()
+            .find(|property| property.name == "correlationId")
+        {
+            property.value = correlation_id.clone();
+        }
+    }
+    // Chunked responses reach the client as they arrive, unless they have to be validated before
+    // the client can receive them. The client doesn't see the end of the body until the exchange
+    // has been recorded.
+    let chunked = parts
+        .headers
+        .get(axum::http::header::TRANSFER_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.to_ascii_lowercase().contains("chunked"));
+    let (buffered, validated) = if chunked && !enforce {
+        let (client_body, buffered, validated) = body::tee(response_body, body_limits.response);
+        let (mut client_headers, _) = response_headers(&parts.headers);
+        if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+            client_headers.append(proxy_headers.correlation_id(), correlation_id);
+        }
+        let _ = passthrough.send((parts.status, client_headers, client_body).into_response());
+        // The client already has the status, so the failure can only be recorded
+        let buffered = match buffered.await {
+            Ok(Some(body)) => body::Buffered::Complete(body),
+            // The whole body was already passed through
+            Ok(None) => body::Buffered::Streaming(axum::body::Body::empty()),
+            Err(_) => {
+                return Err(HandlerError::new(
+                    axum::http::StatusCode::BAD_GATEWAY,
+                    "The upstream response ended early or the client stopped reading it"
+                        .to_string(),
+                ))
+            }
+        };
+        (buffered, Some(validated))
+    } else {
+        let buffered = body::buffer(response_body, body_limits.response)
+            .await
+            .map_err(|err| {
+                HandlerError::new(
+                    axum::http::StatusCode::BAD_GATEWAY,
+                    format!("Failed to read the upstream response: {}", err),
+                )
+            })?;
+        (buffered, None)
+    };
+    let (response_body, streamed_response_body) = match buffered {
+        body::Buffered::Complete(body) => {
+            properties.push(in_flight.response_body(body.len()));
+            (Some(body.to_vec()), None)
+        }
+        body::Buffered::Streaming(stream) => {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "Response body is larger than the {} bytes allowed by --max-response-body and was not validated",
+                    body_limits.response
+                ),
+                r#type: TestcaseFailureType::BodyTooLargeToValidate,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            (None, Some(stream))
+        }
+    };
     let time_end = std::time::Instant::now();
     let duration = time_end - time_start;
-    let mut validated_response = validate_response(response, method, &spec, wayfinder_path);
+    properties.push(timing_property("upstreamLatencyMs", duration));
+    // Compressed bodies are validated decompressed, but the client receives them as the upstream
+    // sent them
+    let (response_body, encoded_response_body) = match response_body {
+        Some(body) => {
+            let content_encoding = parts
+                .headers
+                .get(axum::http::header::CONTENT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            match tokio::task::block_in_place(|| {
+                compression::decompress(content_encoding, &body, body_limits.response)
+            }) {
+                Ok(None) => (Some(body), None),
+                Ok(Some(decompressed)) => (Some(decompressed), Some(body)),
+                Err(err) => {
+                    failures.push(match err {
+                        compression::Error::TooLarge => TestcaseFailure {
+                            text: format!(
+                                "Response body is larger than the {} bytes allowed by --max-response-body once decompressed and was not validated",
+                                body_limits.response
+                            ),
+                            r#type: TestcaseFailureType::BodyTooLargeToValidate,
+                            json_pointer: None,
+                            spec_pointer: None,
+                            perspective: None,
+                        },
+                        compression::Error::Invalid(err) => TestcaseFailure {
+                            text: format!("Failed to decompress response body: {}", err),
+                            r#type: TestcaseFailureType::DecodingFailed,
+                            json_pointer: None,
+                            spec_pointer: None,
+                            perspective: None,
+                        },
+                    });
+                    (None, Some(body))
+                }
+            }
+        }
+        None => (None, None),
+    };
+    let response_body_validated = response_body.is_some();
+    let response_validation_start = std::time::Instant::now();
+    let mut validated_response = tokio::task::block_in_place(|| {
+        validate_response(
+            UpstreamResponse {
+                status: parts.status.as_u16(),
+                headers: parts.headers,
+                body: response_body,
+            },
+            method.clone(),
+            spec,
+            // Responses to requests that skip validation are only read
+            wayfinder_path.filter(|_| !skip_validation),
+            &json_checks,
+            &operations,
+            &validation_cache,
+        )
+    });
+    properties.push(timing_property(
+        "responseValidationMs",
+        response_validation_start.elapsed(),
+    ));
     failures.append(&mut validated_response.failures);
+    show_spec_locations(&mut failures, current_spec.as_ref());
+    properties.extend(captured_headers.response(&validated_response.headers));
+
+    let expression_context = links::ExpressionContext {
+        url: url.as_str(),
+        method: method.as_str(),
+        status: validated_response.status,
+        path_parameters: &path_parameters,
+        query: &query,
+        request_headers: &request_headers,
+        request_body: &body,
+        response_headers: &validated_response.headers,
+        response_body: &validated_response.body,
+    };
+    if let Some(operation_id) = &validated_response.operation_id {
+        let mut pending_links = pending_links.lock().await;
+        let (mut link_failures, mut link_properties) = links::check_pending_links(
+            &pending_links,
+            &correlation_id,
+            operation_id,
+            &expression_context,
+        );
+        failures.append(&mut link_failures);
+        properties.append(&mut link_properties);
+        for (name, link) in validated_response.links.iter() {
+            let operation_id = match links::link_operation_id(link, spec) {
+                Some(operation_id) => operation_id,
+                None => {
+                    debug!(
+                        "Skipping link {} because its operation could not be found",
+                        name
+                    );
+                    continue;
+                }
+            };
+            let parameters = link
+                .parameters
+                .iter()
+                .filter_map(|(parameter, expression)| {
+                    links::evaluate_expression(expression, &expression_context)
+                        .map(|value| (parameter.to_string(), value))
+                })
+                .collect();
+            pending_links.push(links::PendingLink {
+                correlation_id: correlation_id.clone(),
+                name: name.to_string(),
+                source_testcase: testcase_name.clone(),
+                operation_id,
+                parameters,
+            });
+        }
+        drop(pending_links);
+
+        let mut sequence_events = sequence_events.lock().await;
+        failures.extend(sequences::check_sequences(
+            &sequences,
+            &sequence_events,
+            &correlation_id,
+            operation_id,
+            &expression_context,
+        ));
+        sequences::start_sequences(
+            &sequences,
+            &mut sequence_events,
+            &correlation_id,
+            operation_id,
+            &testcase_name,
+            &expression_context,
+        );
+        drop(sequence_events);
+
+        if let Some(golden) = golden.as_ref().filter(|_| response_body_validated) {
+            let (mut golden_failures, mut golden_properties) = golden.check(
+                operation_id,
+                validated_response.status,
+                &validated_response.body,
+            );
+            failures.append(&mut golden_failures);
+            properties.append(&mut golden_properties);
+        }
+    }
+    if let Some(recording) = recording.as_ref() {
+        recording.append(&har::Exchange {
+            started,
+            duration,
+            correlation_id: &correlation_id,
+            method: method.as_str(),
+            url: &url,
+            request_headers: &outgoing_headers,
+            request_body: &body,
+            status: validated_response.status,
+            response_headers: &validated_response.headers,
+            response_body: &validated_response.body,
+        });
+    }
+    if skip_validation {
+        // The operation is still named in the testcase even though it wasn't validated
+        if let Some(operation) = operation {
+            if let Some(operation_id) = &operation.operation_id {
+                properties.push(TestcaseProperty {
+                    name: "operationId".to_string(),
+                    value: operation_id.to_string(),
+                });
+            }
+            properties.extend(operation_properties(operation));
+        }
+        // Failures found while forwarding the request, like headers that couldn't be forwarded,
+        // aren't reported either
+        failures.clear();
+    }
+    let toggles = validation_toggles.lock().await.clone();
+    failures.retain(|failure| toggles.is_enabled(failure.family()));
+    properties.extend(suppress_failures(&mut failures, &suppressed_failures));
+    warnings.extend(take_warnings(&mut failures, &severities));
+    if let Some(artifacts) = artifacts.as_ref().filter(|_| !failures.is_empty()) {
+        properties.extend(artifacts.write(&correlation_id, &body, Some(&validated_response.body)));
+    }
+    let evidence = match evidence.as_ref().filter(|_| !failures.is_empty()) {
+        Some(evidence) => evidence.exchange(
+            (&request_headers, &body),
+            Some((&validated_response.headers, &validated_response.body)),
+        ),
+        None => vec![],
+    };
     properties.append(&mut validated_response.properties);
+    // Request failures were already rejected, so any that are left came from the response
+    let rejection = (enforce && enforce::rejects(&failures))
+        .then(|| enforce::reject_response(&correlation_id, &failures));
+    if let Some(rejection) = &rejection {
+        info!(correlation_id, "Rejecting response that failed validation");
+        properties.push(TestcaseProperty {
+            name: "enforcedStatus".to_string(),
+            value: rejection.status().as_u16().to_string(),
+        });
+    }
     properties.sort();
-    let mut cases = testcases.lock().await;
-    cases.push(Testcase {
-        name: testcase_name,
-        failures,
-        properties,
-        time: format!("{:.2}", duration.as_secs_f64()),
-    });
-    drop(cases);
+    if !(unmatched && unmatched_paths == UnmatchedPaths::Forward) {
+        let mut cases = testcases.lock().await;
+        let reproduction = if failures.is_empty() {
+            None
+        } else {
+            Some(reproduction)
+        };
+        record_testcase(
+            &mut cases,
+            results.as_deref(),
+            Testcase {
+                name: testcase_name,
+                failures,
+                properties,
+                time: format!("{:.2}", handler_start.elapsed().as_secs_f64()),
+                reproduction,
+                skipped,
+                warnings,
+                recorded_at: None,
+                evidence,
+            },
+        );
+    }
+    // The client that is reading a passed through body can now see its end
+    if let Some(validated) = validated {
+        let _ = validated.send(());
+    }
+    if let Some(mut rejection) = rejection {
+        if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+            rejection
+                .headers_mut()
+                .append(proxy_headers.correlation_id(), correlation_id);
+        }
+        return Ok(rejection);
+    }
     let status = validated_response.status;
     let mut response_headers = validated_response.headers;
-    response_headers.append(
-        "OVP-Correlation-Id",
-        HeaderValue::from_bytes(correlation_id.as_bytes()).unwrap(),
-    );
-    let body = validated_response.body;
+    if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+        response_headers.append(proxy_headers.correlation_id(), correlation_id);
+    }
+    let body = match (streamed_response_body, encoded_response_body) {
+        (Some(stream), _) => stream,
+        (None, Some(body)) => body.into(),
+        (None, None) => validated_response.body.into(),
+    };
 
-    (
+    Ok((
         axum::http::status::StatusCode::from_u16(status)
             .unwrap_or(axum::http::status::StatusCode::INTERNAL_SERVER_ERROR),
         response_headers,
         body,
     )
+        .into_response())
+}
+
+/// The headers of a request that is being forwarded. Repeated headers are forwarded as separate
+/// values, because values like dates can contain commas. Cookie values are joined with semicolons
+/// into a single header, which is how HTTP/2 cookies are sent over HTTP/1.1.
+///
+/// Values that aren't valid UTF-8 can't be written to the reproduction command or a recording, so
+/// they are left out of the forwarded request and reported as failures.
+fn request_headers(
+    headers: &axum::http::HeaderMap,
+    skip: &[HeaderName],
+) -> (Vec<(String, String)>, Vec<TestcaseFailure>) {
+    let mut outgoing_headers = vec![];
+    let mut failures = vec![];
+    for key in headers.keys() {
+        if skip.contains(key) {
+            continue;
+        }
+        let mut values = vec![];
+        for value in headers.get_all(key).iter() {
+            match value.to_str() {
+                Ok(value) => values.push(value),
+                Err(_) => failures.push(TestcaseFailure {
+                    text: format!(
+                        "Request header {} has a value that is not valid UTF-8 and was not forwarded",
+                        key
+                    ),
+                    r#type: TestcaseFailureType::MalformedHeader,
+                    json_pointer: None,
+                    spec_pointer: None,
+                    perspective: None,
+                }),
+            }
+        }
+        if key == axum::http::header::COOKIE && !values.is_empty() {
+            outgoing_headers.push((key.to_string(), values.join("; ")));
+        } else {
+            outgoing_headers.extend(
+                values
+                    .into_iter()
+                    .map(|value| (key.to_string(), value.to_string())),
+            );
+        }
+    }
+    (outgoing_headers, failures)
+}
+
+/// Replaces the value of a header that is being forwarded, or adds the header if it isn't there.
+fn set_header(headers: &mut Vec<(String, String)>, name: &str, value: &str) {
+    let name = name.to_ascii_lowercase();
+    headers.retain(|(existing, _)| *existing != name);
+    headers.push((name, value.to_string()));
+}
+
+/// A property with a duration in milliseconds. Validation usually takes well under the hundredth
+/// of a second the testcase time is rounded to.
+fn timing_property(name: &str, duration: std::time::Duration) -> TestcaseProperty {
+    TestcaseProperty {
+        name: name.to_string(),
+        value: format!("{:.3}", duration.as_secs_f64() * 1000.0),
+    }
+}
+
+/// Whether the client asked for the request to be forwarded without being validated. Only `true` is
+/// accepted, like the enforce header.
+fn skip_validation_requested(
+    headers: &axum::http::HeaderMap,
+    proxy_headers: &headers::ProxyHeaders,
+) -> bool {
+    headers
+        .get(proxy_headers.skip_validation())
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
+}
+
+/// Reads the correlation ID sent with a request, generating one if it is missing. A correlation ID
+/// that is not valid UTF-8 is replaced by a generated one and reported as a failure.
+fn read_correlation_id(
+    headers: &axum::http::HeaderMap,
+    proxy_headers: &headers::ProxyHeaders,
+) -> (String, Option<TestcaseFailure>) {
+    let header = proxy_headers.correlation_id();
+    match headers.get(&header).map(|value| value.to_str()) {
+        Some(Ok(correlation_id)) => (correlation_id.to_string(), None),
+        Some(Err(_)) => (
+            uuid::Uuid::new_v4().to_string(),
+            Some(TestcaseFailure {
+                text: format!(
+                    "Request header {} is not valid UTF-8 so a new correlation ID was generated",
+                    header
+                ),
+                r#type: TestcaseFailureType::MalformedHeader,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            }),
+        ),
+        None => (uuid::Uuid::new_v4().to_string(), None),
+    }
+}
+
+/// Copies the headers from an upstream response. Every value of a repeated header, like
+/// Set-Cookie, is kept as a separate header.
+///
+/// Headers whose values aren't valid UTF-8 are reported as failures instead of being forwarded,
+/// the same as request headers.
+fn response_headers(
+    response_headers: &axum::http::HeaderMap,
+) -> (axum::http::HeaderMap, Vec<TestcaseFailure>) {
+    let mut headers = axum::http::HeaderMap::new();
+    let mut failures = vec![];
+    for (name, value) in response_headers.iter() {
+        // Transfer-Encoding only applies to the upstream connection. The response to the client is
+        // framed again, and is still chunked when its length isn't known
+        if name == axum::http::header::TRANSFER_ENCODING {
+            continue;
+        }
+        if value.to_str().is_err() {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "Response header {} has a value that is not valid UTF-8 and was not forwarded",
+                    name
+                ),
+                r#type: TestcaseFailureType::MalformedHeader,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            continue;
+        }
+        headers.append(name.clone(), value.clone());
+    }
+    (headers, failures)
+}
+
+/// Returns a failure if a message included more than one distinct Content-Type header, since only
+/// the first one is used for validation.
+fn validate_single_content_type(
+    headers: &axum::http::HeaderMap,
+    perspective: ValidationPerspective,
+) -> Option<TestcaseFailure> {
+    let mut content_types = headers
+        .get_all("Content-Type")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<&str>>();
+    content_types.dedup();
+    if content_types.len() < 2 {
+        return None;
+    }
+    let subject = match perspective {
+        ValidationPerspective::Request => "Request",
+        ValidationPerspective::Response => "Response",
+    };
+    Some(TestcaseFailure {
+        text: format!(
+            "{} included multiple Content-Type headers: {}",
+            subject,
+            content_types.join(", ")
+        ),
+        r#type: TestcaseFailureType::MismatchedContentTypeHeader,
+        json_pointer: None,
+        spec_pointer: None,
+        perspective: None,
+    })
+}
+
+/// Returns the response the operation declares for the status code, along with its key in the
+/// responses. Status codes without a response of their own use the response for their range,
+/// like 4XX, and then the default response, if the operation has one.
+fn response_for_status(
+    operation: &openapiv3::Operation,
+    status: u16,
+) -> Option<(String, &ReferenceOr<openapiv3::Response>)> {
+    let responses = &operation.responses.responses;
+    [
+        openapiv3::StatusCode::Code(status),
+        openapiv3::StatusCode::Range(status / 100),
+    ]
+    .into_iter()
+    .find_map(|status| {
+        responses
+            .get(&status)
+            .map(|response| (status.to_string(), response))
+    })
+    .or_else(|| {
+        operation
+            .responses
+            .default
+            .as_ref()
+            .map(|response| ("default".to_string(), response))
+    })
+}
+
+/// Returns the operation in the path item for the given HTTP method, if one is defined.
+fn operation_for_method<'a>(
+    path: &'a openapiv3::PathItem,
+    method: &axum::http::Method,
+) -> Option<&'a openapiv3::Operation> {
+    match *method {
+        axum::http::Method::DELETE => path.delete.as_ref(),
+        axum::http::Method::GET => path.get.as_ref(),
+        axum::http::Method::HEAD => path.head.as_ref(),
+        axum::http::Method::OPTIONS => path.options.as_ref(),
+        axum::http::Method::PATCH => path.patch.as_ref(),
+        axum::http::Method::POST => path.post.as_ref(),
+        axum::http::Method::PUT => path.put.as_ref(),
+        axum::http::Method::TRACE => path.trace.as_ref(),
+        _ => None,
+    }
 }
 
 fn validate_response(
-    response: ureq::Response,
+    response: UpstreamResponse,
     method: axum::http::Method,
     spec: &openapiv3::OpenAPI,
     wayfinder_path: Option<String>,
+    json_checks: &JsonChecks,
+    operations: &BTreeMap<String, OperationConfig>,
+    validation_cache: &cache::ValidationCache,
 ) -> ValidatedResponse {
-    let failures = vec![];
     let mut properties = vec![];
-    let status = response.status();
+    let status = response.status;
     properties.push(TestcaseProperty {
         name: "statusCode".to_string(),
         value: status.to_string(),
     });
-    let mut headers = axum::http::HeaderMap::new();
-    for name in &response.headers_names() {
-        // This proxy server does not support Transfer-Encoding
-        if name.to_lowercase() == "transfer-encoding" {
-            continue;
-        }
-        let key = HeaderName::from_str(name).unwrap();
-        let value = response.header(name).unwrap_or("");
-        let value = HeaderValue::from_str(value).unwrap_or(HeaderValue::from_static(""));
-        headers.insert(key, value);
-    }
-    let body_bytes = match status {
-        204 | 304 => vec![],
-        _ => {
-            let mut buffer: Vec<u8> = vec![];
-            // Failing to read the response body probably means a body wasn't included in the response.
-            // If that's the case, just return the empty buffer.
-            response.into_reader().read_to_end(&mut buffer).unwrap_or(0);
-            buffer
-        }
-    };
+    let (headers, failures) = response_headers(&response.headers);
+    let streamed = response.body.is_none();
 
     let mut validated = ValidatedResponse {
-        body: body_bytes,
+        body: response.body.unwrap_or_default(),
         failures,
         headers: headers.clone(),
         method: method.clone(),
         properties,
         status,
+        operation_id: None,
+        links: vec![],
+    };
+
+    if wayfinder_path.is_none() {
+        return validated;
+    }
+
+    let wayfinder_path = wayfinder_path.unwrap();
+    // The path templates are matched with a router built from the same spec
+    let Some(path) = spec.paths.paths.get(&wayfinder_path) else {
+        validated.failures.push(TestcaseFailure {
+            text: format!(
+                "Path template {} was matched but is not in the spec",
+                wayfinder_path
+            ),
+            r#type: TestcaseFailureType::InternalProxyError,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return validated;
+    };
+    let path = path.as_item();
+    if path.is_none() {
+        validated.failures.push(TestcaseFailure {
+            text: "Invalid HTTP method".to_string(),
+            r#type: TestcaseFailureType::PathNotFound,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return validated;
+    }
+    let path = path.unwrap();
+    let operation = operation_for_method(path, &method);
+    if operation.is_none() {
+        validated.failures.push(TestcaseFailure {
+            text: "Invalid HTTP method".to_string(),
+            r#type: TestcaseFailureType::InvalidHTTPMethod,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return validated;
+    }
+    let operation = operation.unwrap();
+    if let Some(operation_id) = &operation.operation_id {
+        validated.properties.push(TestcaseProperty {
+            name: "operationId".to_string(),
+            value: operation_id.to_string(),
+        });
+        validated.operation_id = Some(operation_id.to_string());
+    }
+    validated.properties.extend(operation_properties(operation));
+    let json_checks = &json_checks.for_operation(operation.operation_id.as_deref(), operations);
+    // Failures caused by the spec are located from the operation
+    let operation_pointer = format!(
+        "#/paths/{}/{}",
+        json::escape(&wayfinder_path),
+        method.as_str().to_lowercase()
+    );
+    let spec_response = response_for_status(operation, status);
+    if spec_response.is_none() {
+        validated.failures.push(TestcaseFailure {
+            text: "Response not found for status code".to_string(),
+            r#type: TestcaseFailureType::InvalidStatusCode,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return validated;
+    }
+    let (status_key, spec_response) = spec_response.unwrap();
+    let response_segment = format!("{}/responses/{}", operation_pointer, status_key);
+    let response = resolve_response(spec_response, spec);
+    if response.is_none() {
+        validated.failures.push(TestcaseFailure {
+            text:
+                "Could not find response defined inline or as a #/components/responses/ reference"
+                    .to_string(),
+            r#type: TestcaseFailureType::MissingResponseDefinition,
+            json_pointer: None,
+            spec_pointer: Some(response_segment),
+            perspective: None,
+        });
+        return validated;
+    }
+    let response_segment = spec_segment(spec_response, &response_segment);
+    let spec_response = response.unwrap();
+    validated.links = spec_response
+        .links
+        .iter()
+        .filter_map(|(name, link)| {
+            resolve_link(link, spec).map(|link| (name.clone(), link.clone()))
+        })
+        .collect();
+    if let Some(failure) = validate_single_content_type(&headers, ValidationPerspective::Response) {
+        validated.failures.push(failure);
+    }
+    let response_content_type = headers.get("Content-Type");
+    if response_content_type.is_none() && !spec_response.content.is_empty() {
+        validated.failures.push(TestcaseFailure {
+            text: "Response did not include a Content-Type header".to_string(),
+            r#type: TestcaseFailureType::MissingContentTypeHeader,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return validated;
+    }
+    let response_content_type = match response_content_type.map(HeaderValue::to_str) {
+        Some(Ok(content_type)) => content_type,
+        Some(Err(_)) => {
+            validated.failures.push(TestcaseFailure {
+                text: "Response header content-type has a value that is not valid UTF-8"
+                    .to_string(),
+                r#type: TestcaseFailureType::MalformedHeader,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            ""
+        }
+        None => "",
+    };
+    validated.properties.push(TestcaseProperty {
+        name: "responseContentType".to_string(),
+        value: response_content_type.to_string(),
+    });
+    if streamed {
+        return validated;
+    }
+
+    let mut body_failures = validation_cache.get_or_validate(
+        cache::CacheKey::new(
+            format!("{} {} {}", method, wayfinder_path, status),
+            response_content_type,
+            &validated.body,
+        ),
+        || {
+            validate_body(
+                &validated.body,
+                response_content_type,
+                &spec_response.content,
+                spec,
+                ValidationPerspective::Response,
+                json_checks,
+            )
+        },
+    );
+    locate_in_spec(&mut body_failures, &format!("{}/content", response_segment));
+    validated.failures.extend(body_failures);
+
+    validated
+}
+
+/// Returns the media type in the content that matches the Content-Type header, along with its key
+/// in the content. Media ranges like
+/// `application/*` and `*/*` also match, and the most specific match wins. A media type with
+/// parameters, like `application/json; version=2`, wins when the header has the same parameters
+/// and is only used as a last resort for its media type when it doesn't.
+fn media_type_for<'a>(
+    content: &'a openapiv3::Content,
+    content_type: &str,
+) -> Option<(&'a str, &'a openapiv3::MediaType)> {
+    let essence = charset::essence(content_type);
+    let (r#type, _) = essence.split_once('/').unwrap_or((essence, ""));
+    content
+        .iter()
+        .filter_map(|(content_key, media_type)| {
+            let key = content_key.as_str();
+            let has_parameters = charset::parameters(key).next().is_some();
+            let parameters_match = charset::parameters(key).all(|(name, value)| {
+                charset::parameters(content_type).any(|(header_name, header_value)| {
+                    header_name.eq_ignore_ascii_case(name)
+                        && header_value.eq_ignore_ascii_case(value)
+                })
+            });
+            let key = charset::essence(key);
+            let essence_matches = key.eq_ignore_ascii_case(essence);
+            let specificity = if essence_matches && has_parameters && parameters_match {
+                4
+            } else if essence_matches && !has_parameters {
+                3
+            } else if essence_matches {
+                2
+            } else if key
+                .strip_suffix("/*")
+                .is_some_and(|key| key.eq_ignore_ascii_case(r#type))
+            {
+                1
+            } else if key == "*/*" {
+                0
+            } else {
+                return None;
+            };
+            Some((specificity, (content_key.as_str(), media_type)))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map(|(_, media_type)| media_type)
+}
+
+/// Validates a request or response body against the content defined for it in the spec.
+/// `content_type` is the value of the Content-Type header that was sent with the body. JSON bodies
+/// are also put through any of the optional `json_checks` that are enabled.
+fn validate_body(
+    body: &[u8],
+    content_type: &str,
+    content: &openapiv3::Content,
+    spec: &openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let mut failures = body_failures(body, content_type, content, spec, perspective, json_checks);
+    for failure in failures.iter_mut() {
+        failure.perspective = Some(perspective);
+    }
+    failures
+}
+
+fn body_failures(
+    body: &[u8],
+    content_type: &str,
+    content: &openapiv3::Content,
+    spec: &openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+
+    // No Content-Type header but body is not empty
+    if content_type.is_empty() && !body.is_empty() {
+        failures.push(TestcaseFailure {
+            text: format!("Receieved {} body when empty body is expected", perspective),
+            r#type: TestcaseFailureType::MismatchNonEmptyBody,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return failures;
+    }
+
+    // Body is empty, nothing to validate
+    if body.is_empty() {
+        return failures;
+    }
+
+    // Body is not empty but no matching Content-Type in spec
+    let spec_content = media_type_for(content, content_type);
+    if spec_content.is_none() {
+        failures.push(TestcaseFailure {
+            text: format!(
+                "Spec does not contain matching {} for Content-Type: {}",
+                perspective, content_type
+            ),
+            r#type: TestcaseFailureType::MismatchedContentTypeHeader,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return failures;
+    }
+
+    let (media_type_key, spec_content) = spec_content.unwrap();
+    let schema = spec_content.schema.as_ref();
+    if schema.is_none() {
+        failures.push(TestcaseFailure {
+            text: format!("Receieved {} body when empty body is expected", perspective),
+            r#type: TestcaseFailureType::MismatchNonEmptyBody,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+        return failures;
+    }
+    let schema_reference = schema.unwrap();
+    // Failures are located relative to the content
+    let schema_segment = format!("/{}/schema", json::escape(media_type_key));
+    let schema = resolve_schema(schema_reference, spec);
+    if schema.is_none() {
+        failures.push(TestcaseFailure {
+            text: "Could not find schema defined inline or as a #/components/schemas/ reference"
+                .to_string(),
+            r#type: TestcaseFailureType::MissingSchemaDefinition,
+            json_pointer: None,
+            spec_pointer: Some(schema_segment),
+            perspective: None,
+        });
+        return failures;
+    }
+    let spec_schema = schema.unwrap();
+    let schema_segment = spec_segment(schema_reference, &schema_segment);
+    if !charset::is_json(content_type) && !charset::is_ndjson(content_type) {
+        debug!(
+            "Skipping JSON schema validation for non-JSON {}",
+            perspective
+        );
+        return failures;
+    }
+    let body = match charset::decode(body, content_type) {
+        Ok(body) => body,
+        Err(err) => {
+            failures.push(TestcaseFailure {
+                text: format!("Failed to decode {} body: {}", perspective, err),
+                r#type: TestcaseFailureType::DecodingFailed,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            return failures;
+        }
+    };
+    let mut body_failures = if charset::is_ndjson(content_type) {
+        validate_ndjson(&body, spec_schema, spec, perspective, json_checks)
+    } else {
+        validate_json_document(&body, spec_schema, spec, perspective, json_checks, None)
+    };
+    locate_in_spec(&mut body_failures, schema_segment);
+    failures.extend(body_failures);
+
+    failures
+}
+
+/// Validates every line of a newline delimited JSON body as its own document. The schema of an
+/// NDJSON body can describe the whole stream as an array or a single line, and the items of an
+/// array schema are used for each line. Failures are located by their line number, starting at 1,
+/// like /3/name for the name of the third line.
+fn validate_ndjson(
+    body: &[u8],
+    spec_schema: &openapiv3::Schema,
+    spec: &openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let items_schema = match &spec_schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::Array(spec_array)) => {
+            spec_array.items.clone().map(ReferenceOr::unbox)
+        }
+        _ => None,
+    };
+    let line_schema = match &items_schema {
+        Some(items_schema) => match resolve_schema(items_schema, spec) {
+            Some(line_schema) => line_schema,
+            None => {
+                return vec![TestcaseFailure {
+                    text: "Could not find schema defined inline or as a #/components/schemas/ reference for array items".to_string(),
+                    r#type: TestcaseFailureType::MissingSchemaDefinition,
+                    json_pointer: None,
+                    spec_pointer: Some("/items".to_string()),
+                    perspective: None,
+                }]
+            }
+        },
+        None => spec_schema,
+    };
+    let line_segment = items_schema
+        .as_ref()
+        .map_or("", |items_schema| spec_segment(items_schema, "/items"));
+    let mut failures = body
+        .split(|byte| *byte == b'\n')
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim_ascii()))
+        .filter(|(_, line)| !line.is_empty())
+        .flat_map(|(line_number, line)| {
+            validate_json_document(
+                line,
+                line_schema,
+                spec,
+                perspective,
+                json_checks,
+                Some(line_number),
+            )
+        })
+        .collect::<Vec<TestcaseFailure>>();
+    locate_in_spec(&mut failures, line_segment);
+    failures
+}
+
+/// Validates a JSON document against its schema. `line` is the line of an NDJSON body the document
+/// was on, which the JSON pointers of the failures start with.
+fn validate_json_document(
+    body: &[u8],
+    spec_schema: &openapiv3::Schema,
+    spec: &openapiv3::OpenAPI,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+    line: Option<usize>,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    let prefix = line.map(|line| format!("/{}", line)).unwrap_or_default();
+    let serde_value = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_value) => serde_value,
+        Err(err) => {
+            let mut text = match line {
+                Some(line) => format!(
+                    "Failed to parse line {} of {} body as JSON",
+                    line, perspective
+                ),
+                None => format!("Failed to parse {} body as JSON", perspective),
+            };
+            // Show where parsing stopped. Bodies that aren't UTF-8 can't be shown as text.
+            if let Ok(body) = std::str::from_utf8(body) {
+                let mut offset = json::error_offset(body, &err).min(body.len());
+                while !body.is_char_boundary(offset) {
+                    offset -= 1;
+                }
+                text = format!(
+                    "{}: {}\n\n{}",
+                    text,
+                    err,
+                    json::excerpt(body, offset..offset)
+                );
+            }
+            failures.push(TestcaseFailure {
+                text,
+                r#type: TestcaseFailureType::FailedJSONDeserialization,
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+            return failures;
+        }
     };
+    if json_checks.duplicate_keys {
+        for json_pointer in json::duplicate_keys(body).unwrap_or_default() {
+            failures.push(TestcaseFailure {
+                text: format!("Received duplicate key at {}{}", prefix, json_pointer),
+                r#type: TestcaseFailureType::DuplicateJSONKey,
+                json_pointer: Some(format!("{}{}", prefix, json_pointer)),
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
+    }
+    failures.extend(validate_schema(
+        &serde_value,
+        spec_schema,
+        spec,
+        format!("{}/", prefix),
+        perspective,
+        json_checks,
+    ));
+    // Show where each failure is in the body. The body parsed as JSON so it is valid UTF-8.
+    let body = std::str::from_utf8(body).unwrap_or_default();
+    for failure in failures.iter_mut() {
+        let range = failure
+            .json_pointer
+            .as_deref()
+            .and_then(|json_pointer| json_pointer.strip_prefix(&prefix))
+            .and_then(|json_pointer| json::locate(body, json_pointer));
+        if let Some(range) = range {
+            failure.text = format!("{}\n\n{}", failure.text, json::excerpt(body, range));
+        }
+    }
+
+    failures
+}
+
+/// The largest integer that a JavaScript number can represent exactly, 2^53 - 1.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+fn exceeds_safe_integer(number: &serde_json::Number) -> bool {
+    if let Some(number) = number.as_i64() {
+        return number.unsigned_abs() > MAX_SAFE_INTEGER;
+    }
+    if let Some(number) = number.as_u64() {
+        return number > MAX_SAFE_INTEGER;
+    }
+    number
+        .as_f64()
+        .is_some_and(|number| number.abs() > MAX_SAFE_INTEGER as f64)
+}
+
+/// Returns true if the number has no fractional part. Numbers like 1.0 are integers too, since JSON
+/// doesn't distinguish them from 1.
+fn is_integer(number: &serde_json::Number) -> bool {
+    number.is_i64()
+        || number.is_u64()
+        || number.as_f64().is_some_and(|number| number.fract() == 0.0)
+}
+
+/// The minimum, maximum, multipleOf, and format of a number or integer schema.
+struct NumberConstraints {
+    minimum: Option<f64>,
+    exclusive_minimum: bool,
+    maximum: Option<f64>,
+    exclusive_maximum: bool,
+    multiple_of: Option<f64>,
+    width: Option<NumberWidth>,
+}
+
+/// The formats that limit a number to what fits in a type of a fixed size.
+#[derive(Clone, Copy)]
+enum NumberWidth {
+    Int32,
+    Int64,
+    Float,
+    Double,
+}
+
+impl NumberWidth {
+    fn fits(self, number: &serde_json::Number) -> bool {
+        let value = number.as_f64().unwrap_or_default();
+        match self {
+            NumberWidth::Int32 => match number.as_i64() {
+                Some(value) => i32::try_from(value).is_ok(),
+                None => value >= i32::MIN as f64 && value <= i32::MAX as f64,
+            },
+            // 2^63 is the first value past i64::MAX that a float can represent
+            NumberWidth::Int64 => {
+                number.is_i64()
+                    || (!number.is_u64() && value >= i64::MIN as f64 && value < 2f64.powi(63))
+            }
+            NumberWidth::Float => value.abs() <= f32::MAX as f64,
+            NumberWidth::Double => value.is_finite(),
+        }
+    }
+}
+
+impl std::fmt::Display for NumberWidth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NumberWidth::Int32 => write!(f, "int32"),
+            NumberWidth::Int64 => write!(f, "int64"),
+            NumberWidth::Float => write!(f, "float"),
+            NumberWidth::Double => write!(f, "double"),
+        }
+    }
+}
 
-    if wayfinder_path.is_none() {
-        return validated;
+impl From<&openapiv3::NumberType> for NumberConstraints {
+    fn from(number: &openapiv3::NumberType) -> Self {
+        NumberConstraints {
+            minimum: number.minimum,
+            exclusive_minimum: number.exclusive_minimum,
+            maximum: number.maximum,
+            exclusive_maximum: number.exclusive_maximum,
+            multiple_of: number.multiple_of,
+            width: match number.format {
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::NumberFormat::Float) => {
+                    Some(NumberWidth::Float)
+                }
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::NumberFormat::Double) => {
+                    Some(NumberWidth::Double)
+                }
+                _ => None,
+            },
+        }
     }
+}
 
-    let wayfinder_path = wayfinder_path.unwrap();
-    let path = spec.paths.paths.get(&wayfinder_path).unwrap().as_item();
-    if path.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text: "Invalid HTTP method".to_string(),
-            r#type: TestcaseFailureType::PathNotFound,
-        });
-        return validated;
+impl From<&openapiv3::IntegerType> for NumberConstraints {
+    fn from(integer: &openapiv3::IntegerType) -> Self {
+        NumberConstraints {
+            minimum: integer.minimum.map(|minimum| minimum as f64),
+            exclusive_minimum: integer.exclusive_minimum,
+            maximum: integer.maximum.map(|maximum| maximum as f64),
+            exclusive_maximum: integer.exclusive_maximum,
+            multiple_of: integer.multiple_of.map(|multiple_of| multiple_of as f64),
+            width: match integer.format {
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::IntegerFormat::Int32) => {
+                    Some(NumberWidth::Int32)
+                }
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::IntegerFormat::Int64) => {
+                    Some(NumberWidth::Int64)
+                }
+                _ => None,
+            },
+        }
     }
-    let path = path.unwrap();
-    let operation = match method {
-        axum::http::Method::DELETE => path.delete.as_ref(),
-        axum::http::Method::GET => path.get.as_ref(),
-        axum::http::Method::HEAD => path.head.as_ref(),
-        axum::http::Method::OPTIONS => path.options.as_ref(),
-        axum::http::Method::PATCH => path.patch.as_ref(),
-        axum::http::Method::POST => path.post.as_ref(),
-        axum::http::Method::PUT => path.put.as_ref(),
-        axum::http::Method::TRACE => path.trace.as_ref(),
-        _ => None,
+}
+
+fn validate_number_constraints(
+    number: &serde_json::Number,
+    constraints: &NumberConstraints,
+    json_pointer: &str,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    let value = match number.as_f64() {
+        Some(value) => value,
+        None => return failures,
     };
-    if operation.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text: "Invalid HTTP method".to_string(),
-            r#type: TestcaseFailureType::InvalidHTTPMethod,
-        });
-        return validated;
-    }
-    let operation = operation.unwrap();
-    if let Some(operation_id) = &operation.operation_id {
-        validated.properties.push(TestcaseProperty {
-            name: "operationId".to_string(),
-            value: operation_id.to_string(),
-        });
+    let out_of_range = |text: String| TestcaseFailure {
+        text,
+        r#type: TestcaseFailureType::FailedValidationOutOfRange,
+        json_pointer: Some(json_pointer.to_string()),
+        spec_pointer: None,
+        perspective: None,
+    };
+    if let Some(width) = constraints.width.filter(|width| !width.fits(number)) {
+        failures.push(out_of_range(format!(
+            "Received {} at {} that does not fit in the {} format",
+            number, json_pointer, width
+        )));
     }
-    let spec_response = operation
-        .responses
-        .responses
-        .get(&openapiv3::StatusCode::Code(status));
-    if spec_response.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text: "Response not found for status code".to_string(),
-            r#type: TestcaseFailureType::InvalidStatusCode,
-        });
-        return validated;
+    match constraints.minimum {
+        Some(minimum) if constraints.exclusive_minimum && value <= minimum => {
+            failures.push(out_of_range(format!(
+                "Received {} at {} that is not greater than the exclusive minimum {}",
+                number, json_pointer, minimum
+            )))
+        }
+        Some(minimum) if value < minimum => failures.push(out_of_range(format!(
+            "Received {} at {} that is less than the minimum {}",
+            number, json_pointer, minimum
+        ))),
+        _ => {}
     }
-    let spec_response = spec_response.unwrap();
-    let response = resolve_response(spec_response, spec);
-    if response.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text:
-                "Could not find response defined inline or as a #/components/responses/ reference"
-                    .to_string(),
-            r#type: TestcaseFailureType::MissingResponseDefinition,
-        });
-        return validated;
+    match constraints.maximum {
+        Some(maximum) if constraints.exclusive_maximum && value >= maximum => {
+            failures.push(out_of_range(format!(
+                "Received {} at {} that is not less than the exclusive maximum {}",
+                number, json_pointer, maximum
+            )))
+        }
+        Some(maximum) if value > maximum => failures.push(out_of_range(format!(
+            "Received {} at {} that is greater than the maximum {}",
+            number, json_pointer, maximum
+        ))),
+        _ => {}
     }
-    let spec_response = response.unwrap();
-    let response_content_type = headers.get("Content-Type");
-    if response_content_type.is_none() && !spec_response.content.is_empty() {
-        validated.failures.push(TestcaseFailure {
-            text: "Response did not include a Content-Type header".to_string(),
-            r#type: TestcaseFailureType::MissingContentTypeHeader,
-        });
-        return validated;
+    if let Some(multiple_of) = constraints
+        .multiple_of
+        .filter(|multiple_of| *multiple_of > 0.0)
+    {
+        let is_multiple = match (number.as_i64(), multiple_of.fract() == 0.0) {
+            // Integers are compared exactly so large values aren't affected by rounding
+            (Some(value), true) => value % (multiple_of as i64) == 0,
+            _ => {
+                // Decimal multiples like 0.01 can't be represented exactly, so a small error is
+                // tolerated
+                let quotient = value / multiple_of;
+                (quotient - quotient.round()).abs() < 1e-9
+            }
+        };
+        if !is_multiple {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "Received {} at {} that is not a multiple of {}",
+                    number, json_pointer, multiple_of
+                ),
+                r#type: TestcaseFailureType::FailedValidationNotMultipleOf,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
     }
-    let response_content_type = response_content_type
-        .map(|v| v.to_str().unwrap())
-        .unwrap_or("");
-    validated.properties.push(TestcaseProperty {
-        name: "responseContentType".to_string(),
-        value: response_content_type.to_string(),
-    });
+    failures
+}
 
-    // No Content-Type header but response body is not empty
-    if response_content_type.is_empty() && !validated.body.is_empty() {
-        validated.failures.push(TestcaseFailure {
-            text: "Receieved response body when empty body is expected".to_string(),
-            r#type: TestcaseFailureType::MismatchNonEmptyBody,
-        });
-        return validated;
+fn validate_array_constraints(
+    array: &[serde_json::Value],
+    spec_array: &openapiv3::ArrayType,
+    json_pointer: &str,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    let invalid_length = |text: String| TestcaseFailure {
+        text,
+        r#type: TestcaseFailureType::FailedValidationInvalidArrayLength,
+        json_pointer: Some(json_pointer.to_string()),
+        spec_pointer: None,
+        perspective: None,
+    };
+    match (spec_array.min_items, spec_array.max_items) {
+        (Some(min_items), _) if array.len() < min_items => failures.push(invalid_length(format!(
+            "Received array at {} with {} items, fewer than the minItems {}",
+            json_pointer,
+            array.len(),
+            min_items
+        ))),
+        (_, Some(max_items)) if array.len() > max_items => failures.push(invalid_length(format!(
+            "Received array at {} with {} items, more than the maxItems {}",
+            json_pointer,
+            array.len(),
+            max_items
+        ))),
+        _ => {}
+    }
+    if spec_array.unique_items {
+        // Object keys are sorted when serialized, so equal values serialize the same
+        let mut seen = BTreeMap::new();
+        for (index, item) in array.iter().enumerate() {
+            match seen.entry(item.to_string()) {
+                std::collections::btree_map::Entry::Occupied(first_index) => {
+                    let json_pointer = format!("{}{}/", json_pointer, index);
+                    failures.push(TestcaseFailure {
+                        text: format!(
+                            "Received item at {} that is the same as the item at index {}, but the array requires uniqueItems",
+                            json_pointer,
+                            first_index.get()
+                        ),
+                        r#type: TestcaseFailureType::FailedValidationDuplicateArrayItem,
+                        json_pointer: Some(json_pointer),
+                        spec_pointer: None,
+                        perspective: None,
+                    });
+                }
+                std::collections::btree_map::Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+            }
+        }
     }
+    failures
+}
 
-    // Body is empty, nothing to validate
-    if validated.body.is_empty() {
-        return validated;
+/// Checks strings with the date-time or date format against RFC 3339. In lenient mode deviations
+/// like a missing timezone are reported as LenientDateTime instead of a validation failure.
+fn validate_date_time(
+    value: &str,
+    format: &openapiv3::VariantOrUnknownOrEmpty<openapiv3::StringFormat>,
+    json_pointer: &str,
+    json_checks: &JsonChecks,
+) -> Option<TestcaseFailure> {
+    let (format, check) = match format {
+        openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::DateTime) => {
+            ("date-time", datetime::check_date_time(value))
+        }
+        openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Date) => {
+            ("date", datetime::check_date(value))
+        }
+        _ => return None,
+    };
+    match (check, json_checks.date_time) {
+        (datetime::DateTimeCheck::Valid, _) => None,
+        (datetime::DateTimeCheck::Deviation(deviation), datetime::DateTimeMode::Lenient) => {
+            Some(TestcaseFailure {
+                text: format!(
+                    "Received {} {} at {} that {}",
+                    format, value, json_pointer, deviation
+                ),
+                r#type: TestcaseFailureType::LenientDateTime,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
+            })
+        }
+        (datetime::DateTimeCheck::Deviation(deviation), datetime::DateTimeMode::Strict) => {
+            Some(TestcaseFailure {
+                text: format!(
+                    "Received invalid {} {} at {} that {}",
+                    format, value, json_pointer, deviation
+                ),
+                r#type: TestcaseFailureType::FailedValidationInvalidDateTime,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
+            })
+        }
+        (datetime::DateTimeCheck::Invalid, _) => Some(TestcaseFailure {
+            text: format!("Received invalid {} {} at {}", format, value, json_pointer),
+            r#type: TestcaseFailureType::FailedValidationInvalidDateTime,
+            json_pointer: Some(json_pointer.to_string()),
+            spec_pointer: None,
+            perspective: None,
+        }),
     }
+}
 
-    // Body is not empty but no matching Content-Type in spec
-    let spec_content = spec_response.content.get(response_content_type);
-    if spec_content.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text: format!(
-                "Spec does not contain matching response for Content-Type: {}",
-                response_content_type
-            ),
-            r#type: TestcaseFailureType::MismatchedContentTypeHeader,
-        });
-        return validated;
+/// The failures from validating a value against one branch of a oneOf or anyOf.
+struct BranchResult {
+    /// The $ref of the branch, or its position in the list for inline schemas.
+    name: String,
+    failures: Vec<TestcaseFailure>,
+}
+
+impl BranchResult {
+    /// Whether the value is valid for the branch. Failures that are only reported for tracking,
+    /// like LenientDateTime, don't stop a branch from matching.
+    fn matches(&self) -> bool {
+        self.failures.iter().all(|failure| {
+            matches!(
+                failure.r#type,
+                TestcaseFailureType::LenientDateTime | TestcaseFailureType::Int64PrecisionLoss
+            )
+        })
     }
+}
 
-    let spec_content = spec_content.unwrap();
-    let schema = spec_content.schema.as_ref();
-    if schema.is_none() {
-        if !validated.body.is_empty() {
-            validated.failures.push(TestcaseFailure {
-                text: "Receieved response body when empty body is expected".to_string(),
-                r#type: TestcaseFailureType::MismatchNonEmptyBody,
-            });
-        }
-        return validated;
+/// Validates the value against every branch of a oneOf or anyOf.
+fn validate_branches(
+    serde_value: &serde_json::Value,
+    branches: &[ReferenceOr<openapiv3::Schema>],
+    keyword: &str,
+    spec: &openapiv3::OpenAPI,
+    json_pointer: &str,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<BranchResult> {
+    branches
+        .iter()
+        .enumerate()
+        .map(|(index, branch)| {
+            let name = match branch {
+                ReferenceOr::Reference { reference } => reference.to_string(),
+                ReferenceOr::Item(_) => format!("{}[{}]", keyword, index),
+            };
+            let segment = format!("/{}/{}", keyword, index);
+            let failures = match resolve_schema(branch, spec) {
+                Some(schema) => {
+                    let mut failures = validate_schema(
+                        serde_value,
+                        schema,
+                        spec,
+                        json_pointer.to_string(),
+                        perspective,
+                        json_checks,
+                    );
+                    locate_in_spec(&mut failures, spec_segment(branch, &segment));
+                    failures
+                }
+                None => vec![TestcaseFailure {
+                    text: "Could not find schema defined inline or as a #/components/schemas/ reference".to_string(),
+                    r#type: TestcaseFailureType::MissingSchemaDefinition,
+                    json_pointer: Some(json_pointer.to_string()),
+                    spec_pointer: Some(segment),
+                    perspective: None,
+                }],
+            };
+            BranchResult { name, failures }
+        })
+        .collect()
+}
+
+/// Describes why none of the branches matched. The branch with the fewest failures is named as
+/// the closest match, followed by the failures of every branch.
+fn describe_branch_failures(
+    branches: &[BranchResult],
+    keyword: &str,
+    json_pointer: &str,
+) -> String {
+    let mut text = format!(
+        "Received value at {} that does not match any {} schema",
+        json_pointer, keyword
+    );
+    if let Some(closest) = branches.iter().min_by_key(|branch| branch.failures.len()) {
+        text.push_str(&format!(", the closest match was {}", closest.name));
     }
-    let schema = schema.unwrap();
-    let schema = resolve_schema(schema, spec);
-    if schema.is_none() {
-        validated.failures.push(TestcaseFailure {
-            text: "Could not find schema defined inline or as a #/components/schemas/ reference"
-                .to_string(),
-            r#type: TestcaseFailureType::MissingSchemaDefinition,
-        });
-        return validated;
+    for branch in branches {
+        text.push_str(&format!("\n  {}:", branch.name));
+        for failure in branch.failures.iter() {
+            text.push_str(&format!("\n    - {}: {}", failure.r#type, failure.text));
+        }
     }
-    let spec_schema = schema.unwrap();
-    if response_content_type != "application/json" {
-        debug!("Skipping JSON schema validation for non-JSON response");
-        return validated;
+    text
+}
+
+/// Validates a value that must match exactly one of the schemas in a oneOf.
+fn validate_one_of(
+    serde_value: &serde_json::Value,
+    one_of: &[ReferenceOr<openapiv3::Schema>],
+    spec: &openapiv3::OpenAPI,
+    json_pointer: String,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let branches = validate_branches(
+        serde_value,
+        one_of,
+        "oneOf",
+        spec,
+        &json_pointer,
+        perspective,
+        json_checks,
+    );
+    let matching = branches
+        .iter()
+        .filter(|branch| branch.matches())
+        .collect::<Vec<&BranchResult>>();
+    match matching.as_slice() {
+        [] => vec![TestcaseFailure {
+            text: describe_branch_failures(&branches, "oneOf", &json_pointer),
+            r#type: TestcaseFailureType::FailedValidationNoMatchingSchema,
+            json_pointer: Some(json_pointer.to_string()),
+            spec_pointer: None,
+            perspective: None,
+        }],
+        [branch] => branch.failures.clone(),
+        _ => vec![TestcaseFailure {
+            text: format!(
+                "Received value at {} that matches more than one oneOf schema: {}",
+                json_pointer,
+                matching
+                    .iter()
+                    .map(|branch| branch.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+            r#type: TestcaseFailureType::FailedValidationMultipleMatchingSchemas,
+            json_pointer: Some(json_pointer.to_string()),
+            spec_pointer: None,
+            perspective: None,
+        }],
     }
-    let serde_value = serde_json::from_slice::<serde_json::Value>(&validated.body);
-    if serde_value.is_err() {
-        validated.failures.push(TestcaseFailure {
-            text: "Failed to parse response body as JSON".to_string(),
-            r#type: TestcaseFailureType::FailedJSONDeserialization,
-        });
-        return validated;
+}
+
+/// Validates a value that must match at least one of the schemas in an anyOf.
+fn validate_any_of(
+    serde_value: &serde_json::Value,
+    any_of: &[ReferenceOr<openapiv3::Schema>],
+    spec: &openapiv3::OpenAPI,
+    json_pointer: String,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let branches = validate_branches(
+        serde_value,
+        any_of,
+        "anyOf",
+        spec,
+        &json_pointer,
+        perspective,
+        json_checks,
+    );
+    // Branches that match can still report failures for tracking, so the one with the fewest is used
+    let closest_match = branches
+        .iter()
+        .filter(|branch| branch.matches())
+        .min_by_key(|branch| branch.failures.len());
+    match closest_match {
+        Some(branch) => branch.failures.clone(),
+        None => vec![TestcaseFailure {
+            text: describe_branch_failures(&branches, "anyOf", &json_pointer),
+            r#type: TestcaseFailureType::FailedValidationNoMatchingSchema,
+            json_pointer: Some(json_pointer.to_string()),
+            spec_pointer: None,
+            perspective: None,
+        }],
     }
-    let serde_value = serde_value.unwrap();
-    let schema_validation_failures =
-        validate_schema(&serde_value, spec_schema, spec, "/".to_string());
-    validated.failures.extend(schema_validation_failures);
+}
 
-    validated
+/// Validates a value that must not match the schema of a not.
+fn validate_not(
+    serde_value: &serde_json::Value,
+    not: &ReferenceOr<openapiv3::Schema>,
+    spec: &openapiv3::OpenAPI,
+    json_pointer: String,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let schema = match resolve_schema(not, spec) {
+        Some(schema) => schema,
+        None => {
+            return vec![TestcaseFailure {
+                text:
+                    "Could not find schema defined inline or as a #/components/schemas/ reference"
+                        .to_string(),
+                r#type: TestcaseFailureType::MissingSchemaDefinition,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: Some("/not".to_string()),
+                perspective: None,
+            }]
+        }
+    };
+    let branch = BranchResult {
+        name: match not {
+            ReferenceOr::Reference { reference } => reference.to_string(),
+            ReferenceOr::Item(_) => "not".to_string(),
+        },
+        failures: validate_schema(
+            serde_value,
+            schema,
+            spec,
+            json_pointer.to_string(),
+            perspective,
+            json_checks,
+        ),
+    };
+    match branch.matches() {
+        true => vec![TestcaseFailure {
+            text: format!(
+                "Received value at {} that matches the {} schema, which it must not match",
+                json_pointer, branch.name
+            ),
+            r#type: TestcaseFailureType::FailedValidationMatchedNotSchema,
+            json_pointer: Some(json_pointer.to_string()),
+            spec_pointer: None,
+            perspective: None,
+        }],
+        false => vec![],
+    }
 }
 
 fn validate_schema(
@@ -611,8 +5436,43 @@ fn validate_schema(
     spec_schema: &openapiv3::Schema,
     spec: &openapiv3::OpenAPI,
     json_pointer: String,
+    perspective: ValidationPerspective,
+    json_checks: &JsonChecks,
 ) -> Vec<TestcaseFailure> {
     let mut failures = vec![];
+    if let openapiv3::SchemaKind::OneOf { one_of } = &spec_schema.schema_kind {
+        failures.extend(validate_one_of(
+            serde_value,
+            one_of,
+            spec,
+            json_pointer,
+            perspective,
+            json_checks,
+        ));
+        return failures;
+    }
+    if let openapiv3::SchemaKind::AnyOf { any_of } = &spec_schema.schema_kind {
+        failures.extend(validate_any_of(
+            serde_value,
+            any_of,
+            spec,
+            json_pointer,
+            perspective,
+            json_checks,
+        ));
+        return failures;
+    }
+    if let openapiv3::SchemaKind::Not { not } = &spec_schema.schema_kind {
+        failures.extend(validate_not(
+            serde_value,
+            not,
+            spec,
+            json_pointer,
+            perspective,
+            json_checks,
+        ));
+        return failures;
+    }
     match serde_value {
         serde_json::Value::Null => {
             if !spec_schema.schema_data.nullable {
@@ -622,6 +5482,9 @@ fn validate_schema(
                         json_pointer
                     ),
                     r#type: TestcaseFailureType::FailedValidationUnexpectedNull,
+                    json_pointer: Some(json_pointer.to_string()),
+                    spec_pointer: None,
+                    perspective: None,
                 });
             }
             failures
@@ -635,36 +5498,106 @@ fn validate_schema(
             failures.push(TestcaseFailure {
                 text: format!("Received unexpected boolean at {}", json_pointer),
                 r#type: TestcaseFailureType::FailedValidationUnexpectedBoolean,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
             });
             failures
         }
-        serde_json::Value::Number(_) => {
-            // TODO: This probably needs to do a more thorough check for integer vs number
-            if let openapiv3::SchemaKind::Type(openapiv3::Type::Number(_)) =
+        serde_json::Value::Number(number) => {
+            if let openapiv3::SchemaKind::Type(openapiv3::Type::Number(spec_number)) =
                 &spec_schema.schema_kind
             {
+                failures.extend(validate_number_constraints(
+                    number,
+                    &NumberConstraints::from(spec_number),
+                    &json_pointer,
+                ));
                 return failures;
             }
-            if let openapiv3::SchemaKind::Type(openapiv3::Type::Integer(_)) =
+            if let openapiv3::SchemaKind::Type(openapiv3::Type::Integer(integer)) =
                 &spec_schema.schema_kind
             {
+                if !is_integer(number) {
+                    failures.push(TestcaseFailure {
+                        text: format!(
+                            "Received fractional number {} at {} when an integer is expected",
+                            number, json_pointer
+                        ),
+                        r#type: TestcaseFailureType::FailedValidationUnexpectedNumber,
+                        json_pointer: Some(json_pointer.to_string()),
+                        spec_pointer: None,
+                        perspective: None,
+                    });
+                    return failures;
+                }
+                failures.extend(validate_number_constraints(
+                    number,
+                    &NumberConstraints::from(integer),
+                    &json_pointer,
+                ));
+                let is_int64 = integer.format
+                    == openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::IntegerFormat::Int64);
+                if json_checks.int64_precision && is_int64 && exceeds_safe_integer(number) {
+                    failures.push(TestcaseFailure {
+                        text: format!(
+                            "Received int64 value {} at {} that JavaScript consumers can't represent exactly, consider sending it as a string",
+                            number, json_pointer
+                        ),
+                        r#type: TestcaseFailureType::Int64PrecisionLoss,
+                        json_pointer: Some(json_pointer.to_string()),
+                        spec_pointer: None,
+                        perspective: None,
+                    });
+                }
                 return failures;
             }
             failures.push(TestcaseFailure {
                 text: format!("Received unexpected number at {}", json_pointer),
                 r#type: TestcaseFailureType::FailedValidationUnexpectedNumber,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
             });
             failures
         }
-        serde_json::Value::String(_) => {
-            if let openapiv3::SchemaKind::Type(openapiv3::Type::String(_)) =
+        serde_json::Value::String(value) => {
+            if let openapiv3::SchemaKind::Type(openapiv3::Type::String(string)) =
                 &spec_schema.schema_kind
             {
+                if let Some(failure) =
+                    validate_date_time(value, &string.format, &json_pointer, json_checks)
+                {
+                    failures.push(failure);
+                }
+                if let openapiv3::VariantOrUnknownOrEmpty::Unknown(format) = &string.format {
+                    // Formats registered in the config file replace the standard checkers
+                    let is_valid = match json_checks.formats.get(format) {
+                        Some(validator) => validator.is_valid(value),
+                        None => formats::BuiltinFormat::standard(format)
+                            .is_none_or(|builtin| builtin.is_valid(value)),
+                    };
+                    if !is_valid {
+                        failures.push(TestcaseFailure {
+                            text: format!(
+                                "Received {} at {} that does not match format {}",
+                                value, json_pointer, format
+                            ),
+                            r#type: TestcaseFailureType::FailedValidationInvalidFormat,
+                            json_pointer: Some(json_pointer.to_string()),
+                            spec_pointer: None,
+                            perspective: None,
+                        });
+                    }
+                }
                 return failures;
             }
             failures.push(TestcaseFailure {
                 text: format!("Received unexpected string at {}", json_pointer),
                 r#type: TestcaseFailureType::FailedValidationUnexpectedString,
+                json_pointer: Some(json_pointer.to_string()),
+                spec_pointer: None,
+                perspective: None,
             });
             failures
         }
@@ -672,29 +5605,49 @@ fn validate_schema(
             if let openapiv3::SchemaKind::Type(openapiv3::Type::Array(spec_array)) =
                 &spec_schema.schema_kind
             {
+                failures.extend(validate_array_constraints(
+                    serde_array,
+                    spec_array,
+                    &json_pointer,
+                ));
                 let items_schema = spec_array.items.as_ref();
                 if items_schema.is_none() {
                     failures.push(TestcaseFailure {
                         text: "Array schema does not contain items schema".to_string(),
                         r#type: TestcaseFailureType::MissingSchemaDefinition,
+                        json_pointer: Some(json_pointer.to_string()),
+                        spec_pointer: Some(String::new()),
+                        perspective: None,
                     });
                     return failures;
                 }
-                let items_schema = items_schema.unwrap();
-                let items_schema = items_schema.clone().unbox();
-                let items_schema = resolve_schema(&items_schema, spec);
+                let items_reference = items_schema.unwrap().clone().unbox();
+                let items_schema = resolve_schema(&items_reference, spec);
                 if items_schema.is_none() {
                     failures.push(TestcaseFailure {
                         text: "Could not find schema defined inline or as a #/components/schemas/ reference for array items".to_string(),
                         r#type: TestcaseFailureType::MissingSchemaDefinition,
+                        json_pointer: Some(json_pointer.to_string()),
+                        spec_pointer: Some("/items".to_string()),
+                        perspective: None,
                     });
                     return failures;
                 }
                 let items_schema = items_schema.unwrap();
                 for (index, value) in serde_array.iter().enumerate() {
                     let json_pointer = format!("{}{}/", json_pointer, index);
-                    let schema_validation_failures =
-                        validate_schema(value, items_schema, spec, json_pointer);
+                    let mut schema_validation_failures = validate_schema(
+                        value,
+                        items_schema,
+                        spec,
+                        json_pointer,
+                        perspective,
+                        json_checks,
+                    );
+                    locate_in_spec(
+                        &mut schema_validation_failures,
+                        spec_segment(&items_reference, "/items"),
+                    );
                     failures.extend(schema_validation_failures);
                 }
             }
@@ -703,8 +5656,31 @@ fn validate_schema(
         serde_json::Value::Object(serde_object) => {
             match &spec_schema.schema_kind {
                 openapiv3::SchemaKind::Type(openapiv3::Type::Object(spec_object)) => {
+                    for key in spec_object.required.iter() {
+                        // Required properties that must not be sent in this perspective are only
+                        // required in the other one
+                        let forbidden = spec_object.properties.get(key).is_some_and(|property| {
+                            resolve_schema(&property.clone().unbox(), spec)
+                                .is_some_and(|property| perspective.forbids(&property.schema_data))
+                        });
+                        if !serde_object.contains_key(key) && !forbidden {
+                            failures.push(TestcaseFailure {
+                                text: format!(
+                                    "Missing required property at {}{}",
+                                    json_pointer,
+                                    json::escape(key)
+                                ),
+                                r#type:
+                                    TestcaseFailureType::FailedValidationMissingRequiredProperty,
+                                // The property doesn't exist, so the object is highlighted instead
+                                json_pointer: Some(json_pointer.to_string()),
+                                spec_pointer: None,
+                                perspective: None,
+                            });
+                        }
+                    }
                     for (key, value) in serde_object.iter() {
-                        let json_pointer = format!("{}{}", json_pointer, key);
+                        let json_pointer = format!("{}{}", json_pointer, json::escape(key));
                         let spec_property = spec_object.properties.get(key);
                         if spec_property.is_none() {
                             failures.push(TestcaseFailure {
@@ -713,33 +5689,85 @@ fn validate_schema(
                                     json_pointer, value
                                 ),
                                 r#type: TestcaseFailureType::FailedValidationUnexpectedProperty,
+                                json_pointer: Some(json_pointer.to_string()),
+                                spec_pointer: None,
+                                perspective: None,
                             });
                             continue;
                         }
-                        let spec_property = spec_property.unwrap();
-                        let spec_property = spec_property.clone().unbox();
-                        let spec_property = resolve_schema(&spec_property, spec);
+                        let property_reference = spec_property.unwrap().clone().unbox();
+                        let property_segment = format!("/properties/{}", json::escape(key));
+                        let spec_property = resolve_schema(&property_reference, spec);
                         if spec_property.is_none() {
                             failures.push(TestcaseFailure {
                                 text: format!("Could not find schema defined inline or as a #/components/schemas/ reference for property at {}", json_pointer),
                                 r#type: TestcaseFailureType::MissingSchemaDefinition,
+                                json_pointer: Some(json_pointer.to_string()),
+                                spec_pointer: Some(property_segment),
+                                perspective: None,
                             });
                             continue;
                         }
                         let spec_property = spec_property.unwrap();
-                        let schema_validation_failures = validate_schema(
+                        if perspective.forbids(&spec_property.schema_data) {
+                            let (attribute, r#type) = match perspective {
+                                ValidationPerspective::Request => (
+                                    "readOnly",
+                                    TestcaseFailureType::FailedValidationReadOnlyProperty,
+                                ),
+                                ValidationPerspective::Response => (
+                                    "writeOnly",
+                                    TestcaseFailureType::FailedValidationWriteOnlyProperty,
+                                ),
+                            };
+                            failures.push(TestcaseFailure {
+                                text: format!(
+                                    "Received {} property at {} in a {} body",
+                                    attribute, json_pointer, perspective
+                                ),
+                                r#type,
+                                json_pointer: Some(json_pointer.to_string()),
+                                spec_pointer: None,
+                                perspective: None,
+                            });
+                            continue;
+                        }
+                        let mut schema_validation_failures = validate_schema(
                             value,
                             spec_property,
                             spec,
                             format!("{}/", json_pointer),
+                            perspective,
+                            json_checks,
+                        );
+                        locate_in_spec(
+                            &mut schema_validation_failures,
+                            spec_segment(&property_reference, &property_segment),
                         );
                         failures.extend(schema_validation_failures);
                     }
                 }
                 openapiv3::SchemaKind::AllOf { all_of } => {
                     let schema = create_schema_for_all_of(all_of, spec);
-                    let schema_validation_failures =
-                        validate_schema(serde_value, &schema, spec, json_pointer);
+                    let mut schema_validation_failures = validate_schema(
+                        serde_value,
+                        &schema,
+                        spec,
+                        json_pointer,
+                        perspective,
+                        json_checks,
+                    );
+                    // The merged schema isn't in the spec, so failures in its inline parts point
+                    // at the allOf
+                    for failure in schema_validation_failures.iter_mut() {
+                        if failure
+                            .spec_pointer
+                            .as_ref()
+                            .is_some_and(|spec_pointer| !spec_pointer.starts_with('#'))
+                        {
+                            failure.spec_pointer = Some(String::new());
+                        }
+                    }
                     failures.extend(schema_validation_failures);
                 }
                 _ => {
@@ -749,6 +5777,9 @@ fn validate_schema(
                             spec_schema.schema_kind, json_pointer
                         ),
                         r#type: TestcaseFailureType::FailedValidationUnsupportedSchemaKind,
+                        json_pointer: Some(json_pointer.to_string()),
+                        spec_pointer: Some(String::new()),
+                        perspective: None,
                     });
                 }
             }
@@ -767,6 +5798,7 @@ fn create_schema_for_all_of(
         .collect::<Vec<&openapiv3::Schema>>();
 
     let mut property_map = serde_json::Map::new();
+    let mut required = BTreeSet::new();
     for schema in schemas.iter() {
         match &schema.schema_kind {
             openapiv3::SchemaKind::Type(openapiv3::Type::Object(spec_object)) => {
@@ -774,6 +5806,7 @@ fn create_schema_for_all_of(
                     let json_value = serde_json::to_value(value).unwrap();
                     property_map.insert(key.clone(), serde_json::from_value(json_value).unwrap());
                 }
+                required.extend(spec_object.required.iter().cloned());
             }
 
             _ => {
@@ -789,15 +5822,32 @@ fn create_schema_for_all_of(
         "properties".to_string(),
         serde_json::Value::Object(property_map),
     );
-    // TODO: gotta populate required fields as well
+    serde_map.insert("required".to_string(), required.into_iter().collect());
 
     serde_json::from_value(serde_json::Value::Object(serde_map)).unwrap()
 }
 
+/// Completes when the proxy is asked to stop with Ctrl+C, or with SIGTERM, which is what CI
+/// runners and container orchestrators send before they kill a process.
 async fn shutdown_signal() {
-    signal::ctrl_c()
-        .await
-        .expect("failed to install Ctrl+C handler");
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
     info!("Shutting down...")
 }
 
@@ -820,6 +5870,104 @@ fn resolve_response<'a>(
     }
 }
 
+fn resolve_parameter<'a>(
+    parameter: &'a openapiv3::ReferenceOr<openapiv3::Parameter>,
+    openapi: &'a openapiv3::OpenAPI,
+) -> Option<&'a openapiv3::Parameter> {
+    match parameter {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::Reference { reference } => {
+            let parameter_name = reference.split("#/components/parameters/").nth(1)?;
+            let components = openapi.components.as_ref()?;
+            components.parameters.get(parameter_name)?.as_item()
+        }
+    }
+}
+
+fn resolve_link<'a>(
+    link: &'a openapiv3::ReferenceOr<openapiv3::Link>,
+    openapi: &'a openapiv3::OpenAPI,
+) -> Option<&'a openapiv3::Link> {
+    match link {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::Reference { reference } => {
+            let link_name = reference.split("#/components/links/").nth(1)?;
+            let components = openapi.components.as_ref()?;
+            components.links.get(link_name)?.as_item()
+        }
+    }
+}
+
+fn resolve_request_body<'a>(
+    request_body: &'a openapiv3::ReferenceOr<openapiv3::RequestBody>,
+    openapi: &'a openapiv3::OpenAPI,
+) -> Option<&'a openapiv3::RequestBody> {
+    match request_body {
+        ReferenceOr::Item(item) => Some(item),
+        ReferenceOr::Reference { reference } => {
+            let request_body_name = reference.split("#/components/requestBodies/").nth(1)?;
+            let components = openapi.components.as_ref()?;
+            components.request_bodies.get(request_body_name)?.as_item()
+        }
+    }
+}
+
+/// Makes the spec pointers of failures relative to the part of the spec that contains the
+/// definition they were found in, by prefixing them with the `segment` that leads to it. Pointers
+/// that already start at the root of the document, like the ones into a referenced component,
+/// are left as they are.
+fn locate_in_spec(failures: &mut [TestcaseFailure], segment: &str) {
+    for spec_pointer in failures
+        .iter_mut()
+        .filter_map(|failure| failure.spec_pointer.as_mut())
+    {
+        if !spec_pointer.starts_with('#') {
+            spec_pointer.insert_str(0, segment);
+        }
+    }
+}
+
+/// Shows where in the spec the definitions that caused failures are, so a spec that is missing a
+/// schema or uses one the proxy can't validate against can be fixed without searching for it.
+fn show_spec_locations(failures: &mut [TestcaseFailure], spec: &LoadedSpec) {
+    for failure in failures.iter_mut() {
+        let (r#type, text) = match failure.r#type {
+            TestcaseFailureType::MissingSchemaDefinition => (
+                validate::ProblemType::MissingSchema,
+                "The spec doesn't define a schema here",
+            ),
+            TestcaseFailureType::MissingResponseDefinition => (
+                validate::ProblemType::UnresolvableReference,
+                "The spec doesn't define the response here",
+            ),
+            TestcaseFailureType::FailedValidationUnsupportedSchemaKind => (
+                validate::ProblemType::UnsupportedConstruct,
+                "The proxy can't validate values against this schema",
+            ),
+            _ => continue,
+        };
+        let excerpt = failure
+            .spec_pointer
+            .as_deref()
+            .and_then(|spec_pointer| spec_pointer.strip_prefix('#'))
+            .and_then(|pointer| {
+                validate::spec_excerpt(&spec.name, &spec.content, pointer, r#type, text)
+            });
+        if let Some(excerpt) = excerpt {
+            failure.text = format!("{}\n\n{}", failure.text, excerpt.trim_end());
+        }
+    }
+}
+
+/// The segment that leads to a part of the spec: the reference of a $ref, which starts at the
+/// root of the document, or `segment` for a part that is defined inline.
+fn spec_segment<'a, T>(part: &'a ReferenceOr<T>, segment: &'a str) -> &'a str {
+    match part {
+        ReferenceOr::Reference { reference } => reference,
+        ReferenceOr::Item(_) => segment,
+    }
+}
+
 fn resolve_schema<'a>(
     schema: &'a openapiv3::ReferenceOr<openapiv3::Schema>,
     openapi: &'a openapiv3::OpenAPI,
@@ -0,0 +1,357 @@
+use std::collections::BTreeMap;
+
+use openapiv3::{
+    Parameter, ParameterSchemaOrContent, PathStyle, QueryStyle, ReferenceOr, SchemaKind, Type,
+};
+use serde_json::{Map, Value};
+
+use crate::{JsonChecks, TestcaseFailure, TestcaseFailureType};
+
+/// The values a request was sent with, before they are decoded.
+pub struct RequestParameters<'a> {
+    /// The decoded name and value of every query pair, in the order they were sent.
+    pub query: &'a [(String, String)],
+    pub headers: &'a axum::http::HeaderMap,
+    /// The values of the path parameters, keyed by the name in the path template.
+    pub path: &'a BTreeMap<String, String>,
+}
+
+/// Validates the query, header, and path parameters of a request against the parameters the
+/// operation and its path declare. Values are decoded according to the style and explode of each
+/// parameter first, so arrays and objects are validated as arrays and objects instead of strings.
+pub fn validate_parameters(
+    path_item: &openapiv3::PathItem,
+    operation: &openapiv3::Operation,
+    request: &RequestParameters,
+    spec: &openapiv3::OpenAPI,
+    json_checks: &JsonChecks,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    for parameter in declared_parameters(path_item, operation, spec) {
+        let parameter_data = parameter.parameter_data_ref();
+        // Parameters described by content instead of a schema are sent as documents, like JSON,
+        // which aren't decoded
+        let schema = match &parameter_data.format {
+            ParameterSchemaOrContent::Schema(schema) => crate::resolve_schema(schema, spec),
+            ParameterSchemaOrContent::Content(_) => continue,
+        };
+        let shape = shape(schema, spec);
+        let explode = explode(parameter);
+        let (location, value) = match parameter {
+            Parameter::Query { style, .. } => (
+                "query",
+                decode_query(&parameter_data.name, style, explode, &shape, request.query),
+            ),
+            Parameter::Header { .. } => (
+                "header",
+                request
+                    .headers
+                    .get(parameter_data.name.as_str())
+                    .and_then(|value| value.to_str().ok())
+                    .map(|raw| decode_delimited(raw, ",", explode, &shape)),
+            ),
+            Parameter::Path { style, .. } => (
+                "path",
+                request
+                    .path
+                    .get(&parameter_data.name)
+                    .and_then(|raw| decode_path(&parameter_data.name, raw, style, explode, &shape)),
+            ),
+            Parameter::Cookie { .. } => continue,
+        };
+        let value = match (value, schema) {
+            (Some(value), Some(schema)) => (value, schema),
+            (None, _) if parameter_data.required => {
+                failures.push(match parameter {
+                    Parameter::Header { .. } => TestcaseFailure {
+                        text: format!(
+                            "Request did not include required header {}",
+                            parameter_data.name
+                        ),
+                        r#type: TestcaseFailureType::MissingRequiredHeader,
+                        json_pointer: None,
+                        spec_pointer: None,
+                        perspective: None,
+                    },
+                    _ => TestcaseFailure {
+                        text: format!(
+                            "Request did not include required {} parameter {}",
+                            location, parameter_data.name
+                        ),
+                        r#type: TestcaseFailureType::InvalidParameter,
+                        json_pointer: None,
+                        spec_pointer: None,
+                        perspective: None,
+                    },
+                });
+                continue;
+            }
+            _ => continue,
+        };
+        let (value, schema) = value;
+        let schema_failures = crate::validate_schema(
+            &value,
+            schema,
+            spec,
+            "/".to_string(),
+            crate::ValidationPerspective::Request,
+            json_checks,
+        );
+        for failure in schema_failures {
+            failures.push(TestcaseFailure {
+                text: format!(
+                    "The {} parameter {} with value {} is invalid: {}",
+                    location, parameter_data.name, value, failure.text
+                ),
+                // Failures that are only reported for tracking keep their type
+                r#type: match failure.r#type {
+                    TestcaseFailureType::LenientDateTime
+                    | TestcaseFailureType::Int64PrecisionLoss => failure.r#type,
+                    _ => TestcaseFailureType::InvalidParameter,
+                },
+                // Pointers locate values in the body, which parameters aren't part of
+                json_pointer: None,
+                spec_pointer: None,
+                perspective: None,
+            });
+        }
+    }
+    failures
+}
+
+/// The parameters of the operation, including the ones shared by every operation of the path
+/// unless the operation overrides them.
+fn declared_parameters<'a>(
+    path_item: &'a openapiv3::PathItem,
+    operation: &'a openapiv3::Operation,
+    spec: &'a openapiv3::OpenAPI,
+) -> Vec<&'a Parameter> {
+    let operation_parameters = operation
+        .parameters
+        .iter()
+        .filter_map(|parameter| crate::resolve_parameter(parameter, spec))
+        .collect::<Vec<&Parameter>>();
+    let path_parameters = path_item
+        .parameters
+        .iter()
+        .filter_map(|parameter| crate::resolve_parameter(parameter, spec))
+        .filter(|parameter| {
+            !operation_parameters.iter().any(|overriding| {
+                overriding.parameter_data_ref().name == parameter.parameter_data_ref().name
+                    && std::mem::discriminant(*overriding) == std::mem::discriminant(*parameter)
+            })
+        })
+        .collect::<Vec<&Parameter>>();
+    path_parameters
+        .into_iter()
+        .chain(operation_parameters)
+        .collect()
+}
+
+/// Whether array and object values are sent as separate values. Form style explodes by default
+/// and every other style doesn't.
+fn explode(parameter: &Parameter) -> bool {
+    let default = matches!(
+        parameter,
+        Parameter::Query {
+            style: QueryStyle::Form,
+            ..
+        } | Parameter::Cookie { .. }
+    );
+    parameter.parameter_data_ref().explode.unwrap_or(default)
+}
+
+/// The shape of the value a schema describes, which decides how a parameter is split up. The
+/// schemas of primitives are kept so the values can be converted to their types.
+enum Shape<'a> {
+    Primitive(Option<&'a openapiv3::Schema>),
+    Array(Option<&'a openapiv3::Schema>),
+    Object(BTreeMap<&'a str, Option<&'a openapiv3::Schema>>),
+}
+
+fn shape<'a>(schema: Option<&'a openapiv3::Schema>, spec: &'a openapiv3::OpenAPI) -> Shape<'a> {
+    match schema.map(|schema| &schema.schema_kind) {
+        Some(SchemaKind::Type(Type::Array(array))) => Shape::Array(
+            array
+                .items
+                .as_ref()
+                .and_then(|items| resolve_boxed_schema(items, spec)),
+        ),
+        Some(SchemaKind::Type(Type::Object(object))) => Shape::Object(
+            object
+                .properties
+                .iter()
+                .map(|(name, schema)| (name.as_str(), resolve_boxed_schema(schema, spec)))
+                .collect(),
+        ),
+        _ => Shape::Primitive(schema),
+    }
+}
+
+fn resolve_boxed_schema<'a>(
+    schema: &'a ReferenceOr<Box<openapiv3::Schema>>,
+    spec: &'a openapiv3::OpenAPI,
+) -> Option<&'a openapiv3::Schema> {
+    match schema {
+        ReferenceOr::Item(schema) => Some(schema),
+        ReferenceOr::Reference { reference } => {
+            let name = reference.split("#/components/schemas/").nth(1)?;
+            spec.components.as_ref()?.schemas.get(name)?.as_item()
+        }
+    }
+}
+
+/// Converts a raw value to the type its schema expects. Values that don't look like that type are
+/// kept as strings so validation reports them.
+fn primitive(raw: &str, schema: Option<&openapiv3::Schema>) -> Value {
+    match schema.map(|schema| &schema.schema_kind) {
+        Some(SchemaKind::Type(Type::Integer(_) | Type::Number(_))) => {
+            serde_json::from_str::<serde_json::Number>(raw)
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::String(raw.to_string()))
+        }
+        Some(SchemaKind::Type(Type::Boolean(_))) => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        },
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+fn array<'a>(values: impl Iterator<Item = &'a str>, items: Option<&openapiv3::Schema>) -> Value {
+    Value::Array(values.map(|value| primitive(value, items)).collect())
+}
+
+fn object<'a>(
+    pairs: impl Iterator<Item = (&'a str, &'a str)>,
+    properties: &BTreeMap<&str, Option<&openapiv3::Schema>>,
+) -> Value {
+    Value::Object(
+        pairs
+            .map(|(key, value)| {
+                let schema = properties.get(key).copied().flatten();
+                (key.to_string(), primitive(value, schema))
+            })
+            .collect::<Map<String, Value>>(),
+    )
+}
+
+/// Pairs up alternating keys and values, like `role,admin,name,alex`.
+fn alternating<'a>(values: &[&'a str]) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+    values
+        .chunks(2)
+        .map(|pair| (pair[0], pair.get(1).copied().unwrap_or_default()))
+        .collect::<Vec<(&str, &str)>>()
+        .into_iter()
+}
+
+/// Decodes a value whose items are separated by a delimiter, like a header or a simple path
+/// parameter. Exploded objects separate each key from its value with `=` and unexploded ones
+/// alternate keys and values.
+fn decode_delimited(raw: &str, delimiter: &str, explode: bool, shape: &Shape) -> Value {
+    match shape {
+        Shape::Primitive(schema) => primitive(raw, *schema),
+        Shape::Array(items) => array(raw.split(delimiter).map(str::trim), *items),
+        Shape::Object(properties) if explode => object(
+            raw.split(delimiter)
+                .map(|pair| pair.trim().split_once('=').unwrap_or((pair.trim(), ""))),
+            properties,
+        ),
+        Shape::Object(properties) => object(
+            alternating(&raw.split(delimiter).map(str::trim).collect::<Vec<&str>>()),
+            properties,
+        ),
+    }
+}
+
+fn decode_query(
+    name: &str,
+    style: &QueryStyle,
+    explode: bool,
+    shape: &Shape,
+    query: &[(String, String)],
+) -> Option<Value> {
+    let values = query
+        .iter()
+        .filter(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+        .collect::<Vec<&str>>();
+    let delimiter = match style {
+        QueryStyle::SpaceDelimited => " ",
+        QueryStyle::PipeDelimited => "|",
+        QueryStyle::Form | QueryStyle::DeepObject => ",",
+    };
+    match shape {
+        // Every property of a deepObject is its own pair, like filter[role]=admin
+        Shape::Object(properties) if *style == QueryStyle::DeepObject => {
+            let pairs = query
+                .iter()
+                .filter_map(|(key, value)| {
+                    let property = key
+                        .strip_prefix(name)?
+                        .strip_prefix('[')?
+                        .strip_suffix(']')?;
+                    Some((property, value.as_str()))
+                })
+                .collect::<Vec<(&str, &str)>>();
+            (!pairs.is_empty()).then(|| object(pairs.into_iter(), properties))
+        }
+        // Every property of an exploded form object is its own pair, like role=admin
+        Shape::Object(properties) if explode => {
+            let pairs = query
+                .iter()
+                .filter(|(key, _)| properties.contains_key(key.as_str()))
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect::<Vec<(&str, &str)>>();
+            (!pairs.is_empty()).then(|| object(pairs.into_iter(), properties))
+        }
+        // Every item of an exploded array is its own pair, like id=1&id=2
+        Shape::Array(items) if explode => {
+            (!values.is_empty()).then(|| array(values.into_iter(), *items))
+        }
+        _ => values
+            .first()
+            .map(|raw| decode_delimited(raw, delimiter, false, shape)),
+    }
+}
+
+fn decode_path(
+    name: &str,
+    raw: &str,
+    style: &PathStyle,
+    explode: bool,
+    shape: &Shape,
+) -> Option<Value> {
+    match style {
+        PathStyle::Simple => Some(decode_delimited(raw, ",", explode, shape)),
+        // Label values start with a period, like .1,2 or .1.2 when exploded
+        PathStyle::Label => {
+            let raw = raw.strip_prefix('.')?;
+            let delimiter = if explode { "." } else { "," };
+            Some(decode_delimited(raw, delimiter, explode, shape))
+        }
+        // Matrix values are named, like ;id=1,2 or ;id=1;id=2 when exploded
+        PathStyle::Matrix => {
+            let pairs = raw
+                .strip_prefix(';')?
+                .split(';')
+                .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+                .collect::<Vec<(&str, &str)>>();
+            match shape {
+                Shape::Array(items) if explode => Some(array(
+                    pairs
+                        .iter()
+                        .filter(|(key, _)| *key == name)
+                        .map(|(_, value)| *value),
+                    *items,
+                )),
+                Shape::Object(properties) if explode => Some(object(pairs.into_iter(), properties)),
+                _ => pairs
+                    .iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| decode_delimited(value, ",", false, shape)),
+            }
+        }
+    }
+}
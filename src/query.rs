@@ -0,0 +1,100 @@
+use serde::Deserialize;
+
+use crate::Testcase;
+
+/// Query parameters accepted by /_ovp/testcases. A testcase is listed when it matches every
+/// parameter that is given.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TestcaseQuery {
+    /// Only testcases with a failure of this type, like FailedValidation.UnexpectedString.
+    failure_type: Option<String>,
+    operation_id: Option<String>,
+    /// Only testcases whose correlation ID starts with this.
+    correlation_id_prefix: Option<String>,
+    /// Only testcases with at least one failure.
+    failures_only: bool,
+    /// Only testcases recorded at or after this RFC 3339 date-time.
+    since: Option<String>,
+    /// Only testcases recorded before this RFC 3339 date-time.
+    until: Option<String>,
+}
+
+/// A TestcaseQuery whose date-times have been read.
+pub struct TestcaseFilter<'a> {
+    query: &'a TestcaseQuery,
+    since: Option<std::time::SystemTime>,
+    until: Option<std::time::SystemTime>,
+}
+
+impl TestcaseQuery {
+    /// Reads the date-times of the query, failing with a description of the first one that isn't
+    /// an RFC 3339 date-time.
+    pub fn filter(&self) -> Result<TestcaseFilter<'_>, String> {
+        let parse = |name: &str, value: &Option<String>| match value {
+            Some(value) => crate::datetime::parse_date_time(value)
+                .map(Some)
+                .ok_or_else(|| format!("{} is not an RFC 3339 date-time: {}", name, value)),
+            None => Ok(None),
+        };
+        Ok(TestcaseFilter {
+            query: self,
+            since: parse("since", &self.since)?,
+            until: parse("until", &self.until)?,
+        })
+    }
+}
+
+impl TestcaseFilter<'_> {
+    pub fn matches(&self, testcase: &Testcase) -> bool {
+        let property = |name: &str| {
+            testcase
+                .properties
+                .iter()
+                .find(|property| property.name == name)
+                .map(|property| property.value.as_str())
+        };
+        if self.query.failures_only && testcase.failures.is_empty() {
+            return false;
+        }
+        if let Some(failure_type) = &self.query.failure_type {
+            if !testcase
+                .failures
+                .iter()
+                .any(|failure| &failure.r#type.to_string() == failure_type)
+            {
+                return false;
+            }
+        }
+        if let Some(operation_id) = &self.query.operation_id {
+            if property("operationId") != Some(operation_id.as_str()) {
+                return false;
+            }
+        }
+        if let Some(prefix) = &self.query.correlation_id_prefix {
+            if !testcase
+                .correlation_id()
+                .is_some_and(|id| id.starts_with(prefix.as_str()))
+            {
+                return false;
+            }
+        }
+        if self.since.is_some() || self.until.is_some() {
+            // Testcases saved without the time they were recorded can't be placed in the range
+            let recorded_at = match testcase
+                .recorded_at
+                .as_deref()
+                .and_then(crate::datetime::parse_date_time)
+            {
+                Some(recorded_at) => recorded_at,
+                None => return false,
+            };
+            if self.since.is_some_and(|since| recorded_at < since)
+                || self.until.is_some_and(|until| recorded_at >= until)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{Map, Value};
+
+/// How long to wait for a remote document before giving up on starting the proxy.
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Copies the parts of remote documents that a spec references, like
+/// `$ref: https://schemas.example.com/pets.yaml#/components/schemas/Pet`, into the components of
+/// the spec and points the references at the copies. Every document is fetched once when the proxy
+/// starts, so validating requests never depends on the network. Returns None when the spec has no
+/// remote references.
+pub fn bundle(content: &str, allow_network: bool) -> Result<Option<String>, String> {
+    // Documents that can't be parsed are left for the spec parser to report
+    let mut document = match parse(content) {
+        Ok(document) => document,
+        Err(_) => return Ok(None),
+    };
+    let mut references = vec![];
+    collect_remote_references(&document, &mut references);
+    let first_reference = match references.first() {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+    if !allow_network {
+        return Err(format!(
+            "The spec references the remote document {}. Pass --allow-remote-refs to the proxy command to fetch it",
+            first_reference
+        ));
+    }
+    let mut bundler = Bundler::new(&document);
+    bundler.rewrite_references(&mut document, None)?;
+    if let Some(object) = document.as_object_mut() {
+        let components = object
+            .entry("components")
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(components) = components.as_object_mut() {
+            for ((kind, name), value) in bundler.components {
+                let kind = components
+                    .entry(kind)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if let Some(kind) = kind.as_object_mut() {
+                    kind.insert(name, value);
+                }
+            }
+        }
+    }
+    serde_json::to_string(&document)
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+struct Bundler {
+    /// Remote documents by URL, without the fragment.
+    documents: BTreeMap<String, Value>,
+    /// The local reference that replaces each remote reference.
+    local_references: BTreeMap<String, String>,
+    /// The kind and name of every component, including the ones the spec already had.
+    names: BTreeSet<(String, String)>,
+    /// The components copied from remote documents.
+    components: BTreeMap<(String, String), Value>,
+}
+
+impl Bundler {
+    fn new(document: &Value) -> Self {
+        let names = document
+            .get("components")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter_map(|(kind, components)| Some((kind, components.as_object()?)))
+            .flat_map(|(kind, components)| {
+                components
+                    .keys()
+                    .map(|name| (kind.to_string(), name.to_string()))
+            })
+            .collect();
+        Bundler {
+            documents: BTreeMap::new(),
+            local_references: BTreeMap::new(),
+            names,
+            components: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the remote references in the value. `base` is the URL of the remote document the
+    /// value was copied from, which its own references are relative to.
+    fn rewrite_references(
+        &mut self,
+        value: &mut Value,
+        base: Option<&url::Url>,
+    ) -> Result<(), String> {
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object.iter_mut() {
+                    match value {
+                        Value::String(reference) if key == "$ref" => {
+                            let absolute = match base {
+                                Some(base) => base.join(reference).ok(),
+                                None => url::Url::parse(reference).ok().filter(is_remote),
+                            };
+                            if let Some(absolute) = absolute {
+                                *reference = self.localize(absolute)?;
+                            }
+                        }
+                        _ => self.rewrite_references(value, base)?,
+                    }
+                }
+            }
+            Value::Array(values) => {
+                for value in values.iter_mut() {
+                    self.rewrite_references(value, base)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Copies the value a remote reference points to into the components and returns the local
+    /// reference to the copy.
+    fn localize(&mut self, reference: url::Url) -> Result<String, String> {
+        if let Some(local_reference) = self.local_references.get(reference.as_str()) {
+            return Ok(local_reference.clone());
+        }
+        let mut document_url = reference.clone();
+        document_url.set_fragment(None);
+        let pointer = reference.fragment().unwrap_or_default().to_string();
+        let mut value = self
+            .document(&document_url)?
+            .pointer(&pointer)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "The remote document {} does not contain {}",
+                    document_url, pointer
+                )
+            })?;
+        let (kind, name) = component_name(&document_url, &pointer);
+        let name = (1..)
+            .map(|attempt| match attempt {
+                1 => name.clone(),
+                _ => format!("{}{}", name, attempt),
+            })
+            .find(|name| !self.names.contains(&(kind.clone(), name.clone())))
+            .unwrap_or(name);
+        self.names.insert((kind.clone(), name.clone()));
+        let local_reference = format!("#/components/{}/{}", kind, name);
+        // The reference is recorded before its own references are rewritten so that cycles end
+        self.local_references
+            .insert(reference.to_string(), local_reference.clone());
+        self.rewrite_references(&mut value, Some(&document_url))?;
+        self.components.insert((kind, name), value);
+        Ok(local_reference)
+    }
+
+    fn document(&mut self, url: &url::Url) -> Result<&Value, String> {
+        if !self.documents.contains_key(url.as_str()) {
+            tracing::info!("Fetching remote document {}", url);
+            let content = ureq::get(url.as_str())
+                .timeout(FETCH_TIMEOUT)
+                .call()
+                .map_err(|err| format!("Failed to fetch the remote document {}: {}", url, err))?
+                .into_string()
+                .map_err(|err| format!("Failed to fetch the remote document {}: {}", url, err))?;
+            let document = parse(&content)
+                .map_err(|err| format!("Failed to read the remote document {}: {}", url, err))?;
+            self.documents.insert(url.to_string(), document);
+        }
+        Ok(&self.documents[url.as_str()])
+    }
+}
+
+fn parse(content: &str) -> Result<Value, Box<dyn std::error::Error>> {
+    if content.starts_with("{") {
+        Ok(serde_json::from_str(content)?)
+    } else {
+        Ok(serde_json::to_value(serde_yaml::from_str::<
+            serde_yaml::Value,
+        >(content)?)?)
+    }
+}
+
+fn is_remote(url: &url::Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+fn collect_remote_references(value: &Value, references: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter() {
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        if url::Url::parse(reference).is_ok_and(|url| is_remote(&url)) {
+                            references.push(reference.clone());
+                        }
+                    }
+                    _ => collect_remote_references(value, references),
+                }
+            }
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_remote_references(value, references)),
+        _ => {}
+    }
+}
+
+/// The kind and name of the component a remote value is copied to. Values that are components in
+/// their own document keep their kind and name, and anything else is assumed to be a schema named
+/// after the end of the pointer or the document.
+fn component_name(document_url: &url::Url, pointer: &str) -> (String, String) {
+    let segments = pointer
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect::<Vec<String>>();
+    if let [components, kind, name] = segments.as_slice() {
+        if components == "components" {
+            return (kind.clone(), sanitize(name));
+        }
+    }
+    let name = match segments.last() {
+        Some(name) => name.clone(),
+        None => document_url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|file| file.split('.').next())
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or("Remote")
+            .to_string(),
+    };
+    ("schemas".to_string(), sanitize(&name))
+}
+
+/// Replaces the characters that aren't allowed in component names.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+use axum::http::HeaderName;
+use ureq::OrAnyStatus;
+
+use crate::har;
+
+/// Headers that describe the connection a request was recorded on rather than the request itself.
+/// ureq sets these for the connection to the proxy.
+const CONNECTION_HEADERS: &[&str] = &["host", "content-length", "transfer-encoding", "connection"];
+
+/// Sends the requests of the entries to the proxy one at a time, in the order they were recorded,
+/// so they are validated as if the original client had sent them. Only the path and query of a
+/// recorded URL are kept, the proxy forwards the request to its own upstream. Entries recorded by
+/// the proxy carry their correlation ID even when it wasn't sent upstream, so it is added back to
+/// requests that don't have it in `correlation_id_header`. Requests that can't be sent are
+/// reported and skipped.
+pub fn replay(entries: &[har::Entry], proxy: &url::Url, correlation_id_header: &HeaderName) {
+    let agent = ureq::AgentBuilder::new().build();
+    for entry in entries {
+        let recorded = &entry.request;
+        let url = match url::Url::parse(&recorded.url) {
+            Ok(recorded_url) => {
+                let mut url = proxy.clone();
+                url.set_path(recorded_url.path());
+                url.set_query(recorded_url.query());
+                url
+            }
+            Err(err) => {
+                eprintln!("Skipping {} {}: {}", recorded.method, recorded.url, err);
+                continue;
+            }
+        };
+        let body = match recorded.post_data.as_ref().map(har::PostData::bytes) {
+            Some(Ok(body)) => body,
+            Some(Err(err)) => {
+                eprintln!(
+                    "Skipping {} {} because its body is not valid base64: {}",
+                    recorded.method, recorded.url, err
+                );
+                continue;
+            }
+            None => vec![],
+        };
+        let mut request = agent.request(&recorded.method, url.as_str());
+        for header in recorded.headers.iter() {
+            let name = header.name.to_ascii_lowercase();
+            // Browsers record the pseudo-headers of HTTP/2 requests, like :authority
+            if CONNECTION_HEADERS.contains(&name.as_str()) || name.starts_with(':') {
+                continue;
+            }
+            request = request.set(&header.name, &header.value);
+        }
+        if let Some(correlation_id) = entry.correlation_id.as_ref() {
+            if request.header(correlation_id_header.as_str()).is_none() {
+                request = request.set(correlation_id_header.as_str(), correlation_id);
+            }
+        }
+        if let Err(err) = request.send_bytes(&body).or_any_status() {
+            eprintln!(
+                "Failed to replay {} {}: {}",
+                recorded.method, recorded.url, err
+            );
+        }
+    }
+}
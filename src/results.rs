@@ -0,0 +1,85 @@
+use std::{error::Error, io::Write, path::Path, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{datetime, Session, Testcase};
+
+/// A file that every testcase is appended to as a line of JSON as soon as it is recorded. The
+/// testcases are kept even if the proxy is killed, and the reports can be served from the file
+/// later with the serve-report command.
+#[derive(Debug)]
+pub struct ResultsFile(Mutex<std::fs::File>);
+
+/// The first line of a results file. The reports served from the file describe the same session as
+/// the ones served by the proxy that saved it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionLine {
+    session: SavedSession,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSession {
+    started: String,
+    hostname: String,
+}
+
+impl ResultsFile {
+    /// Creates the file, replacing the results of an earlier session.
+    pub fn create(path: &Path, session: &Session) -> std::io::Result<Self> {
+        let mut file = std::fs::File::create(path)?;
+        let mut line = serde_json::to_string(&SessionLine {
+            session: SavedSession {
+                started: datetime::format_date_time(session.started),
+                hostname: session.hostname.clone(),
+            },
+        })?;
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        Ok(ResultsFile(Mutex::new(file)))
+    }
+
+    pub fn append(&self, testcase: &Testcase) {
+        let mut line = serde_json::to_string(testcase).unwrap();
+        line.push('\n');
+        // The whole line is written at once so testcases from concurrent requests don't interleave
+        if let Err(err) = self.0.lock().unwrap().write_all(line.as_bytes()) {
+            tracing::error!(%err, "Failed to save testcase");
+        }
+    }
+}
+
+/// Reads the session and the testcases saved in a results file. Files written before the session
+/// was saved only have testcases.
+pub fn load(path: &Path) -> Result<(Option<Session>, Vec<Testcase>), Box<dyn Error>> {
+    let content = std::fs::read_to_string(path)?;
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+    let session = lines
+        .peek()
+        .and_then(|(_, line)| serde_json::from_str::<SessionLine>(line).ok())
+        .map(|line| Session {
+            started: datetime::parse_date_time(&line.session.started)
+                .unwrap_or(std::time::UNIX_EPOCH),
+            hostname: line.session.hostname,
+        });
+    if session.is_some() {
+        lines.next();
+    }
+    let testcases = lines
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| {
+                format!(
+                    "{}:{} is not a saved testcase: {}",
+                    path.display(),
+                    index + 1,
+                    err
+                )
+                .into()
+            })
+        })
+        .collect::<Result<_, Box<dyn Error>>>()?;
+    Ok((session, testcases))
+}
@@ -0,0 +1,37 @@
+/// Named runs separate the testcases from different phases of testing, like "smoke" and
+/// "regression", that go through the same proxy. At most one run is active at a time and every
+/// testcase recorded while it is active is tagged with its name.
+#[derive(Debug, Default)]
+pub struct Runs {
+    /// Every run that has been started, in the order they were first started.
+    names: Vec<String>,
+    active: Option<String>,
+}
+
+impl Runs {
+    /// Makes the run active, stopping the run that was active before it. Starting a run that was
+    /// stopped resumes it.
+    pub fn start(&mut self, name: &str) {
+        if !self.names.iter().any(|existing| existing == name) {
+            self.names.push(name.to_string());
+        }
+        self.active = Some(name.to_string());
+    }
+
+    /// Stops the run. Returns false if the run was not active.
+    pub fn stop(&mut self, name: &str) -> bool {
+        if self.active.as_deref() != Some(name) {
+            return false;
+        }
+        self.active = None;
+        true
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}
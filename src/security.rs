@@ -0,0 +1,129 @@
+use openapiv3::{APIKeyLocation, ReferenceOr, SecurityRequirement, SecurityScheme};
+
+use crate::{parameters::RequestParameters, TestcaseFailure, TestcaseFailureType};
+
+/// Checks that a request carries the credentials of at least one of the security requirements of
+/// the operation, or of the spec when the operation doesn't declare its own. Only the presence of
+/// the credentials is checked, the proxy has no way to know if they are valid.
+pub fn validate_security(
+    operation: &openapiv3::Operation,
+    request: &RequestParameters,
+    spec: &openapiv3::OpenAPI,
+) -> Option<TestcaseFailure> {
+    let requirements = operation.security.as_ref().or(spec.security.as_ref())?;
+    // An empty list turns security off for the operation
+    if requirements.is_empty()
+        || requirements
+            .iter()
+            .any(|requirement| is_satisfied(requirement, request, spec))
+    {
+        return None;
+    }
+    let alternatives = requirements
+        .iter()
+        .map(|requirement| {
+            requirement
+                .keys()
+                .map(|name| match scheme(name, spec) {
+                    Some(scheme) => format!("{} ({})", name, describe(scheme)),
+                    None => name.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join(" and ")
+        })
+        .collect::<Vec<String>>();
+    Some(TestcaseFailure {
+        text: format!(
+            "Request did not include the credentials of any security requirement: {}",
+            alternatives.join(" or ")
+        ),
+        r#type: TestcaseFailureType::RequestMissingSecurityCredentials,
+        json_pointer: None,
+        spec_pointer: None,
+        perspective: None,
+    })
+}
+
+/// A requirement is satisfied when the request carries the credentials of every scheme it lists.
+/// Schemes that aren't declared in the components can't be checked, so they are assumed to be
+/// present.
+fn is_satisfied(
+    requirement: &SecurityRequirement,
+    request: &RequestParameters,
+    spec: &openapiv3::OpenAPI,
+) -> bool {
+    requirement.keys().all(|name| match scheme(name, spec) {
+        Some(scheme) => has_credentials(scheme, request),
+        None => true,
+    })
+}
+
+fn scheme<'a>(name: &str, spec: &'a openapiv3::OpenAPI) -> Option<&'a SecurityScheme> {
+    let scheme = spec.components.as_ref()?.security_schemes.get(name)?;
+    match scheme {
+        ReferenceOr::Item(scheme) => Some(scheme),
+        ReferenceOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/securitySchemes/")?;
+            spec.components
+                .as_ref()?
+                .security_schemes
+                .get(name)?
+                .as_item()
+        }
+    }
+}
+
+fn has_credentials(scheme: &SecurityScheme, request: &RequestParameters) -> bool {
+    match scheme {
+        SecurityScheme::APIKey { location, name, .. } => match location {
+            APIKeyLocation::Query => request.query.iter().any(|(key, _)| key == name),
+            APIKeyLocation::Header => request.headers.contains_key(name.as_str()),
+            APIKeyLocation::Cookie => cookies(request.headers).any(|cookie| cookie == name),
+        },
+        SecurityScheme::HTTP { scheme, .. } => authorization_scheme(request)
+            .is_some_and(|authorization| authorization.eq_ignore_ascii_case(scheme)),
+        SecurityScheme::OAuth2 { .. } | SecurityScheme::OpenIDConnect { .. } => {
+            authorization_scheme(request)
+                .is_some_and(|authorization| authorization.eq_ignore_ascii_case("bearer"))
+        }
+    }
+}
+
+/// The scheme of the Authorization header, like Bearer or Basic.
+fn authorization_scheme<'a>(request: &RequestParameters<'a>) -> Option<&'a str> {
+    request
+        .headers
+        .get(axum::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .split_whitespace()
+        .next()
+}
+
+/// The names of the cookies in the Cookie headers of the request.
+fn cookies(headers: &axum::http::HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(axum::http::header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|cookie| cookie.split('=').next())
+        .map(str::trim)
+}
+
+/// Where the credentials of the scheme are expected, for the failure text.
+fn describe(scheme: &SecurityScheme) -> String {
+    match scheme {
+        SecurityScheme::APIKey { location, name, .. } => match location {
+            APIKeyLocation::Query => format!("query parameter {}", name),
+            APIKeyLocation::Header => format!("header {}", name),
+            APIKeyLocation::Cookie => format!("cookie {}", name),
+        },
+        SecurityScheme::HTTP { scheme, .. } => {
+            format!("Authorization header with {} scheme", scheme)
+        }
+        SecurityScheme::OAuth2 { .. } | SecurityScheme::OpenIDConnect { .. } => {
+            "Authorization header with Bearer scheme".to_string()
+        }
+    }
+}
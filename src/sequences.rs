@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::links::{evaluate_expression, request_parameter, ExpressionContext};
+use crate::{TestcaseFailure, TestcaseFailureType};
+
+/// An invariant across requests that share a correlation ID. Once a request for the `after`
+/// operation has been made, later requests for the `then` operation must respond with one of the
+/// expected status codes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sequence {
+    pub name: String,
+    /// The operationId of the earlier request.
+    pub after: String,
+    /// Only start the sequence when the earlier request responded with one of these status codes.
+    /// Any status code starts the sequence when this is empty.
+    #[serde(default)]
+    pub after_status: Vec<u16>,
+    /// The operationId of the later request.
+    pub then: String,
+    pub expect_status: Vec<u16>,
+    /// Runtime expressions evaluated against the earlier request and response. The sequence only
+    /// applies to later requests whose parameters have the same values.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, serde_json::Value>,
+}
+
+/// A request that started a sequence.
+#[derive(Debug, Clone)]
+pub struct SequenceEvent {
+    pub correlation_id: String,
+    pub sequence: usize,
+    pub source_testcase: String,
+    pub parameters: BTreeMap<String, String>,
+}
+
+/// Records the sequences that the request starts. Parameters that can't be evaluated are left out,
+/// which makes the sequence apply to more requests rather than fewer.
+pub fn start_sequences(
+    sequences: &[Sequence],
+    events: &mut Vec<SequenceEvent>,
+    correlation_id: &str,
+    operation_id: &str,
+    testcase_name: &str,
+    context: &ExpressionContext,
+) {
+    for (index, sequence) in sequences.iter().enumerate() {
+        if sequence.after != operation_id {
+            continue;
+        }
+        if !sequence.after_status.is_empty() && !sequence.after_status.contains(&context.status) {
+            continue;
+        }
+        let parameters = sequence
+            .parameters
+            .iter()
+            .filter_map(|(name, expression)| {
+                evaluate_expression(expression, context).map(|value| (name.to_string(), value))
+            })
+            .collect();
+        events.push(SequenceEvent {
+            correlation_id: correlation_id.to_string(),
+            sequence: index,
+            source_testcase: testcase_name.to_string(),
+            parameters,
+        });
+    }
+}
+
+/// Checks the request against the sequences that earlier requests with the same correlation ID
+/// started. When several sequences apply, the one that was started most recently wins. This lets
+/// a DELETE that follows a POST change what a GET is expected to return.
+pub fn check_sequences(
+    sequences: &[Sequence],
+    events: &[SequenceEvent],
+    correlation_id: &str,
+    operation_id: &str,
+    context: &ExpressionContext,
+) -> Vec<TestcaseFailure> {
+    let mut failures = vec![];
+    let event = events.iter().rev().find(|event| {
+        event.correlation_id == correlation_id
+            && sequences[event.sequence].then == operation_id
+            && event
+                .parameters
+                .iter()
+                .all(|(name, expected)| request_parameter(name, context).as_ref() == Some(expected))
+    });
+    let event = match event {
+        Some(event) => event,
+        None => return failures,
+    };
+    let sequence = &sequences[event.sequence];
+    if !sequence.expect_status.contains(&context.status) {
+        failures.push(TestcaseFailure {
+            text: format!(
+                "Sequence {} expected {} to respond with {} after {} in testcase {}, but it responded with {}",
+                sequence.name,
+                sequence.then,
+                sequence
+                    .expect_status
+                    .iter()
+                    .map(|status| status.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" or "),
+                sequence.after,
+                event.source_testcase,
+                context.status
+            ),
+            r#type: TestcaseFailureType::SequenceViolation,
+            json_pointer: None,
+            spec_pointer: None,
+            perspective: None,
+        });
+    }
+    failures
+}
@@ -0,0 +1,63 @@
+use std::{convert::Infallible, fmt, path::PathBuf, str::FromStr, time::Duration};
+
+/// How long to wait for a spec to download.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the proxy reads its spec from. Services that serve their own spec, like at
+/// /openapi.json, can be pointed at directly instead of shipping the spec as a file.
+#[derive(Debug, Clone)]
+pub enum SpecSource {
+    File(PathBuf),
+    Url(url::Url),
+}
+
+impl FromStr for SpecSource {
+    type Err = Infallible;
+
+    /// Anything that isn't an http(s) URL is a file path.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match url::Url::parse(value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(SpecSource::Url(url)),
+            _ => Ok(SpecSource::File(PathBuf::from(value))),
+        }
+    }
+}
+
+impl fmt::Display for SpecSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpecSource::File(path) => write!(f, "{:?}", path),
+            SpecSource::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl SpecSource {
+    /// The name diagnostics show for the spec.
+    pub fn name(&self) -> String {
+        match self {
+            SpecSource::File(path) => path.display().to_string(),
+            SpecSource::Url(url) => url.to_string(),
+        }
+    }
+
+    /// Reads the spec. URLs are fetched with a timeout so a service that is still starting doesn't
+    /// hang the proxy.
+    pub fn read(&self) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            SpecSource::File(path) => {
+                if !path.is_file() {
+                    return Err(format!("Error: {:?} is not a file", path).into());
+                }
+                Ok(std::fs::read_to_string(path)?)
+            }
+            SpecSource::Url(url) => {
+                let response = ureq::get(url.as_str())
+                    .timeout(FETCH_TIMEOUT)
+                    .call()
+                    .map_err(|err| format!("Failed to fetch the spec from {}: {}", url, err))?;
+                Ok(response.into_string()?)
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use openapiv3::ReferenceOr;
+use serde::Serialize;
+
+use crate::{create_schema_for_all_of, resolve_parameter, resolve_request_body, resolve_schema};
+
+/// Schemas are only expanded this many levels deep so that recursive schemas terminate.
+const MAX_EXAMPLE_DEPTH: usize = 8;
+
+/// A skeleton request for an operation that has not been exercised through the proxy.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suggestion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    pub method: String,
+    /// The path template with any path parameters replaced by example values.
+    pub path: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+/// Builds a suggestion for every operation in the spec that is not in `exercised`. Operations are
+/// keyed by "{METHOD} {path template}", the same as the metrics.
+pub fn suggest_requests(
+    spec: &openapiv3::OpenAPI,
+    exercised: &BTreeSet<String>,
+) -> Vec<Suggestion> {
+    let mut suggestions = vec![];
+    for (path_template, method, operation) in spec.operations() {
+        let method = method.to_uppercase();
+        if exercised.contains(&format!("{} {}", method, path_template)) {
+            continue;
+        }
+        let mut path = path_template.to_string();
+        let mut query = BTreeMap::new();
+        let mut headers = BTreeMap::new();
+        for parameter in operation
+            .parameters
+            .iter()
+            .filter_map(|parameter| resolve_parameter(parameter, spec))
+        {
+            let value = parameter_example(parameter.parameter_data_ref(), spec);
+            match parameter {
+                openapiv3::Parameter::Path { parameter_data, .. } => {
+                    path = path.replace(
+                        &format!("{{{}}}", parameter_data.name),
+                        &json_value_to_string(&value),
+                    );
+                }
+                openapiv3::Parameter::Query { parameter_data, .. } => {
+                    query.insert(parameter_data.name.clone(), value);
+                }
+                openapiv3::Parameter::Header { parameter_data, .. } => {
+                    headers.insert(parameter_data.name.clone(), value);
+                }
+                openapiv3::Parameter::Cookie { .. } => {}
+            }
+        }
+        let request_body = operation
+            .request_body
+            .as_ref()
+            .and_then(|request_body| resolve_request_body(request_body, spec));
+        let (content_type, body) = match request_body.and_then(|body| body.content.first()) {
+            Some((content_type, media_type)) => (
+                Some(content_type.to_string()),
+                Some(media_type_example(media_type, spec)),
+            ),
+            None => (None, None),
+        };
+        suggestions.push(Suggestion {
+            operation_id: operation.operation_id.clone(),
+            method,
+            path,
+            query,
+            headers,
+            content_type,
+            body,
+        });
+    }
+    suggestions
+}
+
+fn json_value_to_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn parameter_example(
+    parameter_data: &openapiv3::ParameterData,
+    spec: &openapiv3::OpenAPI,
+) -> serde_json::Value {
+    if let Some(example) = &parameter_data.example {
+        return example.clone();
+    }
+    if let Some(example) = first_example(parameter_data.examples.values()) {
+        return example;
+    }
+    match &parameter_data.format {
+        openapiv3::ParameterSchemaOrContent::Schema(schema) => resolve_schema(schema, spec)
+            .map(|schema| schema_example(schema, spec, 0))
+            .unwrap_or(serde_json::Value::Null),
+        openapiv3::ParameterSchemaOrContent::Content(content) => content
+            .first()
+            .map(|(_, media_type)| media_type_example(media_type, spec))
+            .unwrap_or(serde_json::Value::Null),
+    }
+}
+
+fn media_type_example(
+    media_type: &openapiv3::MediaType,
+    spec: &openapiv3::OpenAPI,
+) -> serde_json::Value {
+    if let Some(example) = &media_type.example {
+        return example.clone();
+    }
+    if let Some(example) = first_example(media_type.examples.values()) {
+        return example;
+    }
+    media_type
+        .schema
+        .as_ref()
+        .and_then(|schema| resolve_schema(schema, spec))
+        .map(|schema| schema_example(schema, spec, 0))
+        .unwrap_or(serde_json::Value::Null)
+}
+
+fn first_example<'a>(
+    examples: impl IntoIterator<Item = &'a ReferenceOr<openapiv3::Example>>,
+) -> Option<serde_json::Value> {
+    examples
+        .into_iter()
+        .filter_map(|example| example.as_item())
+        .find_map(|example| example.value.clone())
+}
+
+/// Generates an example value for a schema, preferring the example, default, or first enum value
+/// from the spec over a placeholder for the schema's type.
+fn schema_example(
+    schema: &openapiv3::Schema,
+    spec: &openapiv3::OpenAPI,
+    depth: usize,
+) -> serde_json::Value {
+    if let Some(example) = &schema.schema_data.example {
+        return example.clone();
+    }
+    if let Some(default) = &schema.schema_data.default {
+        return default.clone();
+    }
+    if depth > MAX_EXAMPLE_DEPTH {
+        return serde_json::Value::Null;
+    }
+    match &schema.schema_kind {
+        openapiv3::SchemaKind::Type(openapiv3::Type::String(string)) => {
+            let placeholder = match string.format {
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::Date) => {
+                    "1970-01-01"
+                }
+                openapiv3::VariantOrUnknownOrEmpty::Item(openapiv3::StringFormat::DateTime) => {
+                    "1970-01-01T00:00:00Z"
+                }
+                _ => "string",
+            };
+            match string.enumeration.iter().flatten().next() {
+                Some(value) => serde_json::Value::String(value.clone()),
+                None => serde_json::Value::String(placeholder.to_string()),
+            }
+        }
+        openapiv3::SchemaKind::Type(openapiv3::Type::Integer(integer)) => {
+            match integer.enumeration.iter().flatten().next() {
+                Some(value) => serde_json::json!(value),
+                None => serde_json::json!(integer.minimum.unwrap_or(0)),
+            }
+        }
+        openapiv3::SchemaKind::Type(openapiv3::Type::Number(number)) => {
+            match number.enumeration.iter().flatten().next() {
+                Some(value) => serde_json::json!(value),
+                None => serde_json::json!(number.minimum.unwrap_or(0.0)),
+            }
+        }
+        openapiv3::SchemaKind::Type(openapiv3::Type::Boolean(_)) => serde_json::Value::Bool(true),
+        openapiv3::SchemaKind::Type(openapiv3::Type::Array(array)) => {
+            let item = array
+                .items
+                .as_ref()
+                .map(|items| items.clone().unbox())
+                .and_then(|items| {
+                    resolve_schema(&items, spec).map(|items| schema_example(items, spec, depth + 1))
+                });
+            match item {
+                Some(item) => serde_json::Value::Array(vec![item]),
+                None => serde_json::Value::Array(vec![]),
+            }
+        }
+        openapiv3::SchemaKind::Type(openapiv3::Type::Object(object)) => {
+            let mut map = serde_json::Map::new();
+            for (name, property) in object.properties.iter() {
+                let property = property.clone().unbox();
+                if let Some(property) = resolve_schema(&property, spec) {
+                    map.insert(name.clone(), schema_example(property, spec, depth + 1));
+                }
+            }
+            serde_json::Value::Object(map)
+        }
+        openapiv3::SchemaKind::AllOf { all_of } => {
+            let schema = create_schema_for_all_of(all_of, spec);
+            schema_example(&schema, spec, depth + 1)
+        }
+        openapiv3::SchemaKind::OneOf { one_of: schemas }
+        | openapiv3::SchemaKind::AnyOf { any_of: schemas } => schemas
+            .iter()
+            .find_map(|schema| resolve_schema(schema, spec))
+            .map(|schema| schema_example(schema, spec, depth + 1))
+            .unwrap_or(serde_json::Value::Null),
+        _ => serde_json::Value::Null,
+    }
+}
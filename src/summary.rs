@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::Testcase;
+
+/// How many testcases and failures were recorded, counted by failure type, operation and status
+/// code, so the state of a session can be seen without reading every testcase.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    #[serde(flatten)]
+    total: Counts,
+    /// The number of failures of each type.
+    failure_types: BTreeMap<String, usize>,
+    /// Keyed by operationId. Testcases that didn't match an operation aren't counted.
+    operations: BTreeMap<String, Counts>,
+    /// Keyed by the status code of the response. Requests that weren't answered aren't counted.
+    status_codes: BTreeMap<String, Counts>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Counts {
+    testcases: usize,
+    /// Testcases with at least one failure.
+    failed_testcases: usize,
+    failures: usize,
+}
+
+impl Counts {
+    fn add(&mut self, testcase: &Testcase) {
+        self.testcases += 1;
+        self.failed_testcases += usize::from(!testcase.failures.is_empty());
+        self.failures += testcase.failures.len();
+    }
+}
+
+pub fn summary(testcases: &[Testcase]) -> Summary {
+    let mut summary = Summary::default();
+    for testcase in testcases.iter() {
+        summary.total.add(testcase);
+        for failure in testcase.failures.iter() {
+            *summary
+                .failure_types
+                .entry(failure.r#type.to_string())
+                .or_default() += 1;
+        }
+        if let Some(operation_id) = testcase.property("operationId") {
+            summary
+                .operations
+                .entry(operation_id.to_string())
+                .or_default()
+                .add(testcase);
+        }
+        if let Some(status) = testcase.property("statusCode") {
+            summary
+                .status_codes
+                .entry(status.to_string())
+                .or_default()
+                .add(testcase);
+        }
+    }
+    summary
+}
@@ -0,0 +1,106 @@
+use std::{path::Path, sync::Arc};
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    DigitallySignedStruct, SignatureScheme,
+};
+
+/// Builds the TLS configuration for connections to the upstream and webhook consumers. The public
+/// web PKI roots are trusted, along with the certificates in the CA bundle if there is one.
+pub fn client_config(
+    ca_bundle: Option<&Path>,
+    insecure_skip_verify: bool,
+) -> Result<rustls::ClientConfig, Box<dyn std::error::Error>> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    if insecure_skip_verify {
+        return Ok(builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(SkipVerification(provider)))
+            .with_no_client_auth());
+    }
+
+    let mut roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if let Some(ca_bundle) = ca_bundle {
+        for certificate in read_certificates(ca_bundle)? {
+            roots.add(certificate).map_err(|err| {
+                format!(
+                    "{} contains an invalid certificate: {}",
+                    ca_bundle.display(),
+                    err
+                )
+            })?;
+        }
+    }
+    Ok(builder.with_root_certificates(roots).with_no_client_auth())
+}
+
+/// Reads every certificate in a PEM file.
+fn read_certificates(
+    path: &Path,
+) -> Result<Vec<CertificateDer<'static>>, Box<dyn std::error::Error>> {
+    let content = std::fs::read(path)
+        .map_err(|err| format!("Failed to read the CA bundle {}: {}", path.display(), err))?;
+    let certificates = rustls_pemfile::certs(&mut content.as_slice())
+        .collect::<Result<Vec<CertificateDer>, std::io::Error>>()
+        .map_err(|err| format!("{} is not a PEM file: {}", path.display(), err))?;
+    if certificates.is_empty() {
+        return Err(format!("{} doesn't contain any certificates", path.display()).into());
+    }
+    Ok(certificates)
+}
+
+/// Accepts any certificate the server presents. The handshake signatures are still checked so
+/// the connection works like any other, but the server's identity is not verified.
+#[derive(Debug)]
+struct SkipVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SkipVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,66 @@
+use axum::http::HeaderMap;
+
+/// The W3C trace context of an upstream request, so each testcase can be found in the
+/// distributed trace of the request it validated.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    pub trace_id: String,
+    /// The traceparent header sent to the upstream.
+    pub traceparent: String,
+    /// Whether the traceparent is the client's. The client's tracestate is only forwarded with
+    /// its own traceparent.
+    pub propagated: bool,
+}
+
+/// Propagates the client's traceparent if it is valid. Otherwise a new trace is started. Its trace
+/// ID is the correlation ID when that is a UUID, like the ones the proxy generates, so the two can
+/// be found from each other.
+pub fn context(headers: &HeaderMap, correlation_id: &str) -> TraceContext {
+    let traceparent = headers
+        .get("traceparent")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim);
+    if let Some((traceparent, trace_id)) =
+        traceparent.and_then(|traceparent| Some((traceparent, parse(traceparent)?)))
+    {
+        return TraceContext {
+            trace_id,
+            traceparent: traceparent.to_string(),
+            propagated: true,
+        };
+    }
+    let trace_id = uuid::Uuid::parse_str(correlation_id)
+        .ok()
+        .filter(|uuid| !uuid.is_nil())
+        .unwrap_or_else(uuid::Uuid::new_v4)
+        .simple()
+        .to_string();
+    let parent_id = uuid::Uuid::new_v4().simple().to_string()[..16].to_string();
+    TraceContext {
+        traceparent: format!("00-{}-{}-01", trace_id, parent_id),
+        trace_id,
+        propagated: false,
+    }
+}
+
+/// The trace ID of a version 00 traceparent, or None if it isn't valid. An all-zero trace or
+/// parent ID is invalid.
+fn parse(traceparent: &str) -> Option<String> {
+    let fields = traceparent.split('-').collect::<Vec<_>>();
+    let [version, trace_id, parent_id, flags] = fields.as_slice() else {
+        return None;
+    };
+    let hex = |field: &str, len: usize| {
+        field.len() == len
+            && field
+                .bytes()
+                .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+    };
+    let valid = *version == "00"
+        && hex(trace_id, 32)
+        && hex(parent_id, 16)
+        && hex(flags, 2)
+        && trace_id.bytes().any(|byte| byte != b'0')
+        && parent_id.bytes().any(|byte| byte != b'0');
+    valid.then(|| trace_id.to_string())
+}
@@ -0,0 +1,184 @@
+use std::{future::Future, pin::Pin, sync::Arc, task::Poll};
+
+use axum::{
+    body::Body,
+    http::{HeaderName, HeaderValue, Uri},
+};
+use hyper_util::{client::legacy, rt::TokioIo};
+use serde::Deserialize;
+
+use crate::dns::CachingResolver;
+
+/// The client used for requests to the upstream and webhook consumers. Connections are kept alive
+/// and reused between requests.
+pub type Client = legacy::Client<hyper_rustls::HttpsConnector<Connector>, Body>;
+
+/// Builds the client. HTTP/2 is negotiated with ALPN on https connections. With
+/// `http2_prior_knowledge`, every connection uses HTTP/2 from the start, which is the only way to
+/// reach cleartext HTTP/2 (h2c) servers.
+pub fn client(
+    resolver: CachingResolver,
+    tls_config: rustls::ClientConfig,
+    http2_prior_knowledge: bool,
+) -> Client {
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(tls_config)
+        .https_or_http()
+        .enable_all_versions()
+        .wrap_connector(Connector(Arc::new(resolver)));
+    legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+        .http2_only(http2_prior_knowledge)
+        .build(connector)
+}
+
+/// The Host header sent to the upstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Host {
+    /// The host and port of the upstream URL, so virtual-hosted upstreams see their own name.
+    Upstream,
+    /// The Host header the client sent to the proxy.
+    Preserve,
+    /// A fixed value.
+    Override(String),
+}
+
+/// Sends the requests under a path prefix to a different upstream than the UPSTREAM argument, so
+/// one proxy can sit in front of an API that is served by several backends.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Route {
+    pub prefix: PathPrefix,
+    pub url: url::Url,
+}
+
+/// A path prefix that is checked when the config file is loaded. It starts with a slash and
+/// doesn't end with one, so it can be compared with request paths directly.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PathPrefix(String);
+
+impl TryFrom<String> for PathPrefix {
+    type Error = String;
+
+    fn try_from(prefix: String) -> Result<Self, Self::Error> {
+        if !prefix.starts_with('/') {
+            return Err(format!(
+                "{} is not a path prefix, it must start with /",
+                prefix
+            ));
+        }
+        Ok(PathPrefix(prefix.trim_end_matches('/').to_string()))
+    }
+}
+
+impl Route {
+    /// The part of the path after the prefix, if the path is under it. The prefix has to end at a
+    /// segment, so /pets doesn't match /petstore.
+    fn rest<'a>(&self, path: &'a str) -> Option<&'a str> {
+        path.strip_prefix(self.prefix.0.as_str())
+            .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// The URL a request is sent to. The prefix is replaced by the path of the route's URL.
+    pub fn url(&self, path: &str, query: Option<&str>) -> url::Url {
+        let rest = self.rest(path).unwrap_or(path);
+        let mut url = self.url.clone();
+        url.set_path(&format!(
+            "{}{}",
+            self.url.path().trim_end_matches('/'),
+            rest
+        ));
+        url.set_query(query);
+        url
+    }
+}
+
+/// Finds the route for a request path. When prefixes overlap, the longest one wins.
+pub fn find_route<'a>(routes: &'a [Route], path: &str) -> Option<&'a Route> {
+    routes
+        .iter()
+        .filter(|route| route.rest(path).is_some())
+        .max_by_key(|route| route.prefix.0.len())
+}
+
+/// The host and port of a URL as they are written in a Host header. The port is left out when it is
+/// the default for the scheme.
+pub fn authority(url: &url::Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// Sends a request and returns the response as soon as its headers arrive. The body is streamed
+/// in both directions. Redirects are not followed, so the client receives the same response the
+/// upstream sent.
+pub async fn send(
+    client: &Client,
+    method: &str,
+    url: &url::Url,
+    headers: &[(String, String)],
+    body: Body,
+) -> Result<axum::http::Response<Body>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut request = axum::http::Request::builder()
+        .method(method)
+        .uri(url.as_str());
+    for (name, value) in headers {
+        request = request.header(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_bytes(value.as_bytes())?,
+        );
+    }
+    let response = client
+        .request(request.body(body)?)
+        .await
+        .map_err(|err| describe(url, &err))?;
+    Ok(response.map(Body::new))
+}
+
+/// Describes a failed request with the URL and every error in the chain. hyper's errors only name
+/// the stage that failed, and the cause, like a refused connection, is in their sources.
+fn describe(url: &url::Url, err: &dyn std::error::Error) -> String {
+    let mut description = format!("{}: {}", url, err);
+    let mut source = err.source();
+    while let Some(err) = source {
+        description.push_str(&format!(": {}", err));
+        source = err.source();
+    }
+    description
+}
+
+/// Opens TCP connections with the CachingResolver, so --resolve overrides and cached addresses
+/// are used for every connection.
+#[derive(Debug, Clone)]
+pub struct Connector(Arc<CachingResolver>);
+
+impl tower_service::Service<Uri> for Connector {
+    type Response = TokioIo<tokio::net::TcpStream>;
+    type Error = std::io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let host = uri.host().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{} doesn't have a host", uri),
+                )
+            })?;
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+            let addresses = resolver.resolve(&format!("{}:{}", host, port)).await?;
+            let stream = tokio::net::TcpStream::connect(addresses.as_slice()).await?;
+            stream.set_nodelay(true)?;
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
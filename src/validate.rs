@@ -0,0 +1,644 @@
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceSpan};
+use openapiv3::{ReferenceOr, Schema, SchemaKind, Type};
+use serde_json::Value;
+
+use crate::{
+    check::LintType,
+    json::{self, escape},
+    version,
+};
+
+/// The kinds of problems that stop the proxy from validating traffic against a spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemType {
+    /// The document isn't valid YAML or JSON, or isn't an OpenAPI document.
+    InvalidDocument,
+    /// A `$ref` points at something that isn't in the document.
+    UnresolvableReference,
+    /// A schema or media type is missing the schema that values are validated against.
+    MissingSchema,
+    /// Two path templates match the same requests, or a path template can't be routed.
+    DuplicatePath,
+    /// The spec doesn't have any paths or webhooks, so every request is PathNotFound.
+    NoPaths,
+    /// The spec uses something the proxy skips or can't validate against.
+    UnsupportedConstruct,
+}
+
+impl ProblemType {
+    fn code(&self) -> &'static str {
+        match self {
+            ProblemType::InvalidDocument => "ovp::invalid_document",
+            ProblemType::UnresolvableReference => "ovp::unresolvable_reference",
+            ProblemType::MissingSchema => "ovp::missing_schema",
+            ProblemType::DuplicatePath => "ovp::duplicate_path",
+            ProblemType::NoPaths => "ovp::no_paths",
+            ProblemType::UnsupportedConstruct => "ovp::unsupported_construct",
+        }
+    }
+}
+
+/// A problem in a spec, reported as a diagnostic that points at the part of the document it is
+/// about when that part can be found.
+#[derive(Debug)]
+pub struct Problem {
+    pub r#type: ProblemType,
+    pub text: String,
+    help: Option<String>,
+    label: Option<(SourceSpan, String)>,
+    source: NamedSource<String>,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl std::error::Error for Problem {}
+
+impl Diagnostic for Problem {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.r#type.code()))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn std::fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.label.as_ref().map(|_| &self.source as _)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let (span, label) = self.label.as_ref()?;
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(label.clone()),
+            *span,
+        ))))
+    }
+}
+
+/// Renders a problem the way it is printed by the validate subcommand. Colors are left out so the
+/// output reads the same in CI logs as in a terminal.
+pub fn render(problem: &Problem) -> String {
+    let mut rendered = String::new();
+    let handler =
+        miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor())
+            .with_links(false);
+    if handler.render_report(&mut rendered, problem).is_err() {
+        rendered = problem.to_string();
+    }
+    rendered
+}
+
+/// Reads the spec the way the proxy does and reports everything that would stop it from
+/// validating traffic: documents that can't be read, references that can't be resolved or
+/// aren't followed, media types and arrays without schemas, path templates that collide, and
+/// schemas the proxy can't validate against.
+pub fn validate_spec(name: &str, content: &str) -> Vec<Problem> {
+    let mut problems = Problems::new(name, content);
+    let spec = match problems.read_spec(content, false) {
+        Some(spec) => spec,
+        None => return problems.problems,
+    };
+    // Swagger documents are converted before they are validated, so their references are only
+    // checked for whether they resolve
+    let is_openapi = problems.document.get("openapi").is_some();
+    let mut references = vec![];
+    collect_references(&problems.document, String::new(), &mut references);
+    for (pointer, reference) in references {
+        problems.check_reference(&pointer, &reference, is_openapi);
+    }
+    if is_openapi {
+        problems.check_path_items();
+    }
+    for (path_template, lint) in crate::check::check_path_templates(&spec) {
+        let help = match lint.r#type {
+            LintType::UnroutablePath => "Requests can't match this path template",
+            _ => "Every request that matches one of these templates is routed to the first one",
+        };
+        problems.push_at(
+            ProblemType::DuplicatePath,
+            lint.text,
+            Some(help),
+            &format!("/paths/{}", escape(path_template)),
+            "declared here",
+        );
+    }
+    problems.check_schemas(&spec);
+    problems.check_paths(&spec);
+    problems.problems
+}
+
+/// Explains why the proxy couldn't read a spec, pointing at the part of the document that is
+/// wrong: a syntax error, a version that isn't supported, or a value that doesn't have the type
+/// OpenAPI requires.
+pub fn read_problems(name: &str, content: &str, force: bool) -> Vec<Problem> {
+    let mut problems = Problems::new(name, content);
+    problems.read_spec(content, force);
+    problems.problems
+}
+
+/// Problems in a spec the proxy could read that mean it won't validate anything.
+pub fn startup_problems(name: &str, content: &str, spec: &openapiv3::OpenAPI) -> Vec<Problem> {
+    let mut problems = Problems::new(name, content);
+    problems.document = parse(content).unwrap_or_default();
+    problems.check_paths(spec);
+    problems.problems
+}
+
+/// Shows where a definition is in the spec, for failures in the traffic that are caused by the
+/// spec rather than the traffic. `pointer` is a JSON pointer into the document as it was read.
+pub fn spec_excerpt(
+    name: &str,
+    content: &str,
+    pointer: &str,
+    r#type: ProblemType,
+    text: &str,
+) -> Option<String> {
+    let document = parse(content).ok()?;
+    // Swagger documents are converted before traffic is validated against them, so the pointers
+    // don't lead anywhere in the document
+    document.get("openapi")?;
+    let span = locate(&document, content, pointer)?;
+    Some(render(&Problem {
+        r#type,
+        text: text.to_string(),
+        help: None,
+        label: Some((span, "defined here".to_string())),
+        source: NamedSource::new(name, content.to_string()),
+    }))
+}
+
+struct Problems {
+    source: NamedSource<String>,
+    document: Value,
+    problems: Vec<Problem>,
+}
+
+impl Problems {
+    fn new(name: &str, content: &str) -> Self {
+        Problems {
+            source: NamedSource::new(name, content.to_string()),
+            document: Value::Null,
+            problems: vec![],
+        }
+    }
+
+    /// Reads the document and the spec in it the way the proxy does. Returns None when the spec
+    /// can't be read, after adding a problem that explains why.
+    fn read_spec(&mut self, content: &str, force: bool) -> Option<openapiv3::OpenAPI> {
+        self.document = match parse(content) {
+            Ok(document) => document,
+            Err((text, offset)) => {
+                let label = offset.map(|offset| (SourceSpan::from((offset, 0)), text.clone()));
+                self.push(ProblemType::InvalidDocument, text, None, label);
+                return None;
+            }
+        };
+        if let Ok(version::SpecVersion::Unsupported(version)) = version::detect(content) {
+            if !force {
+                let pointer = match self.document.get("openapi") {
+                    Some(_) => "/openapi",
+                    None => "/swagger",
+                };
+                self.push_at(
+                    ProblemType::InvalidDocument,
+                    version::unsupported_message(&version),
+                    None,
+                    pointer,
+                    "declared here",
+                );
+                return None;
+            }
+        }
+        match crate::parse_versioned_spec(content, force) {
+            Ok((spec, _)) => Some(spec),
+            Err(err) => {
+                // Swagger documents fail while they are converted, so only OpenAPI documents have
+                // a location to point at
+                let label = match self.document.get("openapi") {
+                    Some(_) => deserialize_offset(content)
+                        .map(|offset| (SourceSpan::from((offset, 0)), "this value".to_string())),
+                    None => None,
+                };
+                self.push(ProblemType::InvalidDocument, err.to_string(), None, label);
+                None
+            }
+        }
+    }
+
+    /// A spec without paths or webhooks is usually indented wrong or the wrong document.
+    fn check_paths(&mut self, spec: &openapiv3::OpenAPI) {
+        let has_webhooks = self
+            .document
+            .get("webhooks")
+            .and_then(Value::as_object)
+            .is_some_and(|webhooks| !webhooks.is_empty());
+        if !spec.paths.paths.is_empty() || has_webhooks {
+            return;
+        }
+        let pointer = match self.document.get("paths") {
+            Some(_) => "/paths",
+            None => "/openapi",
+        };
+        self.push_at(
+            ProblemType::NoPaths,
+            "The spec does not have any paths".to_string(),
+            Some("Every request will be reported as PathNotFound. Check that the paths are indented under the top level paths key"),
+            pointer,
+            "no paths",
+        );
+    }
+
+    fn push(
+        &mut self,
+        r#type: ProblemType,
+        text: String,
+        help: Option<&str>,
+        label: Option<(SourceSpan, String)>,
+    ) {
+        self.problems.push(Problem {
+            r#type,
+            text,
+            help: help.map(str::to_string),
+            label,
+            source: self.source.clone(),
+        });
+    }
+
+    /// Adds a problem labelled with the key at the end of the pointer.
+    fn push_at(
+        &mut self,
+        r#type: ProblemType,
+        text: String,
+        help: Option<&str>,
+        pointer: &str,
+        label: &str,
+    ) {
+        let label = locate(&self.document, self.source.inner(), pointer)
+            .map(|span| (span, label.to_string()));
+        self.push(r#type, text, help, label);
+    }
+
+    fn check_reference(&mut self, pointer: &str, reference: &str, is_openapi: bool) {
+        if url::Url::parse(reference).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+            // Remote references are bundled by the proxy with --allow-remote-refs
+            return;
+        }
+        let target = match reference.strip_prefix('#') {
+            Some(target) => target,
+            None => {
+                self.push_at(
+                    ProblemType::UnresolvableReference,
+                    format!("{} references another file", reference),
+                    Some("Only references within the spec and to http(s) URLs are resolved"),
+                    pointer,
+                    "this reference",
+                );
+                return;
+            }
+        };
+        let resolved = match self.document.pointer(target) {
+            Some(resolved) => resolved,
+            None => {
+                self.push_at(
+                    ProblemType::UnresolvableReference,
+                    format!("{} does not point at anything in the spec", reference),
+                    None,
+                    pointer,
+                    "this reference",
+                );
+                return;
+            }
+        };
+        if !is_openapi {
+            return;
+        }
+        let is_component = target
+            .strip_prefix("/components/")
+            .is_some_and(|name| name.split('/').count() == 2);
+        if !is_component {
+            self.push_at(
+                ProblemType::UnsupportedConstruct,
+                format!("{} does not point at a component", reference),
+                Some("The proxy only follows references to #/components/, move the target there"),
+                pointer,
+                "this reference",
+            );
+        } else if resolved.get("$ref").is_some() {
+            self.push_at(
+                ProblemType::UnsupportedConstruct,
+                format!("{} points at another reference", reference),
+                Some("The proxy only follows one reference, point this one at the final target"),
+                pointer,
+                "this reference",
+            );
+        }
+    }
+
+    /// Path items and webhooks that are references are skipped by the proxy.
+    fn check_path_items(&mut self) {
+        for section in ["paths", "webhooks"] {
+            let references = self
+                .document
+                .get(section)
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .filter(|(_, path_item)| path_item.get("$ref").is_some())
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>();
+            for name in references {
+                self.push_at(
+                    ProblemType::UnsupportedConstruct,
+                    format!("{} in {} is a reference", name, section),
+                    Some("Requests to path items that are references aren't validated, inline the path item"),
+                    &format!("/{}/{}", section, escape(&name)),
+                    "declared here",
+                );
+            }
+        }
+    }
+
+    fn check_schemas(&mut self, spec: &openapiv3::OpenAPI) {
+        for (path_template, path_item) in spec.paths.paths.iter() {
+            let path_item = match path_item.as_item() {
+                Some(path_item) => path_item,
+                None => continue,
+            };
+            let pointer = format!("/paths/{}", escape(path_template));
+            self.check_parameters(&pointer, &path_item.parameters);
+            for (method, operation) in path_item.iter() {
+                let pointer = format!("{}/{}", pointer, method);
+                self.check_parameters(&pointer, &operation.parameters);
+                if let Some(ReferenceOr::Item(request_body)) = operation.request_body.as_ref() {
+                    self.check_content(&format!("{}/requestBody", pointer), &request_body.content);
+                }
+                let responses = operation
+                    .responses
+                    .responses
+                    .iter()
+                    .map(|(status_code, response)| (status_code.to_string(), response))
+                    .chain(
+                        operation
+                            .responses
+                            .default
+                            .iter()
+                            .map(|response| ("default".to_string(), response)),
+                    );
+                for (status_code, response) in responses {
+                    if let ReferenceOr::Item(response) = response {
+                        self.check_content(
+                            &format!("{}/responses/{}", pointer, status_code),
+                            &response.content,
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(components) = spec.components.as_ref() {
+            for (name, schema) in components.schemas.iter() {
+                self.check_schema(
+                    &format!("/components/schemas/{}", escape(name)),
+                    schema.as_item(),
+                );
+            }
+            for (name, response) in components.responses.iter() {
+                if let ReferenceOr::Item(response) = response {
+                    self.check_content(
+                        &format!("/components/responses/{}", escape(name)),
+                        &response.content,
+                    );
+                }
+            }
+            for (name, request_body) in components.request_bodies.iter() {
+                if let ReferenceOr::Item(request_body) = request_body {
+                    self.check_content(
+                        &format!("/components/requestBodies/{}", escape(name)),
+                        &request_body.content,
+                    );
+                }
+            }
+            for (name, parameter) in components.parameters.iter() {
+                if let ReferenceOr::Item(parameter) = parameter {
+                    let pointer = format!("/components/parameters/{}", escape(name));
+                    self.check_parameter_format(&pointer, &parameter.parameter_data_ref().format);
+                }
+            }
+        }
+    }
+
+    fn check_parameters(
+        &mut self,
+        pointer: &str,
+        parameters: &[ReferenceOr<openapiv3::Parameter>],
+    ) {
+        for (index, parameter) in parameters.iter().enumerate() {
+            if let ReferenceOr::Item(parameter) = parameter {
+                let pointer = format!("{}/parameters/{}", pointer, index);
+                self.check_parameter_format(&pointer, &parameter.parameter_data_ref().format);
+            }
+        }
+    }
+
+    fn check_parameter_format(
+        &mut self,
+        pointer: &str,
+        format: &openapiv3::ParameterSchemaOrContent,
+    ) {
+        match format {
+            openapiv3::ParameterSchemaOrContent::Schema(schema) => {
+                self.check_schema(&format!("{}/schema", pointer), schema.as_item())
+            }
+            openapiv3::ParameterSchemaOrContent::Content(content) => {
+                self.check_content(pointer, content)
+            }
+        }
+    }
+
+    fn check_content(&mut self, pointer: &str, content: &openapiv3::Content) {
+        for (media_type_name, media_type) in content.iter() {
+            let pointer = format!("{}/content/{}", pointer, escape(media_type_name));
+            match media_type.schema.as_ref() {
+                Some(schema) => self.check_schema(&format!("{}/schema", pointer), schema.as_item()),
+                None => self.push_at(
+                    ProblemType::MissingSchema,
+                    format!("{} has no schema", pointer),
+                    Some("Bodies of media types without a schema are reported as unexpected, add a schema"),
+                    &pointer,
+                    "declared here",
+                ),
+            }
+        }
+    }
+
+    /// Checks a schema and the schemas nested in it. Referenced schemas are checked as components.
+    fn check_schema(&mut self, pointer: &str, schema: Option<&Schema>) {
+        let schema = match schema {
+            Some(schema) => schema,
+            None => return,
+        };
+        match &schema.schema_kind {
+            SchemaKind::Type(Type::Object(object)) => {
+                for (name, property) in object.properties.iter() {
+                    self.check_schema(
+                        &format!("{}/properties/{}", pointer, escape(name)),
+                        property.as_item().map(|property| &**property),
+                    );
+                }
+                if let Some(openapiv3::AdditionalProperties::Schema(additional_properties)) =
+                    object.additional_properties.as_ref()
+                {
+                    self.check_schema(
+                        &format!("{}/additionalProperties", pointer),
+                        additional_properties.as_item(),
+                    );
+                }
+            }
+            SchemaKind::Type(Type::Array(array)) => match array.items.as_ref() {
+                Some(items) => self.check_schema(
+                    &format!("{}/items", pointer),
+                    items.as_item().map(|items| &**items),
+                ),
+                None => self.push_at(
+                    ProblemType::MissingSchema,
+                    format!("{} is an array without items", pointer),
+                    Some("Arrays are validated against their items, add an items schema"),
+                    pointer,
+                    "declared here",
+                ),
+            },
+            SchemaKind::OneOf { one_of: schemas }
+            | SchemaKind::AnyOf { any_of: schemas }
+            | SchemaKind::AllOf { all_of: schemas } => {
+                let keyword = match &schema.schema_kind {
+                    SchemaKind::OneOf { .. } => "oneOf",
+                    SchemaKind::AnyOf { .. } => "anyOf",
+                    _ => "allOf",
+                };
+                for (index, nested) in schemas.iter().enumerate() {
+                    self.check_schema(
+                        &format!("{}/{}/{}", pointer, keyword, index),
+                        nested.as_item(),
+                    );
+                }
+            }
+            SchemaKind::Not { not } => {
+                self.check_schema(&format!("{}/not", pointer), not.as_item())
+            }
+            SchemaKind::Any(_) => self.push_at(
+                ProblemType::UnsupportedConstruct,
+                format!("{} does not declare a type", pointer),
+                Some("The proxy can't validate values against schemas without a type, add one"),
+                pointer,
+                "declared here",
+            ),
+            SchemaKind::Type(_) => {}
+        }
+    }
+}
+
+/// Parses the document without reading it as a spec. Errors carry the offset of the problem in
+/// the document when the parser reports one.
+fn parse(content: &str) -> Result<Value, (String, Option<usize>)> {
+    if content.starts_with("{") {
+        serde_json::from_str(content).map_err(|err| {
+            (
+                format!("Failed to parse the spec as JSON: {}", err),
+                Some(json::error_offset(content, &err)),
+            )
+        })
+    } else {
+        serde_yaml::from_str::<serde_yaml::Value>(content)
+            .map_err(|err| {
+                (
+                    format!("Failed to parse the spec as YAML: {}", err),
+                    err.location().map(|location| location.index()),
+                )
+            })
+            .and_then(|document| {
+                serde_json::to_value(document)
+                    .map_err(|err| (format!("Failed to parse the spec as YAML: {}", err), None))
+            })
+    }
+}
+
+/// Where deserializing the document as an OpenAPI spec fails, for errors like a value with the
+/// wrong type that parse doesn't catch.
+fn deserialize_offset(content: &str) -> Option<usize> {
+    if content.starts_with("{") {
+        let err = serde_json::from_str::<openapiv3::OpenAPI>(content).err()?;
+        Some(json::error_offset(content, &err))
+    } else {
+        let err = serde_yaml::from_str::<openapiv3::OpenAPI>(content).err()?;
+        err.location().map(|location| location.index())
+    }
+}
+
+/// Collects every `$ref` in the document along with the JSON pointer to it.
+fn collect_references(value: &Value, pointer: String, references: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter() {
+                let pointer = format!("{}/{}", pointer, escape(key));
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        references.push((pointer, reference.clone()))
+                    }
+                    _ => collect_references(value, pointer, references),
+                }
+            }
+        }
+        Value::Array(values) => {
+            for (index, value) in values.iter().enumerate() {
+                collect_references(value, format!("{}/{}", pointer, index), references);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Finds the key at the end of the pointer in the source of the document. Keys are searched for
+/// in order after the key of their parent, which finds the right one in documents where keys
+/// aren't repeated between siblings and their children.
+fn locate(document: &Value, source: &str, pointer: &str) -> Option<SourceSpan> {
+    let mut value = document;
+    let mut offset = 0;
+    let mut span = None;
+    for segment in pointer.split('/').skip(1) {
+        let segment = segment.replace("~1", "/").replace("~0", "~");
+        match value {
+            Value::Object(object) => {
+                value = object.get(&segment)?;
+                let start = offset + find_key(&source[offset..], &segment)?;
+                offset = start + segment.len();
+                span = Some(SourceSpan::from((start, segment.len())));
+            }
+            Value::Array(values) => value = values.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        }
+    }
+    span
+}
+
+/// The offset of the first place the key is used as a key of a YAML or JSON object.
+fn find_key(source: &str, key: &str) -> Option<usize> {
+    source
+        .match_indices(key)
+        .map(|(index, _)| index)
+        .find(|index| {
+            let before = source[..*index].chars().next_back();
+            let after = source[index + key.len()..]
+                .trim_start_matches(['"', '\''])
+                .chars()
+                .next();
+            matches!(
+                before,
+                None | Some(' ' | '\n' | '\t' | '"' | '\'' | '{' | ',')
+            ) && after == Some(':')
+        })
+}
@@ -0,0 +1,515 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// The OpenAPI versions the proxy validates against. openapiv3 models 3.0 documents, and the parts
+/// of 3.1 documents it doesn't model, like webhooks, are read separately.
+const SUPPORTED_VERSIONS: &[&str] = &["3.0", "3.1"];
+
+/// The Swagger version that is converted to OpenAPI 3.0 when it is loaded.
+const CONVERTIBLE_SWAGGER_VERSION: &str = "2.0";
+
+/// The fields that declare the version of an OpenAPI or Swagger document. The values are read
+/// loosely because an unquoted version like `openapi: 3.0` is a number in YAML.
+#[derive(Debug, Default, Deserialize)]
+struct VersionDocument {
+    openapi: Option<serde_yaml::Value>,
+    swagger: Option<serde_yaml::Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpecVersion {
+    /// Contains the version, like "OpenAPI 3.0.0".
+    Supported(String),
+    /// A version that is converted to OpenAPI 3.0 with `convert`. Contains the version, like
+    /// "Swagger 2.0".
+    Convertible(String),
+    /// Contains the version, like "Swagger 1.2".
+    Unsupported(String),
+}
+
+/// Reads the version a document declares. Documents that declare neither an `openapi` nor a
+/// `swagger` version are an error because there is no way to tell how they should be read.
+pub fn detect(content: &str) -> Result<SpecVersion, String> {
+    let document: VersionDocument = if content.starts_with("{") {
+        serde_json::from_str(content).map_err(|err| err.to_string())?
+    } else {
+        serde_yaml::from_str(content).map_err(|err| err.to_string())?
+    };
+    match (document.openapi, document.swagger) {
+        (Some(openapi), _) => {
+            let version = version_string(&openapi);
+            let supported = SUPPORTED_VERSIONS.iter().any(|supported| {
+                version == *supported || version.starts_with(&format!("{}.", supported))
+            });
+            match supported {
+                true => Ok(SpecVersion::Supported(format!("OpenAPI {}", version))),
+                false => Ok(SpecVersion::Unsupported(format!("OpenAPI {}", version))),
+            }
+        }
+        (None, Some(swagger)) => {
+            let version = version_string(&swagger);
+            match version == CONVERTIBLE_SWAGGER_VERSION {
+                true => Ok(SpecVersion::Convertible(format!("Swagger {}", version))),
+                false => Ok(SpecVersion::Unsupported(format!("Swagger {}", version))),
+            }
+        }
+        (None, None) => Err(format!(
+            "The document does not declare its version with an openapi field. Supported versions: {}",
+            supported_versions()
+        )),
+    }
+}
+
+/// The message shown when a document is rejected because of its version.
+pub fn unsupported_message(version: &str) -> String {
+    format!(
+        "The document is {}, which is not supported. Supported versions: {}. Pass --force to the proxy command to validate against it anyway",
+        version,
+        supported_versions()
+    )
+}
+
+/// Reads a document that isn't OpenAPI 3.0 or 3.1 as if it were OpenAPI 3.0. Swagger 2.0 documents
+/// are converted first:
+///
+/// - host, basePath, and schemes become servers
+/// - definitions, parameters, responses, and securityDefinitions become components
+/// - body and formData parameters become request bodies for the media types the operation consumes
+/// - response schemas and headers become content for the media types the operation produces
+pub fn convert(content: &str) -> Result<openapiv3::OpenAPI, Box<dyn std::error::Error>> {
+    let mut document: Value = if content.starts_with("{") {
+        serde_json::from_str(content)?
+    } else {
+        serde_json::to_value(serde_yaml::from_str::<serde_yaml::Value>(content)?)?
+    };
+    if let Some(object) = document.as_object_mut() {
+        if object.contains_key("swagger") {
+            upgrade_swagger(object);
+        }
+        object.insert("openapi".to_string(), "3.0.0".into());
+    }
+    Ok(serde_json::from_value(document)?)
+}
+
+fn upgrade_swagger(document: &mut Map<String, Value>) {
+    let servers = servers(document);
+    if !servers.is_empty() {
+        document.insert("servers".to_string(), Value::Array(servers));
+    }
+    for key in ["host", "basePath", "schemes"] {
+        document.remove(key);
+    }
+    let consumes = media_types(document.remove("consumes").as_ref());
+    let produces = media_types(document.remove("produces").as_ref());
+    let shared_parameters = document
+        .get("parameters")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let mut components = Map::new();
+    for (swagger_key, component_key) in [
+        ("definitions", "schemas"),
+        ("parameters", "parameters"),
+        ("responses", "responses"),
+        ("securityDefinitions", "securitySchemes"),
+    ] {
+        if let Some(component) = document.remove(swagger_key) {
+            components.insert(component_key.to_string(), component);
+        }
+    }
+    if let Some(parameters) = components
+        .get_mut("parameters")
+        .and_then(Value::as_object_mut)
+    {
+        // Shared body parameters have no equivalent in components, so they are left out
+        parameters.retain(|_, parameter| !is_body_parameter(parameter, &shared_parameters));
+        for parameter in parameters.values_mut() {
+            upgrade_parameter(parameter);
+        }
+    }
+    if let Some(responses) = components
+        .get_mut("responses")
+        .and_then(Value::as_object_mut)
+    {
+        for response in responses.values_mut() {
+            upgrade_response(response, &produces);
+        }
+    }
+    if let Some(schemes) = components
+        .get_mut("securitySchemes")
+        .and_then(Value::as_object_mut)
+    {
+        for scheme in schemes.values_mut() {
+            upgrade_security_scheme(scheme);
+        }
+    }
+    if !components.is_empty() {
+        document.insert("components".to_string(), Value::Object(components));
+    }
+    let paths = document
+        .get_mut("paths")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|paths| paths.values_mut())
+        .filter_map(Value::as_object_mut);
+    for path_item in paths {
+        // Body and form parameters shared by every operation of the path become part of each
+        // operation's request body
+        let path_parameters = path_item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(parameters) = path_item.get_mut("parameters") {
+            upgrade_parameters(parameters, &shared_parameters);
+        }
+        let operations = path_item
+            .iter_mut()
+            .filter(|(key, _)| key.as_str() != "parameters")
+            .filter_map(|(_, operation)| operation.as_object_mut());
+        for operation in operations {
+            let consumes = operation
+                .remove("consumes")
+                .map_or(consumes.clone(), |consumes| media_types(Some(&consumes)));
+            let produces = operation
+                .remove("produces")
+                .map_or(produces.clone(), |produces| media_types(Some(&produces)));
+            let parameters = path_parameters
+                .iter()
+                .chain(
+                    operation
+                        .get("parameters")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten(),
+                )
+                .map(|parameter| resolve_parameter(parameter, &shared_parameters).clone())
+                .collect::<Vec<Value>>();
+            if let Some(request_body) = request_body(&parameters, &consumes) {
+                operation.insert("requestBody".to_string(), request_body);
+            }
+            if let Some(parameters) = operation.get_mut("parameters") {
+                upgrade_parameters(parameters, &shared_parameters);
+            }
+            let responses = operation
+                .get_mut("responses")
+                .and_then(Value::as_object_mut)
+                .into_iter()
+                .flat_map(|responses| responses.values_mut());
+            for response in responses {
+                upgrade_response(response, &produces);
+            }
+        }
+    }
+    for value in document.values_mut() {
+        rewrite_references(value);
+    }
+}
+
+/// The servers described by the host, basePath, and schemes of a Swagger document. Without a host
+/// the servers are relative to wherever the document was served from.
+fn servers(document: &Map<String, Value>) -> Vec<Value> {
+    let host = document.get("host").and_then(Value::as_str);
+    let base_path = document
+        .get("basePath")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let schemes = document
+        .get("schemes")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect::<Vec<&str>>();
+    match (host, schemes.as_slice()) {
+        (None, _) if base_path.is_empty() => vec![],
+        (None, _) => vec![serde_json::json!({ "url": base_path })],
+        (Some(host), []) => vec![serde_json::json!({ "url": format!("https://{}{}", host, base_path) })],
+        (Some(host), schemes) => schemes
+            .iter()
+            .map(|scheme| serde_json::json!({ "url": format!("{}://{}{}", scheme, host, base_path) }))
+            .collect(),
+    }
+}
+
+/// Finds the shared parameter a `#/parameters/` reference points to.
+fn resolve_parameter<'a>(
+    parameter: &'a Value,
+    shared_parameters: &'a Map<String, Value>,
+) -> &'a Value {
+    parameter
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/parameters/"))
+        .and_then(|name| shared_parameters.get(name))
+        .unwrap_or(parameter)
+}
+
+fn is_body_parameter(parameter: &Value, shared_parameters: &Map<String, Value>) -> bool {
+    matches!(
+        resolve_parameter(parameter, shared_parameters)
+            .get("in")
+            .and_then(Value::as_str),
+        Some("body" | "formData")
+    )
+}
+
+/// The request body made from the body parameter or the form parameters of an operation.
+fn request_body(parameters: &[Value], consumes: &[String]) -> Option<Value> {
+    if let Some(body) = parameters
+        .iter()
+        .find(|parameter| parameter.get("in") == Some(&Value::from("body")))
+    {
+        let schema = body.get("schema").cloned().unwrap_or_default();
+        let mut request_body = serde_json::json!({ "content": content(consumes, &schema) });
+        if let Some(required) = body.get("required") {
+            request_body["required"] = required.clone();
+        }
+        return Some(request_body);
+    }
+    let form_parameters = parameters
+        .iter()
+        .filter(|parameter| parameter.get("in") == Some(&Value::from("formData")))
+        .collect::<Vec<&Value>>();
+    if form_parameters.is_empty() {
+        return None;
+    }
+    let mut properties = Map::new();
+    let mut required = vec![];
+    for parameter in form_parameters {
+        let name = match parameter.get("name").and_then(Value::as_str) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let mut schema = parameter_schema(&mut parameter.as_object().cloned().unwrap_or_default());
+        // Files are sent as binary strings in OpenAPI 3
+        if schema.get("type") == Some(&Value::from("file")) {
+            schema.insert("type".to_string(), "string".into());
+            schema.insert("format".to_string(), "binary".into());
+        }
+        if parameter.get("required") == Some(&Value::Bool(true)) {
+            required.push(Value::from(name.clone()));
+        }
+        properties.insert(name, Value::Object(schema));
+    }
+    let mut schema = serde_json::json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+    // Form parameters are only sent as forms, whatever the operation consumes otherwise
+    let form_media_types = consumes
+        .iter()
+        .filter(|media_type| {
+            media_type.as_str() == "application/x-www-form-urlencoded"
+                || media_type.as_str() == "multipart/form-data"
+        })
+        .cloned()
+        .collect::<Vec<String>>();
+    let form_media_types = match form_media_types.is_empty() {
+        true => vec!["application/x-www-form-urlencoded".to_string()],
+        false => form_media_types,
+    };
+    Some(serde_json::json!({ "content": content(&form_media_types, &schema) }))
+}
+
+/// Removes body and form parameters and moves the type of the other parameters into a schema.
+fn upgrade_parameters(parameters: &mut Value, shared_parameters: &Map<String, Value>) {
+    let parameters = match parameters.as_array_mut() {
+        Some(parameters) => parameters,
+        None => return,
+    };
+    parameters.retain(|parameter| !is_body_parameter(parameter, shared_parameters));
+    for parameter in parameters.iter_mut() {
+        upgrade_parameter(parameter);
+    }
+}
+
+fn upgrade_parameter(parameter: &mut Value) {
+    let parameter = match parameter.as_object_mut() {
+        Some(parameter) if !parameter.contains_key("schema") && !parameter.contains_key("$ref") => {
+            parameter
+        }
+        _ => return,
+    };
+    // Arrays were separated by commas unless collectionFormat said otherwise
+    match parameter
+        .remove("collectionFormat")
+        .as_ref()
+        .and_then(Value::as_str)
+    {
+        Some("multi") => {
+            parameter.insert("style".to_string(), "form".into());
+            parameter.insert("explode".to_string(), true.into());
+        }
+        Some("ssv") => {
+            parameter.insert("style".to_string(), "spaceDelimited".into());
+        }
+        Some("pipes") => {
+            parameter.insert("style".to_string(), "pipeDelimited".into());
+        }
+        _ if parameter.get("type") == Some(&Value::from("array"))
+            && parameter.get("in") == Some(&Value::from("query")) =>
+        {
+            parameter.insert("explode".to_string(), false.into());
+        }
+        _ => {}
+    }
+    let schema = parameter_schema(parameter);
+    parameter.insert("schema".to_string(), Value::Object(schema));
+}
+
+/// Moves the keys that describe the type of a Swagger parameter or header into a schema.
+fn parameter_schema(parameter: &mut Map<String, Value>) -> Map<String, Value> {
+    let mut schema = Map::new();
+    for key in [
+        "type",
+        "format",
+        "items",
+        "enum",
+        "default",
+        "minimum",
+        "maximum",
+        "exclusiveMinimum",
+        "exclusiveMaximum",
+        "multipleOf",
+        "minLength",
+        "maxLength",
+        "pattern",
+        "minItems",
+        "maxItems",
+        "uniqueItems",
+    ] {
+        if let Some(value) = parameter.remove(key) {
+            schema.insert(key.to_string(), value);
+        }
+    }
+    schema
+}
+
+fn upgrade_response(response: &mut Value, produces: &[String]) {
+    let response = match response.as_object_mut() {
+        Some(response) => response,
+        None => return,
+    };
+    if let Some(schema) = response.remove("schema") {
+        response.insert("content".to_string(), content(produces, &schema));
+    }
+    let headers = response
+        .get_mut("headers")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|headers| headers.values_mut())
+        .filter_map(Value::as_object_mut);
+    for header in headers {
+        let schema = parameter_schema(header);
+        header.insert("schema".to_string(), Value::Object(schema));
+    }
+}
+
+fn upgrade_security_scheme(scheme: &mut Value) {
+    let scheme = match scheme.as_object_mut() {
+        Some(scheme) => scheme,
+        None => return,
+    };
+    match scheme.get("type").and_then(Value::as_str) {
+        Some("basic") => {
+            scheme.insert("type".to_string(), "http".into());
+            scheme.insert("scheme".to_string(), "basic".into());
+        }
+        Some("oauth2") => {
+            let flow = match scheme.remove("flow").as_ref().and_then(Value::as_str) {
+                Some("implicit") => "implicit",
+                Some("password") => "password",
+                Some("application") => "clientCredentials",
+                _ => "authorizationCode",
+            };
+            let mut flow_object = Map::new();
+            for key in ["authorizationUrl", "tokenUrl"] {
+                if let Some(url) = scheme.remove(key) {
+                    flow_object.insert(key.to_string(), url);
+                }
+            }
+            flow_object.insert(
+                "scopes".to_string(),
+                scheme
+                    .remove("scopes")
+                    .unwrap_or_else(|| Value::Object(Map::new())),
+            );
+            scheme.insert(
+                "flows".to_string(),
+                serde_json::json!({ flow: flow_object }),
+            );
+        }
+        _ => {}
+    }
+}
+
+fn media_types(media_types: Option<&Value>) -> Vec<String> {
+    let media_types = media_types
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .map(str::to_string)
+        .collect::<Vec<String>>();
+    match media_types.is_empty() {
+        true => vec!["application/json".to_string()],
+        false => media_types,
+    }
+}
+
+fn content(media_types: &[String], schema: &Value) -> Value {
+    media_types
+        .iter()
+        .map(|media_type| (media_type.clone(), serde_json::json!({ "schema": schema })))
+        .collect::<Map<String, Value>>()
+        .into()
+}
+
+fn rewrite_references(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                match value {
+                    Value::String(reference) if key == "$ref" => {
+                        for (swagger_prefix, component_prefix) in [
+                            ("#/definitions/", "#/components/schemas/"),
+                            ("#/parameters/", "#/components/parameters/"),
+                            ("#/responses/", "#/components/responses/"),
+                        ] {
+                            if let Some(name) = reference.strip_prefix(swagger_prefix) {
+                                *reference = format!("{}{}", component_prefix, name);
+                            }
+                        }
+                    }
+                    _ => rewrite_references(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(rewrite_references),
+        _ => {}
+    }
+}
+
+fn supported_versions() -> String {
+    SUPPORTED_VERSIONS
+        .iter()
+        .map(|version| format!("OpenAPI {}.x", version))
+        .chain(std::iter::once(format!(
+            "Swagger {}",
+            CONVERTIBLE_SWAGGER_VERSION
+        )))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn version_string(value: &serde_yaml::Value) -> String {
+    match value {
+        serde_yaml::Value::String(version) => version.clone(),
+        serde_yaml::Value::Number(version) => version.to_string(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
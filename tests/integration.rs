@@ -1,33 +1,232 @@
 use httpmock::MockServer;
 use insta_cmd::get_cargo_bin;
-use rand::Rng;
-use std::process::Command;
+use std::{
+    io::{Read, Write},
+    process::Command,
+};
 use ureq::OrAnyStatus;
 
+/// The time of a testcase depends on how quickly the mock server responds, the mock server listens
+/// on a random port, and the User-Agent includes the ureq version, so these are redacted from JUnit
+/// snapshots.
+macro_rules! assert_junit_snapshot {
+    ($xml:expr) => {
+        insta::with_settings!({filters => vec![
+            (r#"time="\d+\.\d+""#, r#"time="[time]""#),
+            (r#"timestamp="[^"]+""#, r#"timestamp="[timestamp]""#),
+            (r#"hostname="[^"]+""#, r#"hostname="[hostname]""#),
+            (r#"run-date="[^"]+" run-time="[^"]+" environment="[^"]+""#, r#"run-date="[date]" run-time="[time]" environment="[hostname]""#),
+            (r#"start-time="[^"]+" end-time="[^"]+" duration="[^"]+""#, r#"start-time="[time]" end-time="[time]" duration="[time]""#),
+            (r#" duration="\d+\.\d+""#, r#" duration="[time]""#),
+            (r#"machine-name="[^"]+""#, r#"machine-name="[hostname]""#),
+            (r"(Latency|Validation)Ms=\d+\.\d+", "${1}Ms=[time]"),
+            (r#"(Latency|Validation)Ms" value="\d+\.\d+""#, r#"${1}Ms" value="[time]""#),
+            (r"http://(localhost|127\.0\.0\.1):\d+", "http://[host]"),
+            (r"(localhost|127\.0\.0\.1):\d+", "[host]"),
+            (r"ureq/\d+\.\d+\.\d+", "ureq/[version]"),
+        ]}, {
+            insta::assert_snapshot!($xml);
+        });
+    };
+}
+
+/// Asks the OS for a port that is not in use. Picking a random port could collide with the
+/// ephemeral ports used by the mock servers and clients of other tests running in parallel.
+fn free_port() -> u16 {
+    std::net::TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// The TLS configuration of the test upstreams. Their certificate for localhost is signed by the CA
+/// in tests/tls/ca.pem.
+fn tls_server_config() -> rustls::ServerConfig {
+    let certificates = rustls_pemfile::certs(&mut include_bytes!("tls/localhost.pem").as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = rustls_pemfile::private_key(&mut include_bytes!("tls/localhost.key").as_slice())
+        .unwrap()
+        .unwrap();
+    rustls::ServerConfig::builder_with_provider(std::sync::Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(certificates, key)
+    .unwrap()
+}
+
+/// Starts an https server on a random port that answers every request with the JSON body.
+fn tls_upstream(body: &'static str) -> u16 {
+    use std::io::{BufRead, Write};
+
+    let config = std::sync::Arc::new(tls_server_config());
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let connection = rustls::ServerConnection::new(config.clone()).unwrap();
+            let mut stream = std::io::BufReader::new(rustls::StreamOwned::new(connection, stream));
+            // Clients that don't trust the certificate abort the handshake while the request is read
+            let mut line = String::new();
+            while stream.read_line(&mut line).is_ok_and(|read| read > 2) {
+                line.clear();
+            }
+            let _ = write!(
+                stream.get_mut(),
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.get_mut().flush();
+        }
+    });
+    port
+}
+
+/// Starts a server on a random port that answers every request with the response head and the start
+/// of a body, and then closes the connection before the body is complete.
+fn truncating_upstream(head: &'static str, body: &'static str) -> u16 {
+    use std::io::{BufRead, Write};
+
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let mut stream = std::io::BufReader::new(stream);
+            let mut line = String::new();
+            while stream.read_line(&mut line).is_ok_and(|read| read > 2) {
+                line.clear();
+            }
+            let _ = write!(stream.get_mut(), "{}\r\n\r\n{}", head, body);
+            let _ = stream.get_mut().flush();
+        }
+    });
+    port
+}
+
+/// Starts a server on a random port that only speaks HTTP/2 and answers every request with the JSON
+/// body. With `tls`, the server only offers h2 with ALPN. Otherwise it expects cleartext HTTP/2
+/// (h2c) from the start.
+fn h2_upstream(body: &'static str, tls: bool) -> u16 {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let acceptor = tls.then(|| {
+        let mut config = tls_server_config();
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        tokio_rustls::TlsAcceptor::from(std::sync::Arc::new(config))
+    });
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async move {
+            let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let service = hyper::service::service_fn(|_| async move {
+                        hyper::Response::builder()
+                            .header("Content-Type", "application/json")
+                            .body(http_body_util::Full::new(hyper::body::Bytes::from(body)))
+                    });
+                    let builder = hyper::server::conn::http2::Builder::new(
+                        hyper_util::rt::TokioExecutor::new(),
+                    );
+                    match acceptor {
+                        Some(acceptor) => {
+                            if let Ok(stream) = acceptor.accept(stream).await {
+                                let stream = hyper_util::rt::TokioIo::new(stream);
+                                let _ = builder.serve_connection(stream, service).await;
+                            }
+                        }
+                        None => {
+                            let stream = hyper_util::rt::TokioIo::new(stream);
+                            let _ = builder.serve_connection(stream, service).await;
+                        }
+                    }
+                });
+            }
+        });
+    });
+    port
+}
+
 /// This struct is used to start the validator proxy.
 struct ValidatorProxyServerHandle {
     process: std::process::Child,
 }
 
 impl ValidatorProxyServerHandle {
-    /// new will start the validator proxy on a random part using the petstore.yaml file.
+    /// new will start the validator proxy on the given port using the petstore.yaml file.
     fn new(url: &str, port: u16) -> Self {
+        Self::with_args(url, port, "tests/petstore.yaml", &[])
+    }
+
+    /// with_args will start the validator proxy using the given spec and any additional arguments.
+    fn with_args(url: &str, port: u16, spec: &str, args: &[&str]) -> Self {
+        let mut cmd = Command::new(get_cargo_bin("openapi-validator-proxy"));
+        cmd.args(["proxy", spec, url, "--port", &port.to_string()]);
+        cmd.args(args);
+        Self::spawn(cmd, port)
+    }
+
+    /// serve_report will serve the reports for the testcases saved in the results file.
+    fn serve_report(results: &str, port: u16) -> Self {
+        let mut cmd = Command::new(get_cargo_bin("openapi-validator-proxy"));
+        cmd.args(["serve-report", results, "--port", &port.to_string()]);
+        Self::spawn(cmd, port)
+    }
+
+    /// record will start the validator proxy using the petstore.yaml file and record the traffic
+    /// to the given HAR file.
+    fn record(har: &str, url: &str, port: u16) -> Self {
         let mut cmd = Command::new(get_cargo_bin("openapi-validator-proxy"));
         cmd.args([
-            "proxy",
+            "record",
+            har,
             "tests/petstore.yaml",
             url,
             "--port",
             &port.to_string(),
         ]);
-        let child = cmd.spawn().unwrap();
-        // Wait for the server to start
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+        Self::spawn(cmd, port)
+    }
+
+    fn spawn(mut cmd: Command, port: u16) -> Self {
+        let mut child = cmd.spawn().unwrap();
+        // Wait for the server to start accepting connections. The tests run in parallel so this
+        // can take a while on a busy machine.
+        let started = std::time::Instant::now();
+        while std::net::TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if let Some(status) = child.try_wait().unwrap() {
+                panic!("Proxy server exited before it started: {}", status);
+            }
+            if started.elapsed() > std::time::Duration::from_secs(30) {
+                panic!("Proxy server did not start within 30 seconds");
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
         println!("Proxy server started");
         Self { process: child }
     }
 }
 
+impl ValidatorProxyServerHandle {
+    /// terminate will ask the validator proxy to shut down gracefully, the way CI runners stop
+    /// processes, and wait for it to exit.
+    #[cfg(unix)]
+    fn terminate(&mut self) -> std::process::ExitStatus {
+        Command::new("kill")
+            .args(["-TERM", &self.process.id().to_string()])
+            .status()
+            .unwrap();
+        self.process.wait().unwrap()
+    }
+}
+
 impl Drop for ValidatorProxyServerHandle {
     /// This Drop implementation will kill the validator proxy server when the handle goes out of scope (when the test ends).
     fn drop(&mut self) {
@@ -42,8 +241,7 @@ fn path_not_found() -> Result<(), Box<dyn std::error::Error>> {
         when.method(httpmock::Method::GET).path("/pet");
         then.status(404).body("Not Found");
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pet", port).as_str())
@@ -54,7 +252,7 @@ fn path_not_found() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -65,8 +263,7 @@ fn invalid_http_method() -> Result<(), Box<dyn std::error::Error>> {
         when.method(httpmock::Method::DELETE).path("/pets");
         then.status(405).body("Method Not Allowed");
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::delete(format!("http://localhost:{}/pets", port).as_str())
@@ -77,7 +274,7 @@ fn invalid_http_method() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -85,14 +282,14 @@ fn invalid_http_method() -> Result<(), Box<dyn std::error::Error>> {
 fn invalid_status_code() -> Result<(), Box<dyn std::error::Error>> {
     let mock_server = MockServer::start();
     let mock = mock_server.mock(|when, then| {
-        when.method(httpmock::Method::GET).path("/pets");
+        when.method(httpmock::Method::GET)
+            .path("/any_of_pet_schema");
         then.status(600).body("Server Error");
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
-    ureq::get(format!("http://localhost:{}/pets", port).as_str())
+    ureq::get(format!("http://localhost:{}/any_of_pet_schema", port).as_str())
         .set("OVP-Correlation-Id", "invalid_status_code")
         .call()
         .or_any_status()?;
@@ -100,7 +297,335 @@ fn invalid_status_code() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn default_response() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets")
+            .header("OVP-Correlation-Id", "default_response_valid");
+        then.status(500)
+            .header("Content-Type", "application/json")
+            .body(r#"{"code": 500, "message": "Server Error"}"#);
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets")
+            .header("OVP-Correlation-Id", "default_response_invalid");
+        then.status(503)
+            .header("Content-Type", "application/json")
+            .body(r#"{"message": "Server Error"}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for correlation_id in ["default_response_valid", "default_response_invalid"] {
+        ureq::get(format!("http://localhost:{}/pets", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn status_code_ranges() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mocks = [
+        ("range_valid", 404, r#"{"reason": "Not found"}"#),
+        ("range_invalid", 429, r#"{"retryAfter": 10}"#),
+        ("outside_range", 500, r#"{"reason": "Server Error"}"#),
+    ]
+    .map(|(correlation_id, status, body)| {
+        mock_server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path("/orders")
+                .header("OVP-Correlation-Id", correlation_id);
+            then.status(status)
+                .header("Content-Type", "application/json")
+                .body(body);
+        })
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/status_ranges.yaml",
+        &[],
+    );
+
+    for correlation_id in ["range_valid", "range_invalid", "outside_range"] {
+        ureq::get(format!("http://localhost:{}/orders", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mocks.iter().for_each(|mock| mock.assert());
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn media_type_ranges() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let get_mocks = [
+        ("exact_match", "application/json", r#"{"name": "report"}"#),
+        ("type_range", "text/plain", "report"),
+        ("any_range", "image/png", "PNG"),
+    ]
+    .map(|(correlation_id, content_type, body)| {
+        mock_server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path("/documents")
+                .header("OVP-Correlation-Id", correlation_id);
+            then.status(200)
+                .header("Content-Type", content_type)
+                .body(body);
+        })
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/media_ranges.yaml",
+        &[],
+    );
+
+    for correlation_id in ["exact_match", "type_range", "any_range"] {
+        ureq::get(format!("http://localhost:{}/documents", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()?;
+    }
+    ureq::post(format!("http://localhost:{}/_ovp/webhook/newDocument", port).as_str())
+        .set("OVP-Correlation-Id", "request_type_range")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"name": "report"}"#)?;
+    ureq::post(format!("http://localhost:{}/_ovp/webhook/newDocument", port).as_str())
+        .set("OVP-Correlation-Id", "request_outside_range")
+        .set("Content-Type", "text/plain")
+        .send_string("report")?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    get_mocks.iter().for_each(|mock| mock.assert());
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn content_type_parameters() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mocks = [
+        (
+            "mixed_case",
+            "Application/JSON; Charset=\"UTF-8\"",
+            r#"{"title": "Q3"}"#,
+        ),
+        (
+            "matching_parameter",
+            "application/json; charset=utf-8; version=2",
+            r#"{"title": "Q3"}"#,
+        ),
+    ]
+    .map(|(correlation_id, content_type, body)| {
+        mock_server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path("/summaries")
+                .header("OVP-Correlation-Id", correlation_id);
+            then.status(200)
+                .header("Content-Type", content_type)
+                .body(body);
+        })
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/media_ranges.yaml",
+        &[],
+    );
+
+    for correlation_id in ["mixed_case", "matching_parameter"] {
+        ureq::get(format!("http://localhost:{}/summaries", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mocks.iter().for_each(|mock| mock.assert());
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn ndjson_body() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let events_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/events");
+        then.status(200)
+            .header("Content-Type", "application/x-ndjson")
+            .body("{\"id\": 1, \"kind\": \"created\"}\n{\"id\": \"2\", \"kind\": \"updated\"}\n\n{\"id\": 3}\n");
+    });
+    let logs_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/logs");
+        then.status(200)
+            .header("Content-Type", "application/jsonl")
+            .body("{\"id\": 1, \"kind\": \"info\"}\r\n{\"id\": 2,\r\n");
+    });
+    let port = free_port();
+    let _proxy_handle =
+        ValidatorProxyServerHandle::with_args(&mock_server.url(""), port, "tests/ndjson.yaml", &[]);
+
+    for path in ["/events", "/logs"] {
+        ureq::get(format!("http://localhost:{}{}", port, path).as_str())
+            .set("OVP-Correlation-Id", "ndjson_body")
+            .call()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    events_mock.assert();
+    logs_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn read_only_and_write_only_properties() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mocks = [
+        (
+            "response_without_write_only",
+            r#"{"id": 1, "username": "ada"}"#,
+        ),
+        (
+            "response_with_write_only",
+            r#"{"id": 1, "username": "ada", "password": "hunter2"}"#,
+        ),
+    ]
+    .map(|(correlation_id, body)| {
+        mock_server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path("/accounts/1")
+                .header("OVP-Correlation-Id", correlation_id);
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .body(body);
+        })
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/read_write_only.yaml",
+        &[],
+    );
+
+    for correlation_id in ["response_without_write_only", "response_with_write_only"] {
+        ureq::get(format!("http://localhost:{}/accounts/1", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()?;
+    }
+    for (correlation_id, body) in [
+        (
+            "request_without_read_only",
+            r#"{"username": "ada", "password": "hunter2"}"#,
+        ),
+        (
+            "request_with_read_only",
+            r#"{"id": 1, "username": "ada", "password": "hunter2"}"#,
+        ),
+    ] {
+        ureq::post(format!("http://localhost:{}/_ovp/webhook/newAccount", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .set("Content-Type", "application/json")
+            .send_string(body)?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mocks.iter().for_each(|mock| mock.assert());
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn proxied_request_read_only() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/accounts");
+        then.status(201);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/read_write_only.yaml",
+        &[],
+    );
+
+    for (correlation_id, body) in [
+        (
+            "proxied_request_without_read_only",
+            r#"{"username": "ada", "password": "hunter2"}"#,
+        ),
+        (
+            "proxied_request_with_read_only",
+            r#"{"id": 1, "username": "ada", "password": "hunter2"}"#,
+        ),
+    ] {
+        ureq::post(format!("http://localhost:{}/accounts", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .set("Content-Type", "application/json")
+            .send_string(body)?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert_hits(2);
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn deprecated_operation() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/legacy/orders");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"["order-1"]"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/status_ranges.yaml",
+        &[],
+    );
+
+    ureq::get(format!("http://localhost:{}/legacy/orders", port).as_str())
+        .set("OVP-Correlation-Id", "deprecated_operation")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -111,8 +636,7 @@ fn missing_content_type_header() -> Result<(), Box<dyn std::error::Error>> {
         when.method(httpmock::Method::GET).path("/pets");
         then.status(200).body(r#"[{"id": 1, "name": "dog"}]"#);
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets", port).as_str())
@@ -123,7 +647,7 @@ fn missing_content_type_header() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -136,8 +660,7 @@ fn mismatched_content_type_header() -> Result<(), Box<dyn std::error::Error>> {
             .header("Content-Type", "wrong")
             .body(r#"[{"id": 1, "name": "dog"}]"#);
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets", port).as_str())
@@ -148,7 +671,7 @@ fn mismatched_content_type_header() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -160,8 +683,7 @@ fn mismatch_non_empty_body() -> Result<(), Box<dyn std::error::Error>> {
         then.status(202)
             .json_body(serde_json::json!({"id": 1, "name": "dog"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -173,7 +695,7 @@ fn mismatch_non_empty_body() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -187,8 +709,7 @@ fn missing_schema_definition() -> Result<(), Box<dyn std::error::Error>> {
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!([]));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/missing_pets_schema", port).as_str())
@@ -200,7 +721,50 @@ fn missing_schema_definition() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn spec_locations() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let tags_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/tags");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(["indoor"]));
+    });
+    let pet_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"name": "dog", "owner": "sam"}));
+    });
+    let missing_pet_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(404);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/spec_locations.yaml",
+        &[],
+    );
+
+    for path in ["/tags", "/pets/1", "/pets/2"] {
+        ureq::get(format!("http://localhost:{}{}", port, path).as_str())
+            .set("OVP-Correlation-Id", "spec_locations")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    tags_mock.assert();
+    pet_mock.assert();
+    missing_pet_mock.assert();
+
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -214,8 +778,7 @@ fn failed_json_deserialization() -> Result<(), Box<dyn std::error::Error>> {
             .header("Content-Type", "application/json")
             .body(r#"{"id":, "name": "dog"}"#);
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -227,7 +790,7 @@ fn failed_json_deserialization() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -241,8 +804,7 @@ fn failed_validation_unexpected_null() -> Result<(), Box<dyn std::error::Error>>
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!({"id": null, "name": "dog"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -254,7 +816,7 @@ fn failed_validation_unexpected_null() -> Result<(), Box<dyn std::error::Error>>
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -268,8 +830,7 @@ fn failed_validation_unexpected_boolean() -> Result<(), Box<dyn std::error::Erro
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!({"id": false, "name": "dog"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -281,7 +842,7 @@ fn failed_validation_unexpected_boolean() -> Result<(), Box<dyn std::error::Erro
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -295,8 +856,7 @@ fn failed_validation_unexpected_number() -> Result<(), Box<dyn std::error::Error
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!({"id": 1, "name": 0}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -308,7 +868,7 @@ fn failed_validation_unexpected_number() -> Result<(), Box<dyn std::error::Error
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -322,8 +882,7 @@ fn failed_validation_unexpected_string() -> Result<(), Box<dyn std::error::Error
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!({"id": "1", "name": "dog"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -335,7 +894,7 @@ fn failed_validation_unexpected_string() -> Result<(), Box<dyn std::error::Error
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -349,8 +908,7 @@ fn failed_validation_unexpected_property() -> Result<(), Box<dyn std::error::Err
             .header("Content-Type", "application/json")
             .json_body(serde_json::json!({"id": 1, "name": "dog", "extra": "field"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
@@ -365,29 +923,27 @@ fn failed_validation_unexpected_property() -> Result<(), Box<dyn std::error::Err
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
 #[test]
-fn failed_validation_unsupported_schema_kind() -> Result<(), Box<dyn std::error::Error>> {
+fn failed_validation_missing_required_property() -> Result<(), Box<dyn std::error::Error>> {
     let mock_server = MockServer::start();
     let mock = mock_server.mock(|when, then| {
-        // Prepare mock response with extra field
-        when.method(httpmock::Method::GET)
-            .path("/any_of_pet_schema");
+        // Prepare mock response without the required id
+        when.method(httpmock::Method::GET).path("/pets/1");
         then.status(200)
             .header("Content-Type", "application/json")
-            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+            .json_body(serde_json::json!({"name": "dog"}));
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
-    ureq::get(format!("http://localhost:{}/any_of_pet_schema", port).as_str())
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
         .set(
             "OVP-Correlation-Id",
-            "failed_validation_unsupported_schema_kind",
+            "failed_validation_missing_required_property",
         )
         .call()
         .or_any_status()
@@ -396,7 +952,45 @@ fn failed_validation_unsupported_schema_kind() -> Result<(), Box<dyn std::error:
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn any_of_schema() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/any_of_pet_schema")
+            .header("OVP-Correlation-Id", "any_of_schema_match");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let no_match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/any_of_pet_schema")
+            .header("OVP-Correlation-Id", "any_of_schema_no_match");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for correlation_id in ["any_of_schema_match", "any_of_schema_no_match"] {
+        ureq::get(format!("http://localhost:{}/any_of_pet_schema", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()
+            .or_any_status()
+            .expect("Failed to make request");
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    match_mock.assert();
+    no_match_mock.assert();
+
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -408,8 +1002,7 @@ fn delete_with_204() -> Result<(), Box<dyn std::error::Error>> {
         when.method(httpmock::Method::DELETE).path("/pets/1");
         then.status(204);
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::delete(format!("http://localhost:{}/pets/1", port).as_str())
@@ -421,7 +1014,184 @@ fn delete_with_204() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn query_string_properties() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets")
+            .query_param("limit", "10");
+        then.status(200)
+            .header("content-type", "application/json")
+            .body("[]");
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets?limit=10", port).as_str())
+        .set("OVP-Correlation-Id", "query_string_properties")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn internal_proxy_error() -> Result<(), Box<dyn std::error::Error>> {
+    // Nothing is listening on the upstream port, so sending the request upstream fails
+    let upstream_port = free_port();
+    let port = free_port();
+    let _proxy_handle =
+        ValidatorProxyServerHandle::new(&format!("http://localhost:{}", upstream_port), port);
+
+    for correlation_id in ["internal_proxy_error_1", "internal_proxy_error_2"] {
+        let response = ureq::get(format!("http://localhost:{}/pets", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()
+            .or_any_status()
+            .expect("Failed to make request");
+        assert_eq!(response.status(), 502);
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn forwarded_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let port = free_port();
+    let mock_server = MockServer::start();
+    // The client's address is added to the chain from the proxy in front of it
+    let forwarded_mock = mock_server.mock(|when, then| {
+        when.path("/pets/1")
+            .header("OVP-Correlation-Id", "forwarded_headers")
+            .header("X-Forwarded-For", "203.0.113.7, 127.0.0.1")
+            .header("X-Forwarded-Proto", "https")
+            .header("X-Forwarded-Host", format!("localhost:{}", port))
+            .header(
+                "Forwarded",
+                format!("for=127.0.0.1;host=\"localhost:{}\";proto=http", port),
+            );
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+    let disabled_port = free_port();
+    let disabled_mock = mock_server.mock(|when, then| {
+        when.path("/pets/1")
+            .header("OVP-Correlation-Id", "no_forwarded_headers")
+            .matches(|request| {
+                request.headers.iter().flatten().all(|(name, _)| {
+                    !name.to_lowercase().starts_with("x-forwarded-")
+                        && !name.eq_ignore_ascii_case("forwarded")
+                })
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let _disabled_proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        disabled_port,
+        "tests/petstore.yaml",
+        &["--no-forwarded-headers"],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "forwarded_headers")
+        .set("X-Forwarded-For", "203.0.113.7")
+        .set("X-Forwarded-Proto", "https")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", disabled_port).as_str())
+        .set("OVP-Correlation-Id", "no_forwarded_headers")
+        .call()?;
+    forwarded_mock.assert();
+    disabled_mock.assert();
+    Ok(())
+}
+
+#[test]
+fn host_header() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let upstream_host = mock_server.address().to_string();
+    let mut proxies = vec![];
+    let mut mocks = vec![];
+    // None expects the Host the client sent to the proxy
+    for (name, args, expected) in [
+        ("upstream_host", vec![], Some(upstream_host.as_str())),
+        ("preserve_host", vec!["--preserve-host"], None),
+        (
+            "override_host",
+            vec!["--override-host", "api.example.com"],
+            Some("api.example.com"),
+        ),
+    ] {
+        let port = free_port();
+        let expected = expected
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("localhost:{}", port));
+        mocks.push(mock_server.mock(|when, then| {
+            when.path("/pets/1")
+                .header("OVP-Correlation-Id", name)
+                .header("Host", expected);
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+        }));
+        proxies.push(ValidatorProxyServerHandle::with_args(
+            &mock_server.url(""),
+            port,
+            "tests/petstore.yaml",
+            &args,
+        ));
+        ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+            .set("OVP-Correlation-Id", name)
+            .call()?;
+    }
+    for mock in mocks {
+        mock.assert();
+    }
+    Ok(())
+}
+
+#[test]
+fn non_standard_methods() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.path("/pets/1");
+        then.status(200);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for method in ["TRACE", "PURGE"] {
+        let response = ureq::request(method, format!("http://localhost:{}/pets/1", port).as_str())
+            .set(
+                "OVP-Correlation-Id",
+                &format!("non_standard_methods_{}", method),
+            )
+            .call()
+            .or_any_status()
+            .expect("Failed to make request");
+        assert_eq!(response.status(), 200);
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert_hits(2);
+
+    assert_junit_snapshot!(xml);
     Ok(())
 }
 
@@ -433,8 +1203,7 @@ fn empty_body_200() -> Result<(), Box<dyn std::error::Error>> {
         when.method(httpmock::Method::DELETE).path("/pets/1");
         then.status(200);
     });
-    let mut rng = rand::thread_rng();
-    let port: u16 = rng.gen_range(8000..u16::MAX);
+    let port = free_port();
     let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
 
     ureq::delete(format!("http://localhost:{}/pets/1", port).as_str())
@@ -446,6 +1215,3727 @@ fn empty_body_200() -> Result<(), Box<dyn std::error::Error>> {
     let xml = junit.into_string()?;
     mock.assert();
 
-    insta::assert_snapshot!(xml);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "metrics")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let metrics = ureq::get(format!("http://localhost:{}/_ovp/metrics", port).as_str())
+        .call()?
+        .into_string()?;
+    let metrics: serde_json::Value = serde_json::from_str(&metrics)?;
+    mock.assert();
+
+    insta::assert_snapshot!(serde_json::to_string_pretty(&metrics)?);
+    Ok(())
+}
+
+#[test]
+fn validation_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+    let disabled_port = free_port();
+    let _disabled_proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        disabled_port,
+        "tests/petstore.yaml",
+        &["--validation-cache-size", "0"],
+    );
+
+    for port in [port, port, disabled_port, disabled_port] {
+        ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+            .set("OVP-Correlation-Id", "validation_cache")
+            .call()?;
+    }
+    let metrics: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/metrics", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    assert_eq!(metrics["validationCacheHits"], 1);
+    assert_eq!(metrics["validationCacheMisses"], 1);
+    let disabled_metrics: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/metrics", disabled_port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    assert_eq!(disabled_metrics["validationCacheHits"], 0);
+    // The failures found the first time are reported again for the cached body
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    assert_eq!(junit.matches("<failure ").count(), 2);
+    mock.assert_hits(4);
+    Ok(())
+}
+
+#[test]
+fn suggestions() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "suggestions")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let suggestions = ureq::get(format!("http://localhost:{}/_ovp/suggestions", port).as_str())
+        .call()?
+        .into_string()?;
+    let suggestions: serde_json::Value = serde_json::from_str(&suggestions)?;
+    mock.assert();
+
+    insta::assert_snapshot!(serde_json::to_string_pretty(&suggestions)?);
+    Ok(())
+}
+
+#[test]
+fn operation_coverage() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let show_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for path in ["/pets", "/pets/1", "/pets/1"] {
+        ureq::get(format!("http://localhost:{}{}", port, path).as_str())
+            .set("OVP-Correlation-Id", "operation_coverage")
+            .call()?;
+    }
+    let coverage = ureq::get(format!("http://localhost:{}/_ovp/coverage", port).as_str())
+        .call()?
+        .into_string()?;
+    let coverage: serde_json::Value = serde_json::from_str(&coverage)?;
+    list_mock.assert_hits(1);
+    show_mock.assert_hits(2);
+
+    insta::assert_snapshot!(serde_json::to_string_pretty(&coverage)?);
+    Ok(())
+}
+
+#[test]
+fn query_testcases() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    let before = ureq::get(format!("http://localhost:{}/_ovp/testcases", port).as_str())
+        .call()?
+        .into_string()?;
+    assert_eq!(before, "[]");
+    for (path, correlation_id) in [
+        ("/pets", "query_testcases-list"),
+        ("/pets/1", "query_testcases-show"),
+        ("/pets", "other-list"),
+    ] {
+        ureq::get(format!("http://localhost:{}{}", port, path).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()?;
+    }
+
+    let correlation_ids = |query: &str| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let testcases =
+            ureq::get(format!("http://localhost:{}/_ovp/testcases?{}", port, query).as_str())
+                .call()?
+                .into_string()?;
+        let testcases: Vec<serde_json::Value> = serde_json::from_str(&testcases)?;
+        Ok(testcases
+            .iter()
+            .map(|testcase| {
+                testcase["properties"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|property| property["name"] == "correlationId")
+                    .unwrap()["value"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect())
+    };
+    assert_eq!(
+        correlation_ids("")?,
+        ["query_testcases-list", "query_testcases-show", "other-list"]
+    );
+    assert_eq!(
+        correlation_ids("failuresOnly=true")?,
+        ["query_testcases-show"]
+    );
+    assert_eq!(
+        correlation_ids("failureType=FailedValidation.UnexpectedString")?,
+        ["query_testcases-show"]
+    );
+    assert!(correlation_ids("failureType=FailedValidation.MissingProperty")?.is_empty());
+    assert_eq!(
+        correlation_ids("operationId=listPets")?,
+        ["query_testcases-list", "other-list"]
+    );
+    assert_eq!(
+        correlation_ids("correlationIdPrefix=query_testcases&operationId=listPets")?,
+        ["query_testcases-list"]
+    );
+    assert_eq!(correlation_ids("since=2000-01-01T00:00:00Z")?.len(), 3);
+    assert!(correlation_ids("until=2000-01-01T00:00:00%2B01:00")?.is_empty());
+
+    let invalid =
+        ureq::get(format!("http://localhost:{}/_ovp/testcases?since=yesterday", port).as_str())
+            .call()
+            .or_any_status()?;
+    assert_eq!(invalid.status(), 400);
+    assert_eq!(
+        invalid.into_string()?,
+        "since is not an RFC 3339 date-time: yesterday"
+    );
+    Ok(())
+}
+
+#[test]
+fn testcases_by_correlation_id() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for correlation_id in ["correlated", "correlated", "correlated-other"] {
+        ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .call()?;
+    }
+    let testcases =
+        ureq::get(format!("http://localhost:{}/_ovp/testcases/correlated", port).as_str())
+            .call()?
+            .into_string()?;
+    let testcases: Vec<serde_json::Value> = serde_json::from_str(&testcases)?;
+    assert_eq!(testcases.len(), 2);
+    for testcase in &testcases {
+        assert_eq!(
+            testcase["failures"][0]["type"],
+            "FailedValidation.UnexpectedString"
+        );
+    }
+
+    let missing = ureq::get(format!("http://localhost:{}/_ovp/testcases/missing", port).as_str())
+        .call()
+        .or_any_status()?;
+    assert_eq!(missing.status(), 404);
+    Ok(())
+}
+
+#[test]
+fn enforce() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let show_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--enforce"],
+    );
+
+    let valid = ureq::get(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "enforce-valid")
+        .call()?;
+    assert_eq!(valid.status(), 200);
+    let rejected_request = ureq::get(format!("http://localhost:{}/pets?limit=1000", port).as_str())
+        .set("OVP-Correlation-Id", "enforce-request")
+        .call()
+        .or_any_status()?;
+    let rejected_response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "enforce-response")
+        .call()
+        .or_any_status()?;
+    list_mock.assert_hits(1);
+    show_mock.assert_hits(1);
+
+    let mut rejections = vec![];
+    for rejection in [rejected_request, rejected_response] {
+        assert_eq!(rejection.content_type(), "application/problem+json");
+        let status = rejection.status();
+        let correlation_id = rejection
+            .header("OVP-Correlation-Id")
+            .unwrap_or_default()
+            .to_string();
+        let problem: serde_json::Value = serde_json::from_str(&rejection.into_string()?)?;
+        rejections.push(format!(
+            "{} {}\n{}",
+            status,
+            correlation_id,
+            serde_json::to_string_pretty(&problem)?
+        ));
+    }
+    insta::assert_snapshot!(rejections.join("\n\n"));
+
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn enforce_request_body() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let create_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--enforce"],
+    );
+
+    let valid = ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "enforce-valid-body")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 1, "name": "dog"}"#)?;
+    assert_eq!(valid.status(), 201);
+    let rejected = ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "enforce-invalid-body")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": "1", "name": "dog"}"#)
+        .or_any_status()?;
+    // The invalid body never reaches the upstream
+    create_mock.assert_hits(1);
+
+    assert_eq!(rejected.status(), 400);
+    assert_eq!(rejected.content_type(), "application/problem+json");
+    let problem: serde_json::Value = serde_json::from_str(&rejected.into_string()?)?;
+    insta::assert_snapshot!(serde_json::to_string_pretty(&problem)?);
+    Ok(())
+}
+
+#[test]
+fn enforce_header() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let show_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    let passed_through = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "enforce_header")
+        .call()?;
+    assert_eq!(passed_through.status(), 200);
+    let rejected_response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "enforce_header")
+        .set("OVP-Enforce", "true")
+        .call()
+        .or_any_status()?;
+    assert_eq!(rejected_response.status(), 502);
+    let rejected_request = ureq::get(format!("http://localhost:{}/pets?limit=1000", port).as_str())
+        .set("OVP-Correlation-Id", "enforce_header")
+        .set("OVP-Enforce", "true")
+        .call()
+        .or_any_status()?;
+    assert_eq!(rejected_request.status(), 400);
+    assert_eq!(rejected_request.content_type(), "application/problem+json");
+    let not_requested = ureq::get(format!("http://localhost:{}/pets?limit=1000", port).as_str())
+        .set("OVP-Correlation-Id", "enforce_header")
+        .set("OVP-Enforce", "false")
+        .call()?;
+    assert_eq!(not_requested.status(), 200);
+    list_mock.assert_hits(1);
+    show_mock.assert_hits(2);
+    Ok(())
+}
+
+#[test]
+fn unmatched_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let health_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/health");
+        then.status(200).body("ok");
+    });
+
+    let mut reports = vec![];
+    for mode in ["skip", "forward"] {
+        let port = free_port();
+        let _proxy_handle = ValidatorProxyServerHandle::with_args(
+            &mock_server.url(""),
+            port,
+            "tests/petstore.yaml",
+            &["--unmatched-paths", mode],
+        );
+        let health = ureq::get(format!("http://localhost:{}/health", port).as_str())
+            .set("OVP-Correlation-Id", "unmatched_paths")
+            .call()?
+            .into_string()?;
+        assert_eq!(health, "ok");
+        reports.push(
+            ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+                .call()?
+                .into_string()?,
+        );
+    }
+    health_mock.assert_hits(2);
+
+    assert_junit_snapshot!(reports.join("\n"));
+    Ok(())
+}
+
+#[test]
+fn suppressed_failures() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": false}));
+    });
+    let config = std::env::temp_dir().join(format!(
+        "ovp-suppressed-failures-{}.yaml",
+        std::process::id()
+    ));
+    std::fs::write(
+        &config,
+        "suppressedFailures:\n  - FailedValidation.UnexpectedString\n",
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "suppressed_failures")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn captured_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .header("Server", "petstore/1.2")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let config =
+        std::env::temp_dir().join(format!("ovp-captured-headers-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        "capturedHeaders:\n  request: [X-Request-Id, X-Missing]\n  response: [Server, Content-Length]\n",
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "captured_headers")
+        .set("X-Request-Id", "3f2c9a")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn upstream_correlation_id() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .header("X-Request-Id", "upstream-42")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let config = std::env::temp_dir().join(format!(
+        "ovp-upstream-correlation-id-{}.yaml",
+        std::process::id()
+    ));
+    std::fs::write(&config, "headers:\n  upstreamCorrelationId: X-Request-Id\n")?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str()).call()?;
+    assert_eq!(response.header("OVP-Correlation-Id"), Some("upstream-42"));
+    // A correlation ID sent by the client is kept
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "upstream_correlation_id")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert_hits(2);
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn correlation_id_parameter() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets")
+            .query_param("limit", "10")
+            .matches(|request| {
+                !request
+                    .query_params
+                    .as_ref()
+                    .is_some_and(|params| params.iter().any(|(name, _)| name == "ovp_cid"))
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let config = std::env::temp_dir().join(format!(
+        "ovp-correlation-id-parameter-{}.yaml",
+        std::process::id()
+    ));
+    std::fs::write(&config, "headers:\n  correlationIdParameter: ovp_cid\n")?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    let response = ureq::get(
+        format!(
+            "http://localhost:{}/pets?limit=10&ovp_cid=browser%20run",
+            port
+        )
+        .as_str(),
+    )
+    .call()?;
+    assert_eq!(response.header("OVP-Correlation-Id"), Some("browser run"));
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn skip_validation_header() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "skip_validation_header")
+        .set("OVP-Skip-Validation", "true")
+        .call()?;
+    assert_eq!(response.status(), 200);
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    // Harness requests don't count towards coverage
+    let coverage: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/coverage", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    assert_eq!(coverage["covered"], serde_json::json!([]));
+    Ok(())
+}
+
+#[test]
+fn warning_severity() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let config =
+        std::env::temp_dir().join(format!("ovp-warning-severity-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        "severities:\n  FailedValidation.UnexpectedString: warning\n",
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "warning_severity")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn upstream_routes() -> Result<(), Box<dyn std::error::Error>> {
+    let default_server = MockServer::start();
+    let default_mock = default_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/missing_pets_schema");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1}));
+    });
+    let pets_server = MockServer::start();
+    let pets_mock = pets_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/inventory/pets/1")
+            .query_param("fields", "name")
+            .header("Host", pets_server.address().to_string());
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let config =
+        std::env::temp_dir().join(format!("ovp-upstream-routes-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!(
+            "upstreams:\n  - prefix: /pets/\n    url: {}\n",
+            pets_server.url("/inventory/pets")
+        ),
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &default_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1?fields=name", port).as_str())
+        .set("OVP-Correlation-Id", "routed")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/missing_pets_schema", port).as_str())
+        .set("OVP-Correlation-Id", "default")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    default_mock.assert();
+    pets_mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn spec_from_url() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/abc");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let spec_server = MockServer::start();
+    let mut spec_mock = spec_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/openapi.yaml");
+        then.status(200).body_from_file("tests/petstore.yaml");
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        &spec_server.url("/openapi.yaml"),
+        &["--spec-refresh-interval", "1"],
+    );
+
+    // petId is a string in the first version of the spec and an integer in the second
+    ureq::get(format!("http://localhost:{}/pets/abc", port).as_str())
+        .set("OVP-Correlation-Id", "before_refresh")
+        .call()?;
+    spec_mock.delete();
+    spec_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/openapi.yaml");
+        then.status(200)
+            .body_from_file("tests/petstore_breaking.yaml");
+    });
+    // The second version doesn't have the deletePet operation
+    let started = std::time::Instant::now();
+    while ureq::get(format!("http://localhost:{}/_ovp/coverage", port).as_str())
+        .call()?
+        .into_string()?
+        .contains("deletePet")
+    {
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(30),
+            "The spec was not refreshed"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    ureq::get(format!("http://localhost:{}/pets/abc", port).as_str())
+        .set("OVP-Correlation-Id", "after_refresh")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert_hits(2);
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn servers_base_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    // The servers of the spec are under /v1
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+    let base_path_port = free_port();
+    let _base_path_proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        base_path_port,
+        "tests/petstore.yaml",
+        &["--base-path", "/gateway"],
+    );
+
+    ureq::get(format!("http://localhost:{}/v1/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "servers_base_path")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/gateway/pets/1", base_path_port).as_str())
+        .set("OVP-Correlation-Id", "base_path_argument")
+        .call()?;
+    // The argument replaces the paths of the servers
+    ureq::get(format!("http://localhost:{}/v1/pets/1", base_path_port).as_str())
+        .set("OVP-Correlation-Id", "servers_base_path_replaced")
+        .call()?;
+    let mut xml = String::new();
+    for port in [port, base_path_port] {
+        let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+        xml.push_str(&junit.into_string()?);
+    }
+    mock.assert_hits(3);
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn upstream_tls() -> Result<(), Box<dyn std::error::Error>> {
+    let upstream = format!(
+        "https://localhost:{}",
+        tls_upstream(r#"{"id": 1, "name": "dog"}"#)
+    );
+
+    let mut statuses = vec![];
+    for args in [
+        &[][..],
+        &["--ca-bundle", "tests/tls/ca.pem"][..],
+        &["--insecure-skip-verify"][..],
+    ] {
+        let port = free_port();
+        let _proxy_handle =
+            ValidatorProxyServerHandle::with_args(&upstream, port, "tests/petstore.yaml", args);
+        let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+            .set("OVP-Correlation-Id", "upstream_tls")
+            .call()
+            .or_any_status()?;
+        statuses.push(response.status());
+    }
+
+    // The test CA is only trusted when it is given with --ca-bundle
+    assert_eq!(statuses, [502, 200, 200]);
+    Ok(())
+}
+
+#[test]
+fn invalid_ca_bundle() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "proxy",
+            "tests/petstore.yaml",
+            "https://localhost:1",
+            "--ca-bundle",
+            "tests/petstore.yaml",
+        ])
+    );
+}
+
+#[test]
+fn body_too_large_to_validate() -> Result<(), Box<dyn std::error::Error>> {
+    // Both bodies are larger than the limits
+    let padding = "x".repeat(2048);
+    let request_body = format!(r#"{{"id": 1, "name": "dog", "padding": "{}"}}"#, padding);
+    // The extra property would fail validation if the body was validated
+    let response_body = format!(r#"[{{"id": 1, "name": "dog", "padding": "{}"}}]"#, padding);
+    let mock_server = MockServer::start();
+    let create_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/pets")
+            .body(request_body.clone());
+        then.status(201);
+    });
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(response_body.clone());
+    });
+    let port = free_port();
+    // Bodies that are too large to validate are forwarded even when enforcing
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--max-request-body",
+            "1024",
+            "--max-response-body",
+            "1024",
+            "--enforce",
+        ],
+    );
+
+    let response = ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "request_body_too_large")
+        .set("Content-Type", "application/json")
+        .send_string(&request_body)
+        .or_any_status()
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 201);
+    let response = ureq::get(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "response_body_too_large")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+    let mut received = vec![];
+    response.into_reader().read_to_end(&mut received)?;
+    assert!(received == response_body.as_bytes());
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    create_mock.assert();
+    list_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn compressed_responses() -> Result<(), Box<dyn std::error::Error>> {
+    // The extra property is only found if the body is decompressed before it is validated
+    let body = r#"{"id": 1, "name": "dog", "extra": true}"#;
+    let mut gzip = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gzip.write_all(body.as_bytes())?;
+    let gzip = gzip.finish()?;
+    let mut br = vec![];
+    brotli::CompressorWriter::new(&mut br, 4096, 5, 22).write_all(body.as_bytes())?;
+    let mock_server = MockServer::start();
+    let bodies = [
+        ("1", "gzip", gzip),
+        ("2", "br", br),
+        ("3", "gzip", body.as_bytes().to_vec()),
+    ];
+    for (id, encoding, body) in bodies.iter() {
+        mock_server.mock(|when, then| {
+            when.method(httpmock::Method::GET)
+                .path(format!("/pets/{}", id));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .header("Content-Encoding", *encoding)
+                .body(body);
+        });
+    }
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    for (id, encoding, body) in bodies.iter() {
+        let response = ureq::get(format!("http://localhost:{}/pets/{}", port, id).as_str())
+            .set(
+                "OVP-Correlation-Id",
+                &format!("compressed_responses_{}", id),
+            )
+            .call()
+            .or_any_status()
+            .expect("Failed to make request");
+        // The client receives the body as the upstream encoded it. ureq decompresses gzip on its
+        // own, so only the brotli body can be compared
+        if *encoding == "br" {
+            assert_eq!(response.header("Content-Encoding"), Some("br"));
+            let mut received = vec![];
+            response.into_reader().read_to_end(&mut received)?;
+            assert!(received == *body);
+        }
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn chunked_response_passthrough() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::BufRead;
+
+    let first_chunk = r#"[{"id": 1, "name": "dog"},"#;
+    let second_chunk = r#"{"id": 2, "name": "cat", "extra": true}]"#;
+    // The upstream only sends the second chunk once the client has received the first one, or
+    // after a timeout if the proxy is holding the first chunk back
+    let (received_sender, received) = std::sync::mpsc::channel::<()>();
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))?;
+    let upstream_port = listener.local_addr()?.port();
+    let upstream = std::thread::spawn(move || -> bool {
+        let (stream, _) = listener.accept().unwrap();
+        let mut stream = std::io::BufReader::new(stream);
+        let mut line = String::new();
+        while stream.read_line(&mut line).is_ok_and(|read| read > 2) {
+            line.clear();
+        }
+        let stream = stream.get_mut();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n",
+            first_chunk.len(),
+            first_chunk
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        let passed_through = received
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .is_ok();
+        write!(
+            stream,
+            "{:x}\r\n{}\r\n0\r\n\r\n",
+            second_chunk.len(),
+            second_chunk
+        )
+        .unwrap();
+        stream.flush().unwrap();
+        passed_through
+    });
+    let port = free_port();
+    let _proxy_handle =
+        ValidatorProxyServerHandle::new(&format!("http://localhost:{}", upstream_port), port);
+
+    let response = ureq::get(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "chunked_response_passthrough")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let mut reader = response.into_reader();
+    let mut received_first_chunk = vec![0; first_chunk.len()];
+    reader.read_exact(&mut received_first_chunk)?;
+    received_sender.send(())?;
+    let mut received_second_chunk = String::new();
+    reader.read_to_string(&mut received_second_chunk)?;
+    assert!(upstream.join().unwrap());
+    assert_eq!(received_first_chunk, first_chunk.as_bytes());
+    assert_eq!(received_second_chunk, second_chunk);
+    // The whole response is validated once it has been passed through
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn chunked_request() -> Result<(), Box<dyn std::error::Error>> {
+    let body = r#"{"id": 1, "name": "dog"}"#;
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/pets")
+            .header("Transfer-Encoding", "chunked")
+            .body(body);
+        then.status(201);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    // ureq sends bodies from readers with chunked transfer encoding
+    let response = ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "chunked_request")
+        .set("Content-Type", "application/json")
+        .send(body.as_bytes())
+        .or_any_status()
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 201);
+    mock.assert();
+    Ok(())
+}
+
+#[test]
+fn http2() -> Result<(), Box<dyn std::error::Error>> {
+    let body = r#"{"id": 1, "name": "dog"}"#;
+    // The upstreams reject HTTP/1.1, so the requests only succeed if the proxy uses HTTP/2
+    let h2c_port = free_port();
+    let _h2c_proxy_handle = ValidatorProxyServerHandle::with_args(
+        &format!("http://localhost:{}", h2_upstream(body, false)),
+        h2c_port,
+        "tests/petstore.yaml",
+        &["--http2-prior-knowledge"],
+    );
+    let h2_port = free_port();
+    let _h2_proxy_handle = ValidatorProxyServerHandle::with_args(
+        &format!("https://localhost:{}", h2_upstream(body, true)),
+        h2_port,
+        "tests/petstore.yaml",
+        &["--ca-bundle", "tests/tls/ca.pem"],
+    );
+
+    // Clients can use HTTP/2 with prior knowledge too
+    let runtime = tokio::runtime::Runtime::new()?;
+    let (status, version) = runtime.block_on(async {
+        let client =
+            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
+                .http2_only(true)
+                .build_http::<http_body_util::Empty<hyper::body::Bytes>>();
+        let request = hyper::Request::get(format!("http://localhost:{}/pets/1", h2c_port))
+            .header("OVP-Correlation-Id", "http2_h2c")
+            .body(http_body_util::Empty::new())?;
+        let response = client.request(request).await?;
+        Ok::<_, Box<dyn std::error::Error>>((response.status(), response.version()))
+    })?;
+    assert_eq!(status, 200);
+    assert_eq!(version, hyper::Version::HTTP_2);
+    let response = ureq::get(format!("http://localhost:{}/pets/1", h2_port).as_str())
+        .set("OVP-Correlation-Id", "http2_alpn")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+    Ok(())
+}
+
+#[test]
+fn concurrency_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}))
+            .delay(std::time::Duration::from_millis(1500));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--max-concurrency",
+            "1",
+            "--max-queue",
+            "0",
+            "--retry-after",
+            "5",
+        ],
+    );
+
+    let slow_request = std::thread::spawn(move || {
+        ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+            .set("OVP-Correlation-Id", "concurrency_limit")
+            .call()
+            .or_any_status()
+            .expect("Failed to make request")
+            .status()
+    });
+    // Give the first request time to take the only slot
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let rejected = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "concurrency_limit")
+        .call()
+        .or_any_status()?;
+    assert_eq!(rejected.status(), 503);
+    assert_eq!(rejected.header("Retry-After"), Some("5"));
+    assert_eq!(slow_request.join().unwrap(), 200);
+
+    let metrics = ureq::get(format!("http://localhost:{}/_ovp/metrics", port).as_str())
+        .call()?
+        .into_string()?;
+    let metrics: serde_json::Value = serde_json::from_str(&metrics)?;
+    mock.assert_hits(1);
+
+    assert_eq!(metrics["rejectedRequests"], 1);
+    Ok(())
+}
+
+#[test]
+fn disabled_validation_family() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/any_of_pet_schema");
+        then.status(600).body("Server Error");
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::put(format!("http://localhost:{}/_ovp/validation", port).as_str())
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"statusCodes": false}"#)?;
+    ureq::get(format!("http://localhost:{}/any_of_pet_schema", port).as_str())
+        .set("OVP-Correlation-Id", "disabled_validation_family")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn request_body_validation_family() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/webhooks.yaml",
+        &[],
+    );
+
+    let mut failure_types = vec![];
+    for (correlation_id, toggles) in [
+        ("response_body_disabled", r#"{"responseBody": false}"#),
+        ("request_body_disabled", r#"{"requestBody": false}"#),
+    ] {
+        ureq::put(format!("http://localhost:{}/_ovp/validation", port).as_str())
+            .set("Content-Type", "application/json")
+            .send_string(toggles)?;
+        ureq::post(format!("http://localhost:{}/_ovp/webhook/newPet", port).as_str())
+            .set("OVP-Correlation-Id", correlation_id)
+            .set("Content-Type", "application/json")
+            .send_string(r#"{"id": 1, "name": false}"#)
+            .or_any_status()
+            .expect("Failed to make request");
+        let testcases: serde_json::Value = serde_json::from_str(
+            &ureq::get(
+                format!(
+                    "http://localhost:{}/_ovp/testcases/{}",
+                    port, correlation_id
+                )
+                .as_str(),
+            )
+            .call()?
+            .into_string()?,
+        )?;
+        failure_types.push(
+            testcases[0]["failures"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|failure| failure["type"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    // The webhook's body is a request, so it is only silenced by the request body family
+    assert_eq!(
+        failure_types,
+        [
+            vec![
+                "MissingRequiredHeader",
+                "FailedValidation.UnexpectedBoolean"
+            ],
+            vec!["MissingRequiredHeader"]
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn callback() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "callback")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 1, "name": "dog"}"#)
+        .or_any_status()
+        .expect("Failed to make request");
+    // This simulates the upstream sending the callback for the request above to the proxy
+    ureq::post(format!("http://localhost:{}/_ovp/callback/createPets", port).as_str())
+        .set("OVP-Correlation-Id", "callback")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": "1", "name": "dog"}"#)
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn webhook() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let consumer = MockServer::start();
+    let consumer_mock = consumer.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/hooks/new-pet");
+        then.status(200);
+    });
+    let config = std::env::temp_dir().join(format!("ovp-webhook-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!("webhooks:\n  newPet: {}\n", consumer.url("/hooks/new-pet")),
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/webhooks.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::post(format!("http://localhost:{}/_ovp/webhook/newPet", port).as_str())
+        .set("OVP-Correlation-Id", "webhook")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 1, "name": false}"#)
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    consumer_mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn webhook_body_too_large() -> Result<(), Box<dyn std::error::Error>> {
+    // The name would fail validation if the body was validated
+    let padding = "x".repeat(2048);
+    let request_body = format!(r#"{{"id": 1, "name": false, "padding": "{}"}}"#, padding);
+    let consumer_body = format!(r#"{{"received": "{}"}}"#, padding);
+    let mock_server = MockServer::start();
+    let consumer = MockServer::start();
+    let consumer_mock = consumer.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/hooks/new-pet")
+            .body(request_body.clone());
+        then.status(200).body(consumer_body.clone());
+    });
+    let config =
+        std::env::temp_dir().join(format!("ovp-webhook-too-large-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        format!("webhooks:\n  newPet: {}\n", consumer.url("/hooks/new-pet")),
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/webhooks.yaml",
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "--max-request-body",
+            "1024",
+            "--max-response-body",
+            "1024",
+        ],
+    );
+
+    // Both bodies are streamed through the proxy
+    let response = ureq::post(format!("http://localhost:{}/_ovp/webhook/newPet", port).as_str())
+        .set("OVP-Correlation-Id", "webhook_body_too_large")
+        .set("Content-Type", "application/json")
+        .send_string(&request_body)
+        .or_any_status()
+        .expect("Failed to make request");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.into_string()?, consumer_body);
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    consumer_mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn link_parameter_mismatch() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let get_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let delete_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::DELETE);
+        then.status(204);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "link_parameter_mismatch")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    // This request follows the deletePet link from the response above
+    ureq::delete(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "link_parameter_mismatch")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    // This request uses a different petId than the link specified
+    ureq::delete(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "link_parameter_mismatch")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    get_mock.assert();
+    delete_mock.assert_hits(2);
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn sequence_violation() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let delete_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::DELETE).path("/pets/1");
+        then.status(204);
+    });
+    let get_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let config = std::env::temp_dir().join(format!("ovp-sequence-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"
+sequences:
+  - name: deleted-pet-is-gone
+    after: deletePet
+    then: showPetById
+    expectStatus: [404]
+    parameters:
+      petId: $request.path.petId
+"#,
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::delete(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "sequence_violation")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    // The pet was deleted so this request should have returned a 404
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "sequence_violation")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    // This pet was not deleted so the sequence does not apply
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "sequence_violation")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    delete_mock.assert();
+    get_mock.assert_hits(2);
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn golden_response_drift() -> Result<(), Box<dyn std::error::Error>> {
+    let golden_dir = std::env::temp_dir().join(format!("ovp-golden-{}", std::process::id()));
+
+    // Record the shape of a response
+    let record_server = MockServer::start();
+    let record_mock = record_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog", "tag": "good"}));
+    });
+    let port = free_port();
+    let record_handle = ValidatorProxyServerHandle::with_args(
+        &record_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--golden-dir",
+            golden_dir.to_str().unwrap(),
+            "--golden-mode",
+            "record",
+        ],
+    );
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "golden_response_drift")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    record_mock.assert();
+    drop(record_handle);
+
+    // The response is still valid according to the spec, but the tag disappeared
+    let verify_server = MockServer::start();
+    let verify_mock = verify_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _verify_handle = ValidatorProxyServerHandle::with_args(
+        &verify_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--golden-dir",
+            golden_dir.to_str().unwrap(),
+            "--golden-mode",
+            "verify",
+        ],
+    );
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "golden_response_drift")
+        .call()
+        .or_any_status()
+        .expect("Failed to make request");
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    verify_mock.assert();
+    std::fs::remove_dir_all(&golden_dir)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn resolve_override() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    // petstore.internal doesn't exist, so the request only succeeds if the override is used
+    let upstream = format!("http://petstore.internal:{}", mock_server.port());
+    let resolve = format!("petstore.internal:{}=127.0.0.1", mock_server.port());
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &upstream,
+        port,
+        "tests/petstore.yaml",
+        &["--resolve", &resolve],
+    );
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "resolve_override")
+        .call()
+        .or_any_status()?;
+    mock.assert();
+
+    assert_eq!(response.status(), 200);
+    Ok(())
+}
+
+#[test]
+fn multi_value_headers() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/1")
+            // Repeated headers are forwarded as they were sent, because values like dates can
+            // contain commas
+            .header("x-tag", "small")
+            .header("x-tag", "fluffy")
+            .header("x-seen-at", "Wed, 21 Oct 2015 07:28:00 GMT")
+            .header("x-seen-at", "Thu, 22 Oct 2015 08:00:00 GMT");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .header("Set-Cookie", "session=abc")
+            .header("Set-Cookie", "theme=dark")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "multi_value_headers")
+        .set("X-Tag", "small")
+        .set("X-Tag", "fluffy")
+        .set("X-Seen-At", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .set("X-Seen-At", "Thu, 22 Oct 2015 08:00:00 GMT")
+        .call()
+        .or_any_status()?;
+    mock.assert();
+
+    assert_eq!(
+        response.all("set-cookie"),
+        vec!["session=abc", "theme=dark"]
+    );
+    Ok(())
+}
+
+#[test]
+fn multiple_content_types() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .header("Content-Type", "text/plain")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "multiple_content_types")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn malformed_header() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    // ureq only sends UTF-8 headers so the request has to be written by hand
+    let mut stream = std::net::TcpStream::connect(("localhost", port))?;
+    stream.write_all(b"GET /pets/1 HTTP/1.1\r\nHost: localhost\r\nOVP-Correlation-Id: malformed_header\r\nX-Pet-Name: caf\xe9\r\nConnection: close\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200"));
+
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn request_body_read_error() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{Read, Write};
+
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    // The client promises a longer body than it sends before it disconnects
+    let mut stream = std::net::TcpStream::connect(("localhost", port))?;
+    stream.write_all(b"POST /pets HTTP/1.1\r\nHost: localhost\r\nOVP-Correlation-Id: request_body_read_error\r\nContent-Type: application/json\r\nContent-Length: 100\r\n\r\n{\"id\": 1")?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+
+    let url = format!(
+        "http://localhost:{}/_ovp/testcases/request_body_read_error",
+        port
+    );
+    let mut attempts = 0;
+    let testcases: serde_json::Value = loop {
+        let response = ureq::get(&url).call().or_any_status()?;
+        if response.status() == 200 || attempts == 50 {
+            break serde_json::from_str(&response.into_string()?)?;
+        }
+        attempts += 1;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    };
+    mock.assert_hits(0);
+
+    let failure = &testcases[0]["failures"][0];
+    assert_eq!(failure["type"], "InternalProxyError");
+    assert!(failure["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to read the request body: "));
+    Ok(())
+}
+
+#[test]
+fn upstream_read_error() -> Result<(), Box<dyn std::error::Error>> {
+    let length_port = truncating_upstream(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 100",
+        r#"{"id": 1"#,
+    );
+    let chunked_port = truncating_upstream(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked",
+        "8\r\n{\"id\": 1\r\n",
+    );
+
+    let port = free_port();
+    let _proxy_handle =
+        ValidatorProxyServerHandle::new(&format!("http://127.0.0.1:{}", length_port), port);
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "upstream_read_error")
+        .call()
+        .or_any_status()?;
+    assert_eq!(response.status(), 502);
+    let testcases: serde_json::Value = serde_json::from_str(
+        &ureq::get(
+            format!(
+                "http://localhost:{}/_ovp/testcases/upstream_read_error",
+                port
+            )
+            .as_str(),
+        )
+        .call()?
+        .into_string()?,
+    )?;
+    let failure = &testcases[0]["failures"][0];
+    assert_eq!(failure["type"], "InternalProxyError");
+    assert!(failure["text"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to read the upstream response: "));
+
+    // A chunked response has already been passed to the client when it ends early
+    let port = free_port();
+    let _proxy_handle =
+        ValidatorProxyServerHandle::new(&format!("http://127.0.0.1:{}", chunked_port), port);
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "upstream_chunked_read_error")
+        .call()
+        .or_any_status()?;
+    assert_eq!(response.status(), 200);
+    assert!(response.into_string().is_err());
+    let url = format!(
+        "http://localhost:{}/_ovp/testcases/upstream_chunked_read_error",
+        port
+    );
+    let mut attempts = 0;
+    let testcases: serde_json::Value = loop {
+        let response = ureq::get(&url).call().or_any_status()?;
+        if response.status() == 200 || attempts == 50 {
+            break serde_json::from_str(&response.into_string()?)?;
+        }
+        attempts += 1;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    };
+    let failure = &testcases[0]["failures"][0];
+    assert_eq!(failure["type"], "InternalProxyError");
+    assert_eq!(
+        failure["text"],
+        "The upstream response ended early or the client stopped reading it"
+    );
+    Ok(())
+}
+
+#[test]
+fn schema_failure_locations() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/things/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"name": 5, "size": 0, "tags": ["a", "b"], "width/height": "wide", "owner": {}, "color": "red"}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/locations.yaml",
+        &[],
+    );
+
+    ureq::get(format!("http://localhost:{}/things/1", port).as_str())
+        .set("OVP-Correlation-Id", "schema_failure_locations")
+        .call()?;
+    let testcases: serde_json::Value = serde_json::from_str(
+        &ureq::get(
+            format!(
+                "http://localhost:{}/_ovp/testcases/schema_failure_locations",
+                port
+            )
+            .as_str(),
+        )
+        .call()?
+        .into_string()?,
+    )?;
+    mock.assert();
+
+    // Every schema failure shows where it is in the body, including keys that have to be escaped
+    let texts = testcases[0]["failures"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|failure| failure["text"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(texts.len(), 6);
+    for text in texts.iter() {
+        assert!(text.lines().last().unwrap().trim_start().starts_with('^'));
+    }
+    insta::assert_snapshot!(texts.join("\n\n"));
+    Ok(())
+}
+
+#[test]
+fn charset_decoding() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let utf16_body = r#"{"id": 1, "name": "café"}"#
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect::<Vec<u8>>();
+    let utf16_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json; charset=utf-16le")
+            .body(utf16_body);
+    });
+    let unsupported_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json; charset=klingon")
+            .body(r#"{"id": 2, "name": "dog"}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "charset_decoding")
+        .call()
+        .or_any_status()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "charset_decoding")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    utf16_mock.assert();
+    unsupported_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn duplicate_json_keys() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"id": 1, "name": "dog", "name": "cat"}"#);
+    });
+    let config = std::env::temp_dir().join(format!("ovp-strict-json-{}.yaml", std::process::id()));
+    std::fs::write(&config, "strictJson: true\n")?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "duplicate_json_keys")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn int64_precision_loss() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"id": 9007199254740993, "name": "dog"}"#);
+    });
+    let config = std::env::temp_dir().join(format!("ovp-int64-{}.yaml", std::process::id()));
+    std::fs::write(&config, "int64PrecisionWarnings: true\n")?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "int64_precision_loss")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn date_time_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!([
+                {"id": 1, "name": "dog", "adoptedAt": "2024-01-31 12:00:00"},
+                {"id": 2, "name": "cat", "birthday": "2024-02-30"}
+            ]));
+    });
+    let show_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(
+                {"id": 1, "name": "dog", "birthday": "2020-02-29", "adoptedAt": "2024-01-31 12:00:00"}
+            ));
+    });
+    let config = std::env::temp_dir().join(format!("ovp-date-time-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"
+dateTimeFormat: strict
+operations:
+  listPets:
+    dateTimeFormat: lenient
+"#,
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    // The legacy timestamp is tolerated for listPets but not for showPetById
+    ureq::get(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "date_time_formats")
+        .call()
+        .or_any_status()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "date_time_formats")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    list_mock.assert();
+    show_mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn custom_header_prefix() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/1")
+            .header("x-request-id", "custom_header_prefix")
+            .matches(|request| {
+                // The proxy's headers are stripped before the request is forwarded
+                request
+                    .headers
+                    .iter()
+                    .flatten()
+                    .all(|(name, _)| !name.to_lowercase().starts_with("x-acme-qa-"))
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let config = std::env::temp_dir().join(format!("ovp-headers-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"
+headers:
+  prefix: X-Acme-QA-
+  stripUpstream: true
+"#,
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("X-Acme-QA-Correlation-Id", "custom_header_prefix")
+        .set("X-Acme-QA-Fused-Correlation-Headers", "x-request-id")
+        .call()
+        .or_any_status()?;
+    assert_eq!(
+        response.header("X-Acme-QA-Correlation-Id"),
+        Some("custom_header_prefix")
+    );
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn header_prefix_flags() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/1")
+            .matches(|request| {
+                request
+                    .headers
+                    .iter()
+                    .flatten()
+                    .all(|(name, _)| !name.to_lowercase().starts_with("x-staging-qa-"))
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--header-prefix", "X-Staging-QA-", "--strip-proxy-headers"],
+    );
+
+    let response = ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("X-Staging-QA-Correlation-Id", "header_prefix_flags")
+        .call()?;
+    assert_eq!(
+        response.header("X-Staging-QA-Correlation-Id"),
+        Some("header_prefix_flags")
+    );
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn trace_context() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let propagated_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/1")
+            .header(
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            )
+            .header("tracestate", "congo=t61rcWkgMzE");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let started_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/2")
+            .header_exists("traceparent")
+            .matches(|request| {
+                // A tracestate is only forwarded with the traceparent it belongs to
+                request
+                    .headers
+                    .iter()
+                    .flatten()
+                    .all(|(name, _)| !name.eq_ignore_ascii_case("tracestate"))
+            });
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 2, "name": "cat"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--trace-context"],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "trace_context")
+        .set(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        )
+        .set("tracestate", "congo=t61rcWkgMzE")
+        .call()?;
+    // The trace ID of a new trace is the correlation ID when that is a UUID
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "4bf92f35-77b3-4da6-a3ce-929d0e0e4736")
+        .set("traceparent", "not-a-traceparent")
+        .set("tracestate", "congo=t61rcWkgMzE")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    propagated_mock.assert();
+    started_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn custom_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(
+                {"id": 1, "name": "dog", "microchipId": "0ujtsYcgvSTl8PAuAdqWYSMnLOv"}
+            ));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 2, "name": "cat", "microchipId": "not-a-ksuid"}));
+    });
+    let config = std::env::temp_dir().join(format!("ovp-formats-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        r#"
+formats:
+  ksuid:
+    builtin: ksuid
+"#,
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--config", config.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "custom_formats")
+        .call()
+        .or_any_status()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "custom_formats")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+    std::fs::remove_file(config)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn standard_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/contacts/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({
+                "id": "4f8e2a9c-1b3d-4e5f-8a7b-9c0d1e2f3a4b",
+                "email": "rex@example.com",
+                "website": "https://example.com/rex",
+                "hostname": "api.example.com",
+                "ipv4": "192.168.0.1",
+                "ipv6": "2001:db8::1"
+            }));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/contacts/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({
+                "id": "4f8e2a9c",
+                "email": "rex.example.com",
+                "website": "/rex",
+                "hostname": "-api.example.com",
+                "ipv4": "192.168.0.256",
+                "ipv6": "2001:db8:::1"
+            }));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/formats.yaml",
+        &[],
+    );
+
+    for contact_id in 1..=2 {
+        ureq::get(format!("http://localhost:{}/contacts/{}", port, contact_id).as_str())
+            .set("OVP-Correlation-Id", "standard_formats")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn number_constraints() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(
+                {"quantity": 100, "packs": 12, "discount": 0.25, "price": 19.99}
+            ));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(
+                {"quantity": 0, "packs": 10, "discount": 1, "price": 0.005}
+            ));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/constraints.yaml",
+        &[],
+    );
+
+    for order_id in 1..=2 {
+        ureq::get(format!("http://localhost:{}/orders/{}", port, order_id).as_str())
+            .set("OVP-Correlation-Id", "number_constraints")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn integer_vs_number() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"quantity": 2.0, "packs": 6, "discount": 0, "price": 5}"#);
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"quantity": 1.5, "packs": 6e-1}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/constraints.yaml",
+        &[],
+    );
+
+    for order_id in 1..=2 {
+        ureq::get(format!("http://localhost:{}/orders/{}", port, order_id).as_str())
+            .set("OVP-Correlation-Id", "integer_vs_number")
+            .call()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn number_formats() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"views": 2147483647, "bytes": -9223372036854775808, "weight": 3.4e38}"#);
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .body(r#"{"views": 2147483648, "bytes": 9223372036854775808, "weight": 3.5e38}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/constraints.yaml",
+        &[],
+    );
+
+    for order_id in 1..=2 {
+        ureq::get(format!("http://localhost:{}/orders/{}", port, order_id).as_str())
+            .set("OVP-Correlation-Id", "number_formats")
+            .call()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn array_constraints() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!(
+                {"items": ["apple", "apple"], "coupons": ["SPRING", "WELCOME"]}
+            ));
+    });
+    let empty_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"items": []}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/orders/3");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({
+                "items": ["apple", "pear", "plum", "kiwi"],
+                "coupons": ["SPRING", "WELCOME", "SPRING"]
+            }));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/constraints.yaml",
+        &[],
+    );
+
+    for order_id in 1..=3 {
+        ureq::get(format!("http://localhost:{}/orders/{}", port, order_id).as_str())
+            .set("OVP-Correlation-Id", "array_constraints")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    valid_mock.assert();
+    empty_mock.assert();
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn parameter_styles() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(204);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/parameters.yaml",
+        &[],
+    );
+
+    ureq::get(
+        format!(
+            "http://localhost:{}/items/.1,2?tags=a,b&limit=10&filter[role]=admin&filter[age]=30&sizes=1|2",
+            port
+        )
+        .as_str(),
+    )
+    .set("OVP-Correlation-Id", "parameter_styles_valid")
+    .set("X-Rate", "5")
+    .set("X-Flags", "true, false")
+    .call()?;
+    ureq::get(
+        format!(
+            "http://localhost:{}/items/.1,x?limit=ten&filter[age]=old&sizes=1|big",
+            port
+        )
+        .as_str(),
+    )
+    .set("OVP-Correlation-Id", "parameter_styles_invalid")
+    .set("X-Flags", "yes")
+    .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert_hits(2);
+
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn missing_security_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(204);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/security.yaml",
+        &[],
+    );
+
+    let send = |path: &str, correlation_id: &str, headers: &[(&str, &str)]| {
+        let mut request = ureq::get(format!("http://localhost:{}{}", port, path).as_str())
+            .set("OVP-Correlation-Id", correlation_id);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        request.call().map_err(Box::new)
+    };
+    send("/pets", "bearer", &[("Authorization", "Bearer token")])?;
+    send(
+        "/pets",
+        "missing_bearer",
+        &[("Authorization", "Basic dXNlcjpwYXNz")],
+    )?;
+    send("/admin?api_key=secret", "api_key", &[])?;
+    send(
+        "/admin",
+        "basic_and_session",
+        &[
+            ("Authorization", "Basic dXNlcjpwYXNz"),
+            ("Cookie", "theme=dark; SESSION=abc"),
+        ],
+    )?;
+    send(
+        "/admin",
+        "missing_session",
+        &[("Authorization", "Basic dXNlcjpwYXNz")],
+    )?;
+    send("/health", "no_security", &[])?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert_hits(6);
+
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn one_of_best_match() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let no_match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"name": "rex", "barks": "loudly", "wags": true}));
+    });
+    let multiple_match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"name": "rex"}));
+    });
+    let match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/3");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"name": "tom", "meows": true}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/composition.yaml",
+        &[],
+    );
+
+    for pet_id in 1..=3 {
+        ureq::get(format!("http://localhost:{}/pets/{}", port, pet_id).as_str())
+            .set("OVP-Correlation-Id", "one_of_best_match")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    no_match_mock.assert();
+    multiple_match_mock.assert();
+    match_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn not_schema() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1/nickname");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"nickname": 7}));
+    });
+    let no_match_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2/nickname");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"nickname": "rex"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/composition.yaml",
+        &[],
+    );
+
+    for pet_id in 1..=2 {
+        ureq::get(format!("http://localhost:{}/pets/{}/nickname", port, pet_id).as_str())
+            .set("OVP-Correlation-Id", "not_schema")
+            .call()
+            .or_any_status()?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    match_mock.assert();
+    no_match_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn runs() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::post(format!("http://localhost:{}/_ovp/runs/smoke/start", port).as_str()).call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "runs")
+        .call()?;
+    // Starting another run stops the active one
+    ureq::post(format!("http://localhost:{}/_ovp/runs/regression/start", port).as_str()).call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "runs")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/3", port).as_str())
+        .set("OVP-Correlation-Id", "runs")
+        .call()?;
+    ureq::post(format!("http://localhost:{}/_ovp/runs/regression/stop", port).as_str()).call()?;
+    // This request is not part of any run
+    ureq::get(format!("http://localhost:{}/pets/4", port).as_str())
+        .set("OVP-Correlation-Id", "runs")
+        .call()?;
+
+    let stop_inactive =
+        ureq::post(format!("http://localhost:{}/_ovp/runs/smoke/stop", port).as_str())
+            .call()
+            .or_any_status()?;
+    assert_eq!(stop_inactive.status(), 404);
+    let unknown_run =
+        ureq::get(format!("http://localhost:{}/_ovp/runs/manual/junit", port).as_str())
+            .call()
+            .or_any_status()?;
+    assert_eq!(unknown_run.status(), 404);
+    let runs: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/runs", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    assert_eq!(
+        runs,
+        serde_json::json!([
+            {"name": "smoke", "active": false, "testcases": 1},
+            {"name": "regression", "active": false, "testcases": 2}
+        ])
+    );
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/runs/regression/junit", port).as_str())
+        .call()?;
+    let xml = junit.into_string()?;
+    mock.assert_hits(4);
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn tenants() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "tenants")
+        .set("OVP-Tenant", "adoptions")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "tenants")
+        .set("OVP-Tenant", "grooming")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/3", port).as_str())
+        .set("OVP-Correlation-Id", "tenants")
+        .set("OVP-Tenant", "grooming")
+        .call()?;
+    // This request doesn't belong to a tenant
+    ureq::get(format!("http://localhost:{}/pets/4", port).as_str())
+        .set("OVP-Correlation-Id", "tenants")
+        .call()?;
+
+    let tenants: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/tenants", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    assert_eq!(
+        tenants,
+        serde_json::json!([
+            {"name": "adoptions", "testcases": 1},
+            {"name": "grooming", "testcases": 2}
+        ])
+    );
+    let tenant_junit =
+        ureq::get(format!("http://localhost:{}/_ovp/tenants/grooming/junit", port).as_str())
+            .call()?
+            .into_string()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert_hits(4);
+
+    assert_junit_snapshot!(tenant_junit);
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn record_traffic() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let get_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let post_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201);
+    });
+    let har = std::env::temp_dir().join(format!("ovp-record-{}.har", std::process::id()));
+    let port = free_port();
+    let proxy_handle =
+        ValidatorProxyServerHandle::record(har.to_str().unwrap(), &mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1?fields=name", port).as_str())
+        .set("OVP-Correlation-Id", "record_get")
+        .call()?;
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "record_post")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 2, "name": "cat"}"#)?;
+    get_mock.assert();
+    post_mock.assert();
+    // Entries are written as they are recorded, so killing the proxy leaves a complete HAR file
+    drop(proxy_handle);
+
+    let mut recording: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&har)?)?;
+    std::fs::remove_file(har)?;
+    recording["log"]["creator"]["version"] = "[version]".into();
+    for entry in recording["log"]["entries"].as_array_mut().unwrap() {
+        entry["startedDateTime"] = "[startedDateTime]".into();
+        entry["time"] = "[time]".into();
+        entry["timings"]["wait"] = "[time]".into();
+        for header in entry["response"]["headers"].as_array_mut().unwrap() {
+            if header["name"] == "date" {
+                header["value"] = "[date]".into();
+            }
+        }
+    }
+    let recording = serde_json::to_string_pretty(&recording)?;
+    insta::with_settings!({filters => vec![
+        (r"http://(localhost|127\.0\.0\.1):\d+", "http://[host]"),
+        (r"(localhost|127\.0\.0\.1):\d+", "[host]"),
+        (r"ureq/\d+\.\d+\.\d+", "ureq/[version]"),
+    ]}, {
+        insta::assert_snapshot!(recording);
+    });
+    Ok(())
+}
+
+#[test]
+fn replay_traffic() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let get_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/pets/1")
+            .query_param("fields", "name")
+            .header("OVP-Correlation-Id", "replay_valid");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    // The correlation ID of the second entry was only recorded in _correlationId
+    let post_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST)
+            .path("/pets")
+            .header("OVP-Correlation-Id", "replay_stripped")
+            .body(r#"{"id": 2, "name": "cat"}"#);
+        then.status(500);
+    });
+    let output = std::env::temp_dir().join(format!("ovp-replay-{}.xml", std::process::id()));
+    let status = Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args([
+            "replay",
+            "tests/replay.har",
+            "tests/petstore.yaml",
+            &mock_server.url(""),
+            "--port",
+            &free_port().to_string(),
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .status()?;
+    get_mock.assert();
+    post_mock.assert();
+    assert_eq!(status.code(), Some(1));
+
+    let xml = std::fs::read_to_string(&output)?;
+    std::fs::remove_file(output)?;
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn replay_stdout() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.any_request();
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    // Logs and status messages go to stderr, so the report printed to stdout can be redirected to
+    // a file
+    let output = Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args(["replay", "tests/replay.har", "tests/petstore.yaml"])
+        .arg(mock_server.url(""))
+        .args(["--port", &free_port().to_string()])
+        .env_remove("RUST_LOG")
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let document = roxmltree::Document::parse(&stdout)?;
+    assert_eq!(document.root_element().tag_name().name(), "testsuites");
+    assert_eq!(
+        document
+            .descendants()
+            .filter(|node| node.has_tag_name("testcase"))
+            .count(),
+        2
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Starting proxy server"));
+    Ok(())
+}
+
+#[test]
+fn log_level() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    mock_server.mock(|when, then| {
+        when.any_request();
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let replay = |args: &[&str], rust_log: Option<&str>| -> Result<String, std::io::Error> {
+        let mut cmd = Command::new(get_cargo_bin("openapi-validator-proxy"));
+        cmd.args(["replay", "tests/replay.har", "tests/petstore.yaml"])
+            .arg(mock_server.url(""))
+            .args(["--port", &free_port().to_string()])
+            .args(args)
+            .env_remove("RUST_LOG");
+        if let Some(rust_log) = rust_log {
+            cmd.env("RUST_LOG", rust_log);
+        }
+        let output = cmd.output()?;
+        Ok(String::from_utf8_lossy(&output.stderr).to_string())
+    };
+
+    // Requests are logged at info level, which is the default
+    assert!(replay(&[], None)?.contains("Handling request"));
+    assert!(!replay(&["-q"], None)?.contains("Handling request"));
+    assert!(!replay(&[], Some("warn"))?.contains("Handling request"));
+    assert!(replay(&["-v"], Some("warn"))?.contains("Handling request"));
+    assert!(!replay(
+        &["--log-level", "openapi_validator_proxy=warn"],
+        Some("info")
+    )?
+    .contains("Handling request"));
+    Ok(())
+}
+
+#[test]
+fn invalid_log_level() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "check",
+            "tests/petstore_lint.yaml",
+            "--log-level",
+            "info,openapi_validator_proxy=loud"
+        ])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn junit_output_on_shutdown() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let junit = std::env::temp_dir().join(format!("ovp-junit-{}.xml", std::process::id()));
+    let port = free_port();
+    let mut proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--junit-output", junit.to_str().unwrap()],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "junit_output_on_shutdown")
+        .call()?;
+    mock.assert();
+    assert!(proxy_handle.terminate().success());
+
+    let xml = std::fs::read_to_string(&junit)?;
+    std::fs::remove_file(junit)?;
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn junit_group_by_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let junit = std::env::temp_dir().join(format!("ovp-junit-group-by-{}.xml", std::process::id()));
+    let port = free_port();
+    let mut proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--junit-output",
+            junit.to_str().unwrap(),
+            "--junit-group-by",
+            "path",
+        ],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "junit_group_by_path")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/missing_pets_schema", port).as_str())
+        .set("OVP-Correlation-Id", "junit_group_by_path")
+        .call()?;
+    // This path is not in the spec so it is grouped by the path that was requested
+    ureq::get(format!("http://localhost:{}/toys/1", port).as_str())
+        .set("OVP-Correlation-Id", "junit_group_by_path")
+        .call()?;
+    let grouped = ureq::get(format!("http://localhost:{}/_ovp/junit?groupBy=path", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert_hits(3);
+    assert!(proxy_handle.terminate().success());
+
+    let xml = std::fs::read_to_string(&junit)?;
+    std::fs::remove_file(junit)?;
+    assert_eq!(xml, grouped);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn junit_merge_correlated() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let pet_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let create_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201)
+            .header("Content-Type", "application/json")
+            .body(r#"{"id": "2", "name": "cat"}"#);
+    });
+    let junit = std::env::temp_dir().join(format!(
+        "ovp-junit-merge-correlated-{}.xml",
+        std::process::id()
+    ));
+    let port = free_port();
+    let mut proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--junit-output",
+            junit.to_str().unwrap(),
+            "--junit-merge-correlated",
+        ],
+    );
+
+    // The scenario's requests are merged into one testcase with a step for each request
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "adopt-a-cat")
+        .call()?;
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "adopt-a-cat")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 2, "name": "cat"}"#)?;
+    // A correlation ID with a single request keeps its testcase as it is
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "show-a-pet")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "adopt-a-cat")
+        .call()?;
+    let merged =
+        ureq::get(format!("http://localhost:{}/_ovp/junit?mergeCorrelated=true", port).as_str())
+            .call()?
+            .into_string()?;
+    pet_mock.assert_hits(3);
+    create_mock.assert();
+    assert!(proxy_handle.terminate().success());
+
+    let xml = std::fs::read_to_string(&junit)?;
+    std::fs::remove_file(junit)?;
+    assert_eq!(xml, merged);
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn xunit_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": "cat"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "xunit_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "xunit_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "xunit_report")
+        .set("OVP-Skip-Validation", "true")
+        .call()?;
+    let xunit = ureq::get(format!("http://localhost:{}/_ovp/xunit", port).as_str()).call()?;
+    assert_eq!(xunit.content_type(), "application/xml");
+    let xml = xunit.into_string()?;
+    valid_mock.assert_hits(2);
+    invalid_mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn nunit_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": "cat"}));
+    });
+    let report = std::env::temp_dir().join(format!("ovp-nunit-{}.xml", std::process::id()));
+    let port = free_port();
+    let mut proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &[
+            "--junit-output",
+            report.to_str().unwrap(),
+            "--report-format",
+            "nunit",
+        ],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "nunit_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "nunit_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "nunit_report")
+        .set("OVP-Skip-Validation", "true")
+        .call()?;
+    let nunit = ureq::get(format!("http://localhost:{}/_ovp/nunit", port).as_str()).call()?;
+    assert_eq!(nunit.content_type(), "application/xml");
+    let served = nunit.into_string()?;
+    valid_mock.assert_hits(2);
+    invalid_mock.assert();
+    assert!(proxy_handle.terminate().success());
+
+    let xml = std::fs::read_to_string(&report)?;
+    std::fs::remove_file(report)?;
+    // The end time is when the report was rendered
+    let without_end_time = |xml: &str| {
+        xml.split_once(r#" end-time=""#)
+            .unwrap()
+            .1
+            .split_once('"')
+            .unwrap()
+            .1
+            .to_string()
+    };
+    assert_eq!(without_end_time(&xml), without_end_time(&served));
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn ctrf_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": "cat"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "ctrf_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "ctrf_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "ctrf_report")
+        .set("OVP-Skip-Validation", "true")
+        .call()?;
+    let report: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/ctrf", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    valid_mock.assert_hits(2);
+    invalid_mock.assert();
+
+    let summary = &report["results"]["summary"];
+    assert!(summary["start"].as_u64().unwrap() <= summary["stop"].as_u64().unwrap());
+    let report = serde_json::to_string_pretty(&report)?;
+    insta::with_settings!({filters => vec![
+        (r#""(start|stop|duration)": \d+"#, r#""$1": "[time]""#),
+        (r#""(\w+Ms)": "\d+\.\d+""#, r#""$1": "[time]""#),
+        (r"http://(localhost|127\.0\.0\.1):\d+", "http://[host]"),
+        (r"localhost:\d+", "[host]"),
+        (r"ureq/\d+\.\d+\.\d+", "ureq/[version]"),
+        (r#""version": "[^"]+""#, r#""version": "[version]""#),
+    ]}, {
+        insta::assert_snapshot!(report);
+    });
+    Ok(())
+}
+
+#[test]
+fn failures_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": 3}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "failures_csv")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "failures_csv")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/nowhere", port).as_str())
+        .set("OVP-Correlation-Id", "failures,csv")
+        .call()
+        .ok();
+    let response =
+        ureq::get(format!("http://localhost:{}/_ovp/failures.csv", port).as_str()).call()?;
+    assert_eq!(response.content_type(), "text/csv");
+    let csv = response.into_string()?;
+    let filtered = ureq::get(
+        format!(
+            "http://localhost:{}/_ovp/failures.csv?correlationIdPrefix=failures_",
+            port
+        )
+        .as_str(),
+    )
+    .call()?
+    .into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert!(csv.ends_with("\r\n"));
+    assert!(csv.starts_with(
+        "correlationId,operationId,method,statusCode,failureType,jsonPointer,message\r\n"
+    ));
+    assert!(!filtered.contains("failures,csv"));
+    insta::assert_snapshot!(csv.replace("\r\n", "\n"));
+    Ok(())
+}
+
+#[test]
+fn summary() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": 3}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "summary")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "summary")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/nowhere", port).as_str())
+        .set("OVP-Correlation-Id", "other")
+        .call()
+        .ok();
+    let summary: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/summary", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    let filtered: serde_json::Value = serde_json::from_str(
+        &ureq::get(
+            format!(
+                "http://localhost:{}/_ovp/summary?correlationIdPrefix=summary",
+                port
+            )
+            .as_str(),
+        )
+        .call()?
+        .into_string()?,
+    )?;
+    valid_mock.assert();
+    invalid_mock.assert();
+
+    assert_eq!(filtered["testcases"], 2);
+    assert_eq!(filtered["failureTypes"].get("PathNotFound"), None);
+    insta::assert_snapshot!(serde_json::to_string_pretty(&summary)?);
+    Ok(())
+}
+
+#[test]
+fn serve_report() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/2");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "2", "name": "cat"}));
+    });
+    let results = std::env::temp_dir().join(format!("ovp-results-{}.jsonl", std::process::id()));
+    let port = free_port();
+    let proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--results", results.to_str().unwrap()],
+    );
+
+    ureq::post(format!("http://localhost:{}/_ovp/runs/smoke/start", port).as_str()).call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "serve_report")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/2", port).as_str())
+        .set("OVP-Correlation-Id", "serve_report")
+        .call()?;
+    let live_junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert();
+    // The results are saved as they are recorded, so killing the proxy doesn't lose them
+    drop(proxy_handle);
+
+    let port = free_port();
+    let _report_handle = ValidatorProxyServerHandle::serve_report(results.to_str().unwrap(), port);
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    assert_eq!(junit, live_junit);
+    let run_junit = ureq::get(format!("http://localhost:{}/_ovp/runs/smoke/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    assert_eq!(run_junit, live_junit);
+    let runs: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/runs", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    assert_eq!(
+        runs,
+        serde_json::json!([{"name": "smoke", "active": false, "testcases": 2}])
+    );
+    let saved: serde_json::Value = serde_json::from_str(
+        &ureq::get(format!("http://localhost:{}/_ovp/results", port).as_str())
+            .call()?
+            .into_string()?,
+    )?;
+    std::fs::remove_file(results)?;
+
+    let saved_testcase = serde_json::to_string_pretty(&saved[1])?;
+    insta::with_settings!({filters => vec![
+        (r#""time": "\d+\.\d+""#, r#""time": "[time]""#),
+        (r#""recordedAt": "[^"]+""#, r#""recordedAt": "[recordedAt]""#),
+        (r#"("name": "\w+Ms",\s+"value": )"\d+\.\d+""#, r#"$1"[time]""#),
+        (r"http://(localhost|127\.0\.0\.1):\d+", "http://[host]"),
+        (r"localhost:\d+", "[host]"),
+        (r"ureq/\d+\.\d+\.\d+", "ureq/[version]"),
+    ]}, {
+        insta::assert_snapshot!(saved_testcase);
+    });
+    Ok(())
+}
+
+#[test]
+fn failure_artifacts() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201)
+            .header("Content-Type", "application/json")
+            .body(r#"{"id": "2", "name": "cat"}"#);
+    });
+    let artifacts = std::env::temp_dir().join(format!("ovp-artifacts-{}", std::process::id()));
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--artifacts-dir", artifacts.to_str().unwrap()],
+    );
+
+    // Passing testcases don't write artifacts
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "failure/artifacts")
+        .call()?;
+    for name in ["cat", "kitten"] {
+        ureq::post(format!("http://localhost:{}/pets", port).as_str())
+            .set("OVP-Correlation-Id", "failure/artifacts")
+            .set("Content-Type", "application/json")
+            .send_string(&format!(r#"{{"id": 2, "name": "{}"}}"#, name))?;
+    }
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert_hits(2);
+
+    let mut files = std::fs::read_dir(&artifacts)?
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+        .collect::<Result<Vec<String>, std::io::Error>>()?;
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "failure_artifacts-2.request",
+            "failure_artifacts-2.response",
+            "failure_artifacts.request",
+            "failure_artifacts.response"
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(artifacts.join("failure_artifacts-2.request"))?,
+        r#"{"id": 2, "name": "kitten"}"#
+    );
+    assert_eq!(
+        std::fs::read_to_string(artifacts.join("failure_artifacts.response"))?,
+        r#"{"id": "2", "name": "cat"}"#
+    );
+    std::fs::remove_dir_all(&artifacts)?;
+
+    let junit = junit.replace(artifacts.to_str().unwrap(), "[artifacts]");
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn failure_evidence() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let valid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let invalid_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(201)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(r#"{"id": "2", "name": "cat", "tag": "<indoor> & <outdoor>"}"#);
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/petstore.yaml",
+        &["--evidence-max-bytes", "40"],
+    );
+
+    // Passing testcases don't embed evidence
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "failure/evidence")
+        .call()?;
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "failure/evidence")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 2, "name": "cat"}"#)?;
+    // Bodies with other content types are left out
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "failure/evidence")
+        .set("Content-Type", "application/octet-stream")
+        .send_bytes(&[0, 1, 2])?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    valid_mock.assert();
+    invalid_mock.assert_hits(2);
+
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn report_tags() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET);
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": 1, "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "report_tags")
+        .call()?;
+    // This path is not in the spec so the testcase has no tags
+    ureq::get(format!("http://localhost:{}/toys/1", port).as_str())
+        .set("OVP-Correlation-Id", "report_tags")
+        .call()?;
+    let filtered = ureq::get(format!("http://localhost:{}/_ovp/junit?tag=pets", port).as_str())
+        .call()?
+        .into_string()?;
+    let grouped = ureq::get(format!("http://localhost:{}/_ovp/junit?groupBy=tag", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert_hits(2);
+
+    assert_junit_snapshot!(filtered);
+    assert_junit_snapshot!(grouped);
+    Ok(())
+}
+
+#[test]
+fn reproduction_redacts_secrets() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::POST).path("/pets");
+        then.status(418).body("I'm a teapot");
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::new(&mock_server.url(""), port);
+
+    ureq::post(format!("http://localhost:{}/pets", port).as_str())
+        .set("OVP-Correlation-Id", "reproduction_redacts_secrets")
+        .set("Authorization", "Bearer hunter2")
+        .set("Content-Type", "application/json")
+        .send_string(r#"{"id": 1, "name": "O'Malley"}"#)
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn check_spec_lints() {
+    insta_cmd::assert_cmd_snapshot!(Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args(["check", "tests/petstore_lint.yaml"]));
+}
+
+#[test]
+fn check_spec_examples() {
+    insta_cmd::assert_cmd_snapshot!(Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args(["check", "tests/examples.yaml"]));
+}
+
+#[test]
+fn validate_spec_problems() {
+    insta_cmd::assert_cmd_snapshot!(Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args(["validate", "tests/validate.yaml"]));
+}
+
+#[test]
+fn validate_valid_spec() {
+    insta_cmd::assert_cmd_snapshot!(Command::new(get_cargo_bin("openapi-validator-proxy"))
+        .args(["validate", "tests/composition.yaml"]));
+}
+
+#[test]
+fn diff_breaking_changes() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "diff",
+            "tests/petstore.yaml",
+            "tests/petstore_breaking.yaml"
+        ])
+    );
+}
+
+#[test]
+fn unsupported_spec_version() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "proxy",
+            "tests/openapi_4.yaml",
+            "http://localhost:8080"
+        ])
+    );
+}
+
+#[test]
+fn invalid_spec() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "proxy",
+            "tests/invalid_spec.yaml",
+            "http://localhost:8080"
+        ])
+    );
+}
+
+#[test]
+fn swagger_2_0() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let list_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .header("x-next", "/pets?page=2")
+            .json_body(serde_json::json!([{"id": 1, "name": "dog"}]));
+    });
+    let show_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/swagger.yaml",
+        &[],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets?limit=10&tags=a&tags=b", port).as_str())
+        .set("OVP-Correlation-Id", "swagger_2_0")
+        .call()?;
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "swagger_2_0")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    list_mock.assert();
+    show_mock.assert();
+
+    assert_junit_snapshot!(junit);
+    Ok(())
+}
+
+#[test]
+fn remote_refs_not_allowed() {
+    insta_cmd::assert_cmd_snapshot!(
+        Command::new(get_cargo_bin("openapi-validator-proxy")).args([
+            "proxy",
+            "tests/remote_refs.yaml",
+            "http://localhost:8080"
+        ])
+    );
+}
+
+#[test]
+fn remote_refs() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let library_mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET)
+            .path("/schemas/pets.yaml");
+        then.status(200)
+            .header("Content-Type", "application/yaml")
+            .body(
+                r##"
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        name:
+          type: string
+        tag:
+          $ref: "#/components/schemas/Tag"
+    Tag:
+      type: object
+      properties:
+        label:
+          type: string
+"##,
+            );
+    });
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"name": "dog", "tag": {"label": 1}}));
+    });
+    let spec = std::env::temp_dir().join(format!("ovp-remote-refs-{}.yaml", std::process::id()));
+    std::fs::write(
+        &spec,
+        std::fs::read_to_string("tests/remote_refs.yaml")?.replace(
+            "https://schemas.example.com/pets.yaml",
+            &mock_server.url("/schemas/pets.yaml"),
+        ),
+    )?;
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        spec.to_str().unwrap(),
+        &["--allow-remote-refs"],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "remote_refs")
+        .call()
+        .or_any_status()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str()).call()?;
+    let xml = junit.into_string()?;
+    library_mock.assert();
+    mock.assert();
+    std::fs::remove_file(spec)?;
+
+    assert_junit_snapshot!(xml);
+    Ok(())
+}
+
+#[test]
+fn forced_unsupported_spec_version() -> Result<(), Box<dyn std::error::Error>> {
+    let mock_server = MockServer::start();
+    let mock = mock_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/pets/1");
+        then.status(200)
+            .header("Content-Type", "application/json")
+            .json_body(serde_json::json!({"id": "1", "name": "dog"}));
+    });
+    let port = free_port();
+    let _proxy_handle = ValidatorProxyServerHandle::with_args(
+        &mock_server.url(""),
+        port,
+        "tests/openapi_4.yaml",
+        &["--force"],
+    );
+
+    ureq::get(format!("http://localhost:{}/pets/1", port).as_str())
+        .set("OVP-Correlation-Id", "forced_unsupported_spec_version")
+        .call()?;
+    let junit = ureq::get(format!("http://localhost:{}/_ovp/junit", port).as_str())
+        .call()?
+        .into_string()?;
+    mock.assert();
+
+    assert_junit_snapshot!(junit);
     Ok(())
 }